@@ -0,0 +1,223 @@
+//! Import mails into a Postsack database from the command line.
+//!
+//! ``` sh
+//! postsack-import mbox ~/Mails/archive --database archive.sqlite --sender me@example.com
+//! ```
+//!
+//! Additional sources are imported into the same database after the first one:
+//!
+//! ``` sh
+//! postsack-import apple ~/Library/Mail --name old --source takeout:mbox:~/Takeout
+//! ```
+//!
+//! Only mails from 2015 onward, without the Spam, Trash and Drafts folders:
+//!
+//! ``` sh
+//! postsack-import maildir ~/Maildir --from 2015-01-01 --exclude Spam,Trash,Drafts
+//! ```
+use ps_core::eyre::{bail, eyre, Result};
+use ps_core::message_adapter::{format_duration, Adapter};
+use ps_core::{Config, DatabaseLike, FormatType, ImportFilter, Source, TagSource};
+use ps_database::Database;
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const USAGE: &str =
+    "Usage: postsack-import <format> <folder> [--database <path>] [--sender <email>]...
+                       [--name <name>] [--source <name>:<format>:<folder>]...
+                       [--include <folders>]... [--exclude <folders>]...
+                       [--from <YYYY-MM-DD>] [--until <YYYY-MM-DD>] [--skip-tag <tags>]...
+                       [--notmuch-dump <file> | --notmuch <database> | --mu <muhome>]
+Formats: apple, gmailvault, mbox, maildir, mh or auto to detect the format
+Folders and tags are separated by comma and can contain wildcards, e.g. Spam,Trash*
+The senders and filters apply to all sources, the notmuch or mu tags to the first Maildir";
+
+struct Arguments {
+    format: FormatType,
+    folder: PathBuf,
+    database: Option<PathBuf>,
+    senders: Vec<String>,
+    name: Option<String>,
+    /// The name, format and folder of each additional source
+    sources: Vec<(String, FormatType, PathBuf)>,
+    filter: ImportFilter,
+    tag_source: Option<TagSource>,
+}
+
+fn parse_format(format: &str) -> Result<FormatType> {
+    FormatType::all_cases()
+        .find(|f| String::from(*f) == format)
+        .ok_or_else(|| eyre!("Unknown format {}\n{}", format, USAGE))
+}
+
+/// Use the most likely format of `folder`
+fn detect_format(folder: &Path) -> Result<FormatType> {
+    let candidates = ps_importer::probe_format(folder)?;
+    let candidate = candidates
+        .first()
+        .ok_or_else(|| eyre!("Could not detect the format of {}", folder.display()))?;
+    let format = candidate
+        .supported()
+        .ok_or_else(|| eyre!("{} can't be imported", candidate.format.name()))?;
+    eprintln!(
+        "Detected {} ({:.0}%): {}",
+        format.name(),
+        candidate.confidence * 100.0,
+        candidate.reason
+    );
+    Ok(format)
+}
+
+fn parse_source(source: &str) -> Result<(String, FormatType, PathBuf)> {
+    let mut parts = source.splitn(3, ':');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(name), Some(format), Some(folder)) if !name.is_empty() => {
+            // The shell doesn't expand a `~` after `name:format:`
+            let folder = PathBuf::from(shellexpand::tilde(folder).as_ref());
+            let format = match format {
+                "auto" => detect_format(&folder)?,
+                _ => parse_format(format)?,
+            };
+            Ok((name.to_string(), format, folder))
+        }
+        _ => bail!("Invalid source {}\n{}", source, USAGE),
+    }
+}
+
+fn parse_arguments() -> Result<Arguments> {
+    let mut arguments = std::env::args().skip(1);
+    let format = arguments.next().ok_or_else(|| eyre!(USAGE))?;
+    let folder = PathBuf::from(arguments.next().ok_or_else(|| eyre!(USAGE))?);
+    let format = match format.as_str() {
+        "auto" => detect_format(&folder)?,
+        _ => parse_format(&format)?,
+    };
+    let mut database = None;
+    let mut senders = Vec::new();
+    let mut name = None;
+    let mut sources = Vec::new();
+    let mut filter = ImportFilter::default();
+    let mut tag_source = None;
+    while let Some(argument) = arguments.next() {
+        let value = arguments
+            .next()
+            .ok_or_else(|| eyre!("Missing value for {}\n{}", argument, USAGE))?;
+        match argument.as_str() {
+            "--database" => database = Some(PathBuf::from(value)),
+            "--sender" => senders.push(value),
+            "--name" => name = Some(value),
+            "--source" => sources.push(parse_source(&value)?),
+            "--include" => filter
+                .include_folders
+                .extend(ImportFilter::parse_list(&value)?),
+            "--exclude" => filter
+                .exclude_folders
+                .extend(ImportFilter::parse_list(&value)?),
+            "--from" => filter.from_date = ImportFilter::parse_date(&value)?,
+            "--until" => filter.until_date = ImportFilter::parse_date(&value)?,
+            "--skip-tag" => filter.skip_tags.extend(ImportFilter::parse_list(&value)?),
+            "--notmuch-dump" => tag_source = Some(TagSource::NotmuchDump(PathBuf::from(value))),
+            "--notmuch" => tag_source = Some(TagSource::Notmuch(PathBuf::from(value))),
+            "--mu" => tag_source = Some(TagSource::Mu(PathBuf::from(value))),
+            _ => bail!("Unknown argument {}\n{}", argument, USAGE),
+        }
+    }
+    Ok(Arguments {
+        format,
+        folder,
+        database,
+        senders,
+        name,
+        sources,
+        filter,
+        tag_source,
+    })
+}
+
+fn main() -> Result<()> {
+    let arguments = parse_arguments()?;
+    let mut config = Config::new(
+        arguments.database.as_ref(),
+        &arguments.folder,
+        arguments.senders.clone(),
+        arguments.format,
+    )?;
+    if let Some(name) = arguments.name {
+        config.source_name = name;
+    }
+    config.filter = arguments.filter;
+    config.tag_source = arguments.tag_source;
+    for (name, format, folder) in arguments.sources {
+        let source = Source::new(name, format, folder, arguments.senders.clone());
+        config.sources.push(source);
+    }
+    config.check_source_names()?;
+    let database = Database::new(&config.database_path)?;
+    let adapter = Adapter::new();
+    let handle = adapter.process(database, ps_importer::sources_importer(config.clone()))?;
+
+    let mut stderr = std::io::stderr();
+    loop {
+        std::thread::sleep(Duration::from_millis(500));
+        if let Some(error) = adapter.error()? {
+            eprintln!();
+            return Err(error);
+        }
+        let state = adapter.finished()?;
+        if state.done {
+            break;
+        }
+        let statistics = adapter.statistics()?;
+        let (read, write) = (adapter.read_count()?, adapter.write_count()?);
+        let mut line = match statistics.phase {
+            Some(phase) => format!(
+                "{}: read {}/{}, parsed {}/{}",
+                phase.name(),
+                read.count,
+                read.total,
+                write.count,
+                write.total
+            ),
+            None => "Starting".to_string(),
+        };
+        for detail in [statistics.throughput_label(), statistics.eta_label()]
+            .iter()
+            .flatten()
+        {
+            line.push_str(" | ");
+            line.push_str(detail);
+        }
+        // Clear the rest of the line, the previous output might have been longer
+        write!(stderr, "\r{}\x1b[K", line)?;
+        stderr.flush()?;
+    }
+    eprintln!();
+
+    handle
+        .join()
+        .map_err(|e| eyre!("Import failed: {:?}", e))??;
+
+    let state = adapter.finished()?;
+    if state.cancelled {
+        bail!("The import was cancelled");
+    }
+    let statistics = adapter.statistics()?;
+    let database = Database::new(&config.database_path)?;
+    println!(
+        "Imported {} mails into {} in {}",
+        database.total_mails()?,
+        config.database_path.display(),
+        format_duration(statistics.elapsed())
+    );
+    println!("{}", statistics.phases_label());
+    if let Some(throughput) = statistics.throughput_label() {
+        println!("{}", throughput);
+    }
+    let issues = database.import_errors()?;
+    if !issues.is_empty() {
+        println!("{} mails could not be imported", issues.len());
+    }
+    Ok(())
+}
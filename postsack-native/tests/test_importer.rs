@@ -4,8 +4,10 @@ use ps_importer;
 
 #[cfg(test)]
 mod tests {
-    use ps_core::{Config, Field, Query, QueryResult};
+    use ps_core::{Config, Field, MailLocation, Message, Query, QueryResult, TimezoneMode};
+    use std::path::Path;
     use std::sync::Once;
+    use tempfile::TempDir;
 
     use super::*;
 
@@ -20,6 +22,64 @@ mod tests {
         });
     }
 
+    /// An email folder in a temporary directory and the database it was
+    /// imported into. The directory is removed when the fixture is dropped,
+    /// even if the test panics.
+    struct Fixture {
+        database: Database,
+        config: Config,
+        /// The messages that were sent during the import
+        messages: Vec<Message>,
+        _directory: TempDir,
+    }
+
+    /// Write the `files`, paths relative to the email folder and their contents,
+    /// and import them as `format`
+    fn import_fixture<P: AsRef<Path>, C: AsRef<[u8]>>(
+        format: FormatType,
+        files: &[(P, C)],
+    ) -> Fixture {
+        import_fixture_with(format, files, |_| ())
+    }
+
+    /// Like `import_fixture`, but `configure` can change the config before the import
+    fn import_fixture_with<P: AsRef<Path>, C: AsRef<[u8]>>(
+        format: FormatType,
+        files: &[(P, C)],
+        configure: impl FnOnce(&mut Config),
+    ) -> Fixture {
+        initialize();
+        let directory = tempfile::tempdir().expect("Temporary directory");
+        let folder = directory.path().join("mails");
+        // A Maildir needs all of its folders, even if they're empty
+        if format == FormatType::Maildir {
+            for sub in ["cur", "new", "tmp"] {
+                std::fs::create_dir_all(folder.join(sub)).unwrap();
+            }
+        }
+        for (path, content) in files {
+            let path = folder.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+
+        let database_path = directory.path().join("postsack.sqlite");
+        let mut config =
+            Config::new(Some(database_path), folder, Vec::new(), format).expect("Config");
+        configure(&mut config);
+        let importer = ps_importer::sources_importer(config.clone());
+        let database = Database::new(&config.database_path).unwrap();
+        let (receiver, handle) = importer.import(database).unwrap();
+        handle.join().expect("").expect("");
+
+        Fixture {
+            database: Database::new(&config.database_path).unwrap(),
+            config,
+            messages: receiver.try_iter().collect(),
+            _directory: directory,
+        }
+    }
+
     #[test]
     /// Test that the mbox importer works
     fn test_mbox_import() {
@@ -35,12 +95,13 @@ mod tests {
         let db = Database::new(&config.database_path).unwrap();
 
         let total_mails = db.total_mails().expect("Expected total mails");
-        assert_eq!(total_mails, 141);
+        assert_eq!(total_mails, 144);
 
         let mails = db.query(&Query::Normal {
             fields: vec![Field::Subject],
             filters: Vec::new(),
-            range: 0..141,
+            range: 0..144,
+            timezone: TimezoneMode::Utc,
         });
         let mails = mails.expect("Expected Mails");
 
@@ -55,6 +116,52 @@ mod tests {
         assert!(subjects.contains(&"check bogus body header (from)".into()));
     }
 
+    #[test]
+    /// Test that single mails can be read again from an mbox
+    fn test_mbox_locations() {
+        initialize();
+        let path = "tests/resources/mbox";
+        let config =
+            Config::new(None, path, vec!["".to_string()], FormatType::Mbox).expect("Config");
+        let importer = ps_importer::mbox_importer(config.clone());
+        let database = Database::new(&config.database_path).unwrap();
+        let (_receiver, handle) = importer.import(database).unwrap();
+        handle.join().expect("").expect("");
+
+        let db = Database::new(&config.database_path).unwrap();
+        let mails = db
+            .query(&Query::Normal {
+                fields: vec![
+                    Field::Path,
+                    Field::Subject,
+                    Field::MboxOffset,
+                    Field::MboxLength,
+                ],
+                filters: Vec::new(),
+                range: 0..10,
+                timezone: TimezoneMode::Utc,
+            })
+            .expect("Expected Mails");
+        assert_eq!(mails.len(), 10);
+        for mail in mails {
+            let row = match mail {
+                QueryResult::Normal(row) => row,
+                _ => panic!(),
+            };
+            let location = MailLocation::from_row(&row).expect("Location");
+            assert!(location.mbox_location.is_some());
+            let preview =
+                ps_importer::load_preview(&FormatType::Mbox, &location).expect("Expected Preview");
+            let subject = preview
+                .headers
+                .iter()
+                .find(|(name, _)| name == "Subject")
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default();
+            assert_eq!(subject, row[&Field::Subject].to_string());
+        }
+    }
+
     /// Test that the AppleMail importer works
     #[test]
     /// FIXME: On windows we have an issue with the `\n` / `\r\n` line endings it seems
@@ -77,6 +184,7 @@ mod tests {
             fields: vec![Field::Subject],
             filters: Vec::new(),
             range: 0..10,
+            timezone: TimezoneMode::Utc,
         });
         let mails = mails.expect("Expected Mails");
         assert_eq!(mails.len(), 4);
@@ -100,4 +208,1273 @@ mod tests {
         let total_mails = db.total_mails().expect("Expected total mails");
         assert_eq!(total_mails, 6);
     }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    /// Test that an imported mail can be loaded again for the preview
+    fn test_maildir_preview() {
+        initialize();
+        let path = "tests/resources/maildir";
+        let config =
+            Config::new(None, path, vec!["".to_string()], FormatType::Maildir).expect("Config");
+        let importer = ps_importer::maildir_importer(config.clone());
+        let database = Database::new(&config.database_path).unwrap();
+        let (_receiver, handle) = importer.import(database).unwrap();
+        handle.join().expect("").expect("");
+
+        let db = Database::new(&config.database_path).unwrap();
+        let mails = db
+            .query(&Query::Normal {
+                fields: vec![Field::Path],
+                filters: Vec::new(),
+                range: 0..1,
+                timezone: TimezoneMode::Utc,
+            })
+            .expect("Expected Mails");
+        let location = match &mails[0] {
+            QueryResult::Normal(row) => MailLocation::from_row(row).expect("Location"),
+            _ => panic!(),
+        };
+        let preview =
+            ps_importer::load_preview(&FormatType::Maildir, &location).expect("Expected Preview");
+        assert!(preview.headers.iter().any(|(name, _)| name == "Subject"));
+        assert!(!preview.body.is_empty());
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    /// Test that exported mails can be imported again
+    fn test_mbox_export_to_maildir() {
+        initialize();
+        let path = "tests/resources/mbox";
+        let config =
+            Config::new(None, path, vec!["".to_string()], FormatType::Mbox).expect("Config");
+        let importer = ps_importer::mbox_importer(config.clone());
+        let database = Database::new(&config.database_path).unwrap();
+        let (_receiver, handle) = importer.import(database).unwrap();
+        handle.join().expect("").expect("");
+
+        let db = Database::new(&config.database_path).unwrap();
+        let rows: Vec<_> = db
+            .query(&Query::Normal {
+                fields: vec![
+                    Field::Path,
+                    Field::Timestamp,
+                    Field::MboxOffset,
+                    Field::MboxLength,
+                ],
+                filters: Vec::new(),
+                range: 0..144,
+                timezone: TimezoneMode::Utc,
+            })
+            .expect("Expected Mails")
+            .into_iter()
+            .map(|result| match result {
+                QueryResult::Normal(row) => row,
+                _ => panic!(),
+            })
+            .collect();
+
+        let export_folder = config.database_path.with_extension("export");
+        let exported = ps_importer::export_mails(
+            &config,
+            &rows,
+            ps_importer::MailExportFormat::Maildir,
+            &export_folder,
+        )
+        .expect("Expected Export");
+        assert_eq!(exported, 144);
+
+        let config = Config::new(
+            None,
+            &export_folder,
+            vec!["".to_string()],
+            FormatType::Maildir,
+        )
+        .expect("Config");
+        let importer = ps_importer::maildir_importer(config.clone());
+        let database = Database::new(&config.database_path).unwrap();
+        let (_receiver, handle) = importer.import(database).unwrap();
+        handle.join().expect("").expect("");
+        let db = Database::new(&config.database_path).unwrap();
+        assert_eq!(db.total_mails().expect("Expected total mails"), 144);
+    }
+
+    #[test]
+    /// Test that imported items can be exported as data
+    fn test_mbox_data_export() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+        use ps_core::export::{export_table, DataExportFormat, ExportTable, EXPORT_FIELDS};
+        initialize();
+        let path = "tests/resources/mbox";
+        let config =
+            Config::new(None, path, vec!["".to_string()], FormatType::Mbox).expect("Config");
+        let importer = ps_importer::mbox_importer(config.clone());
+        let database = Database::new(&config.database_path).unwrap();
+        let (_receiver, handle) = importer.import(database).unwrap();
+        handle.join().expect("").expect("");
+
+        let db = Database::new(&config.database_path).unwrap();
+        let rows: Vec<_> = db
+            .query(&Query::Normal {
+                fields: EXPORT_FIELDS.to_vec(),
+                filters: Vec::new(),
+                range: 0..144,
+                timezone: TimezoneMode::Utc,
+            })
+            .expect("Expected Mails")
+            .into_iter()
+            .map(|result| match result {
+                QueryResult::Normal(row) => row,
+                _ => panic!(),
+            })
+            .collect();
+        let table = ExportTable::from_rows(EXPORT_FIELDS, &rows);
+        assert_eq!(table.rows.len(), 144);
+
+        let csv_path = config.database_path.with_extension("csv");
+        std::fs::write(&csv_path, "An older export").unwrap();
+        export_table(&table, DataExportFormat::Csv, &csv_path).expect("Expected CSV");
+        let mut reader = csv::Reader::from_path(&csv_path).expect("Expected CSV");
+        assert_eq!(reader.headers().unwrap().len(), EXPORT_FIELDS.len());
+        assert_eq!(reader.records().count(), 144);
+
+        let jsonl_path = config.database_path.with_extension("jsonl");
+        export_table(&table, DataExportFormat::JsonLines, &jsonl_path).expect("Expected JSONL");
+        let content = std::fs::read_to_string(&jsonl_path).unwrap();
+        assert_eq!(content.lines().count(), 144);
+        for line in content.lines() {
+            let object: ps_core::Value = ps_core::serde_json::from_str(line).unwrap();
+            assert!(object["sender_domain"].is_string());
+            assert!(object["year"].is_u64());
+            // The keys are in the order of the columns
+            let positions: Vec<usize> = EXPORT_FIELDS
+                .iter()
+                .map(|field| line.find(&format!("\"{}\":", field.as_str())).unwrap())
+                .collect();
+            assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+
+        let parquet_path = config.database_path.with_extension("parquet");
+        export_table(&table, DataExportFormat::Parquet, &parquet_path).expect("Expected Parquet");
+        let reader = SerializedFileReader::new(std::fs::File::open(&parquet_path).unwrap())
+            .expect("Expected Parquet");
+        let metadata = reader.metadata().file_metadata();
+        assert_eq!(metadata.num_rows(), 144);
+        let columns: Vec<&str> = metadata
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|column| column.name())
+            .collect();
+        let names: Vec<&str> = EXPORT_FIELDS.iter().map(|field| field.as_str()).collect();
+        assert_eq!(columns, names);
+        let rows = reader.get_row_iter(None).expect("Expected Rows").count();
+        assert_eq!(rows, 144);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    /// Test that mails which can't be imported are recorded with their reason
+    fn test_maildir_import_errors() {
+        use ps_core::ImportErrorKind;
+        let valid = "From: A <a@example.com>\r\nDate: Thu, 12 Mar 2020 10:00:00 +0100\r\nSubject: Hi\r\n\r\nBody\r\n";
+        let no_date = "From: A <a@example.com>\r\nSubject: No date\r\n\r\nBody\r\n";
+        let no_sender =
+            "Date: Thu, 12 Mar 2020 10:00:00 +0100\r\nSubject: No sender\r\n\r\nBody\r\n";
+        let fixture = import_fixture(
+            FormatType::Maildir,
+            &[
+                ("cur/1.a:2,", valid),
+                ("cur/2.b:2,", no_date),
+                ("cur/3.c:2,", no_sender),
+            ],
+        );
+
+        let db = &fixture.database;
+        assert_eq!(db.total_mails().expect("Expected total mails"), 1);
+        let mut errors = db.import_errors().expect("Expected errors");
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].kind, ImportErrorKind::Date);
+        assert_eq!(errors[0].format, Some(FormatType::Maildir));
+        assert!(errors[0].path.ends_with("2.b:2,"));
+        assert!(errors[0].snippet.as_ref().unwrap().contains("No date"));
+        assert_eq!(errors[1].kind, ImportErrorKind::Address);
+    }
+
+    #[test]
+    /// Test that a cancelled import sends `Cancelled` and removes the database
+    fn test_cancelled_import() {
+        use ps_core::{CancellationToken, Message};
+        initialize();
+        let path = "tests/resources/mbox";
+        let config =
+            Config::new(None, path, vec!["".to_string()], FormatType::Mbox).expect("Config");
+        let importer = ps_importer::mbox_importer(config.clone());
+        let database = Database::new(&config.database_path).unwrap();
+        assert!(config.database_path.exists());
+
+        // Start paused, so the import blocks until it is cancelled
+        let token = CancellationToken::new();
+        token.pause();
+        let (receiver, handle) = importer
+            .cancellable_import(database, token.clone())
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        token.cancel();
+        handle.join().expect("").expect("");
+
+        assert!(receiver
+            .try_iter()
+            .any(|message| matches!(message, Message::Cancelled)));
+        assert!(!config.database_path.exists());
+    }
+
+    #[test]
+    /// Test that the importer reports all phases in order
+    fn test_import_phases() {
+        use ps_core::{ImportPhase, Message};
+        initialize();
+        let path = "tests/resources/mbox";
+        let config =
+            Config::new(None, path, vec!["".to_string()], FormatType::Mbox).expect("Config");
+        let importer = ps_importer::mbox_importer(config.clone());
+        let database = Database::new(&config.database_path).unwrap();
+        let (receiver, handle) = importer.import(database).unwrap();
+        handle.join().expect("").expect("");
+
+        let mut bytes = 0;
+        let phases: Vec<_> = receiver
+            .try_iter()
+            .filter_map(|message| match message {
+                Message::Phase(phase) => Some(phase),
+                Message::WriteOne { bytes: n, .. } => {
+                    bytes += n;
+                    None
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            phases,
+            vec![
+                ImportPhase::Discovering,
+                ImportPhase::Reading,
+                ImportPhase::Parsing,
+                ImportPhase::Writing,
+                ImportPhase::Indexing
+            ]
+        );
+        assert!(bytes > 0);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    /// Test that several sources are imported into one database
+    fn test_multiple_sources() {
+        use ps_core::Source;
+        initialize();
+        let mut config =
+            Config::new(None, "tests/resources/mbox", vec![], FormatType::Mbox).expect("Config");
+        config.source_name = "archive".to_string();
+        config.sources.push(Source::new(
+            "current",
+            FormatType::Maildir,
+            "tests/resources/maildir",
+            vec![],
+        ));
+        let importer = ps_importer::sources_importer(config.clone());
+        let database = Database::new(&config.database_path).unwrap();
+        let (_receiver, handle) = importer.import(database).unwrap();
+        handle.join().expect("").expect("");
+
+        let db = Database::new(&config.database_path).unwrap();
+        assert_eq!(db.total_mails().expect("Expected total mails"), 150);
+
+        let groups = db
+            .query(&Query::Grouped {
+                filters: Vec::new(),
+                group_by: Field::Source,
+                timezone: TimezoneMode::Utc,
+            })
+            .expect("Expected groups");
+        let mut counts: Vec<(String, usize)> = groups
+            .into_iter()
+            .map(|result| match result {
+                QueryResult::Grouped { count, value } => (value.to_string(), count),
+                _ => panic!(),
+            })
+            .collect();
+        counts.sort();
+        assert_eq!(
+            counts,
+            vec![("archive".to_string(), 144), ("current".to_string(), 6)]
+        );
+
+        // The sources are stored with the config
+        let stored = Database::config(&config.database_path).expect("Config");
+        assert_eq!(stored.source_name, "archive");
+        assert_eq!(stored.sources, config.sources);
+        assert_eq!(stored.all_sources().len(), 2);
+
+        // Every mail is read in the format of its source
+        let rows: Vec<_> = db
+            .query(&Query::Normal {
+                fields: vec![
+                    Field::Path,
+                    Field::MboxOffset,
+                    Field::MboxLength,
+                    Field::Source,
+                ],
+                filters: Vec::new(),
+                range: 0..150,
+                timezone: TimezoneMode::Utc,
+            })
+            .expect("Expected Mails")
+            .into_iter()
+            .map(|result| match result {
+                QueryResult::Normal(row) => row,
+                _ => panic!(),
+            })
+            .collect();
+        let export_path = config.database_path.with_extension("mbox");
+        let exported = ps_importer::export_mails(
+            &stored,
+            &rows,
+            ps_importer::MailExportFormat::Mbox,
+            &export_path,
+        )
+        .expect("Expected Export");
+        assert_eq!(exported, 150);
+        let _ = std::fs::remove_file(&export_path);
+
+        // A source name can only be used once, as the mails are identified by it
+        let mut duplicate = stored.clone();
+        duplicate.sources[0].name = "archive".to_string();
+        assert!(duplicate.check_source_names().is_err());
+        assert!(db.recompute_is_send(&duplicate).is_err());
+        let mut fields = stored.into_fields().unwrap();
+        fields.insert(
+            "source_name".to_string(),
+            ps_core::serde_json::Value::from("current"),
+        );
+        assert!(Config::from_fields(&config.database_path, fields).is_err());
+    }
+
+    #[test]
+    /// Test that a database of the first release is migrated,
+    /// and that a database of a newer release is refused
+    fn test_migration() {
+        use ps_core::{Filter, ImportErrorKind, ValueField};
+        initialize();
+        let directory = tempfile::tempdir().expect("Temporary directory");
+        let path = directory.path().join("old.sqlite");
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                r#"
+CREATE TABLE emails (
+  path TEXT NOT NULL, sender_domain TEXT NOT NULL, sender_local_part TEXT NOT NULL,
+  sender_name TEXT NOT NULL, year INTEGER NOT NULL, month INTEGER NOT NULL,
+  day INTEGER NOT NULL, timestamp INTEGER NOT NULL, subject TEXT NOT NULL,
+  to_count INTEGER NOT NULL, to_group TEXT NULL, to_name TEXT NULL, to_address TEXT NULL,
+  is_reply BOOL, is_send BOOL, meta_tags TEXT NULL, meta_is_seen BOOL NULL
+);
+CREATE TABLE errors (message TEXT NOT NULL);
+INSERT INTO emails VALUES ('1.eml', 'example.com', 'a', 'A', 2020, 3, 12, 1584007200,
+  'Hi', 0, NULL, NULL, NULL, 0, 0, 'Inbox:|:Work', 1);
+INSERT INTO emails VALUES ('2.eml', 'example.com', 'a', 'A', 2020, 3, 12, 1584007200,
+  'Hi', 0, NULL, NULL, NULL, 0, 0, 'Work', 1);
+INSERT INTO errors VALUES ('Invalid mail');
+"#,
+            )
+            .unwrap();
+        drop(connection);
+
+        let db = Database::new(&path).expect("Migrated database");
+        let count = |filter: Filter| {
+            let query = Query::Normal {
+                fields: vec![Field::Subject, Field::Hour, Field::SenderOrganization],
+                filters: vec![filter],
+                range: 0..10,
+                timezone: TimezoneMode::Utc,
+            };
+            db.query(&query).unwrap().len()
+        };
+        assert_eq!(
+            count(Filter::Like(ValueField::string(&Field::MetaTags, "Work"))),
+            2
+        );
+        assert_eq!(
+            count(Filter::Like(ValueField::string(&Field::MetaTags, "Inbox"))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Is(ValueField::string(
+                &Field::SenderOrganization,
+                "example.com"
+            ))),
+            2
+        );
+        let errors = db.import_errors().expect("Expected errors");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ImportErrorKind::Parse);
+        assert_eq!(errors[0].message, "Invalid mail");
+        drop(db);
+        // Opening it again doesn't migrate again
+        let db = Database::new(&path).expect("Database");
+        assert_eq!(db.total_mails().unwrap(), 2);
+        drop(db);
+
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection.pragma_update(None, "user_version", &99).unwrap();
+        drop(connection);
+        assert!(Database::new(&path).is_err());
+    }
+
+    #[test]
+    /// Test that the own addresses are detected and that `is_send`
+    /// can be recomputed without importing again
+    fn test_recompute_is_send() {
+        use ps_core::{Filter, ValueField};
+        initialize();
+        let path = "tests/resources/mbox";
+        let mut config =
+            Config::new(None, path, vec!["".to_string()], FormatType::Mbox).expect("Config");
+
+        // The first candidate is the most frequent sender in `SENT.mbox`
+        let candidates = ps_importer::detect_identities(&config).expect("Candidates");
+        assert_eq!(candidates[0].address, "a.u.thor@example.com");
+        assert!(candidates[0].sent_folder > 0);
+
+        let importer = ps_importer::mbox_importer(config.clone());
+        let database = Database::new(&config.database_path).unwrap();
+        let (_receiver, handle) = importer.import(database).unwrap();
+        handle.join().expect("").expect("");
+
+        let db = Database::new(&config.database_path).unwrap();
+        let send_mails = |db: &Database| {
+            db.query(&Query::Normal {
+                fields: vec![Field::SenderLocalPart],
+                filters: vec![Filter::Is(ValueField::bool(&Field::IsSend, true))],
+                range: 0..200,
+                timezone: TimezoneMode::Utc,
+            })
+            .expect("Expected Mails")
+            .len()
+        };
+        assert_eq!(send_mails(&db), 0);
+
+        config.sender_emails = vec![candidates[0].address.clone()].into_iter().collect();
+        let count = db.recompute_is_send(&config).expect("Recompute");
+        assert!(count > 0);
+        assert_eq!(send_mails(&db), count);
+
+        let stored = Database::config(&config.database_path).expect("Config");
+        assert_eq!(stored.sender_emails, config.sender_emails);
+    }
+
+    #[test]
+    /// Test that the format of a folder is detected
+    fn test_probe_format() {
+        use ps_importer::{probe_format, ProbedFormat};
+        let detected =
+            |path: &str| probe_format(std::path::Path::new(path)).expect("Candidates")[0].format;
+        assert_eq!(
+            detected("tests/resources/mbox"),
+            ProbedFormat::Supported(FormatType::Mbox)
+        );
+        assert_eq!(
+            detected("tests/resources/applemail"),
+            ProbedFormat::Supported(FormatType::AppleMail)
+        );
+        #[cfg(not(target_os = "windows"))]
+        assert_eq!(
+            detected("tests/resources/maildir"),
+            ProbedFormat::Supported(FormatType::Maildir)
+        );
+
+        let folder = tempfile::tempdir().expect("Temporary directory");
+        std::fs::write(folder.path().join("Outlook.pst"), b"!BDN\x00\x00").unwrap();
+        let candidates = probe_format(folder.path()).expect("Candidates");
+        assert_eq!(candidates[0].format, ProbedFormat::Pst);
+        assert_eq!(candidates[0].supported(), None);
+    }
+
+    #[test]
+    /// Test that mailing list mails are detected and reported
+    fn test_mailing_lists() {
+        use ps_core::model::mailing_lists::mailing_lists;
+        let header = "Date: Thu, 12 Mar 2020 10:00:00 +0100\r\nSubject: Hi\r\n";
+        let list = "From: Rust <rust@lists.example.com>\r\nList-Id: Rust Users <rust-users.lists.example.com>\r\nList-Unsubscribe: <mailto:leave@lists.example.com>, <https://lists.example.com/leave>\r\n";
+        let newsletter = "From: Shop <news@shop.example>\r\nPrecedence: bulk\r\n";
+        let personal = "From: A <a@example.com>\r\n";
+        let files: Vec<(String, String)> = [
+            ("1.a:2,", list),
+            ("2.b:2,", list),
+            ("3.c:2,", newsletter),
+            ("4.d:2,", personal),
+        ]
+        .iter()
+        .map(|(name, content)| {
+            let mail = format!("{}{}\r\nBody\r\n", content, header);
+            (format!("cur/{}", name), mail)
+        })
+        .collect();
+        let fixture = import_fixture(FormatType::Maildir, &files);
+
+        let db = &fixture.database;
+        assert_eq!(db.total_mails().expect("Expected total mails"), 4);
+        let groups = db
+            .query(&Query::Grouped {
+                filters: Vec::new(),
+                group_by: Field::ListId,
+                timezone: TimezoneMode::Utc,
+            })
+            .expect("Expected groups");
+        let mut counts: Vec<(String, usize)> = groups
+            .into_iter()
+            .map(|result| match result {
+                QueryResult::Grouped { count, value } => (value.to_string(), count),
+                _ => panic!(),
+            })
+            .collect();
+        counts.sort();
+        assert_eq!(
+            counts,
+            vec![
+                ("".to_string(), 2),
+                ("rust-users.lists.example.com".to_string(), 2)
+            ]
+        );
+
+        let lists = mailing_lists(db, 4).expect("Expected lists");
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists[0].name, "rust-users.lists.example.com");
+        assert_eq!(lists[0].count, 2);
+        assert_eq!(
+            lists[0].unsubscribe,
+            vec![
+                "mailto:leave@lists.example.com".to_string(),
+                "https://lists.example.com/leave".to_string()
+            ]
+        );
+        assert_eq!(lists[1].name, "news@shop.example");
+        assert!(lists[1].unsubscribe.is_empty());
+    }
+
+    #[test]
+    /// Test that subdomains are grouped into one organization
+    fn test_sender_organization() {
+        let senders = [
+            "a@mail.google.com",
+            "b@google.com",
+            "c@accounts.google.com",
+            "d@news.bbc.co.uk",
+        ];
+        let files: Vec<(String, String)> = senders
+            .iter()
+            .enumerate()
+            .map(|(index, sender)| {
+                let mail = format!(
+                    "From: {}\r\nDate: Thu, 12 Mar 2020 10:00:00 +0100\r\nSubject: Hi\r\n\r\nBody\r\n",
+                    sender
+                );
+                (format!("cur/{}.a:2,", index), mail)
+            })
+            .collect();
+        let fixture = import_fixture(FormatType::Maildir, &files);
+
+        let groups = fixture
+            .database
+            .query(&Query::Grouped {
+                filters: Vec::new(),
+                group_by: Field::SenderOrganization,
+                timezone: TimezoneMode::Utc,
+            })
+            .expect("Expected groups");
+        let mut counts: Vec<(String, usize)> = groups
+            .into_iter()
+            .map(|result| match result {
+                QueryResult::Grouped { count, value } => (value.to_string(), count),
+                _ => panic!(),
+            })
+            .collect();
+        counts.sort();
+        assert_eq!(
+            counts,
+            vec![("bbc.co.uk".to_string(), 1), ("google.com".to_string(), 3)]
+        );
+    }
+
+    #[test]
+    /// Test that the contact graph is built from senders and all recipients
+    fn test_contact_graph() {
+        use ps_core::model::contact_graph::{ContactGraph, GRAPH_FIELDS};
+        use ps_core::{Filter, ValueField};
+        initialize();
+        let path = "tests/resources/mbox";
+        let config =
+            Config::new(None, path, vec!["".to_string()], FormatType::Mbox).expect("Config");
+        let importer = ps_importer::mbox_importer(config.clone());
+        let database = Database::new(&config.database_path).unwrap();
+        let (_receiver, handle) = importer.import(database).unwrap();
+        handle.join().expect("").expect("");
+
+        let db = Database::new(&config.database_path).unwrap();
+        let graph = |filters: Vec<Filter>| {
+            let rows: Vec<_> = db
+                .query(&Query::Normal {
+                    fields: GRAPH_FIELDS.to_vec(),
+                    filters,
+                    range: 0..1000,
+                    timezone: TimezoneMode::Utc,
+                })
+                .expect("Expected rows")
+                .into_iter()
+                .filter_map(|result| match result {
+                    QueryResult::Normal(row) => Some(row),
+                    _ => None,
+                })
+                .collect();
+            ContactGraph::from_rows(&rows)
+        };
+        let all = graph(Vec::new());
+        assert_eq!(all.nodes.len(), 8);
+        assert_eq!(all.nodes["a.u.thor@example.com"].sent, 81);
+        assert_eq!(all.nodes["junkio@cox.net"].received, 9);
+        let edge = &all.edges[&(
+            "davidk@lysator.liu.se".to_string(),
+            "junkio@cox.net".to_string(),
+        )];
+        assert_eq!((edge.weight, edge.first), (9, 1124129905));
+
+        // The graph only contains the mails matching the filters
+        let scoped = graph(vec![Filter::Is(ValueField::string(
+            &Field::SenderDomain,
+            "lysator.liu.se",
+        ))]);
+        assert_eq!(scoped.nodes.len(), 2);
+        assert_eq!(scoped.edges.len(), 1);
+    }
+
+    #[test]
+    /// Test that replies are paired with their parents and the response times computed
+    fn test_response_times() {
+        use ps_core::model::response_times::response_times;
+        let mails = [
+            ("me@example.org", "10:00", "<1@example.org>", None),
+            (
+                "friend@example.com",
+                "12:00",
+                "<2@example.com>",
+                Some("<1@example.org>"),
+            ),
+            (
+                "me@example.org",
+                "12:30",
+                "<3@example.org>",
+                Some("<2@example.com>"),
+            ),
+            (
+                "friend@example.com",
+                "13:00",
+                "<4@example.com>",
+                Some("<unknown@example.com>"),
+            ),
+        ];
+        let files: Vec<(String, String)> = mails
+            .iter()
+            .enumerate()
+            .map(|(index, (sender, time, id, parent))| {
+                let in_reply_to = parent
+                    .map(|p| format!("In-Reply-To: {}\r\n", p))
+                    .unwrap_or_default();
+                let mail = format!(
+                    "From: {}\r\nMessage-Id: {}\r\n{}Date: Thu, 12 Mar 2020 {}:00 +0100\r\nSubject: Hi\r\n\r\nBody\r\n",
+                    sender, id, in_reply_to, time
+                );
+                (format!("cur/{}.a:2,", index), mail)
+            })
+            .collect();
+        let fixture = import_fixture_with(FormatType::Maildir, &files, |config| {
+            config.sender_emails = ["me@example.org".to_string()].into_iter().collect();
+        });
+
+        let db = &fixture.database;
+        let times = response_times(db, 4).expect("Expected response times");
+        assert_eq!(times.by_domain.len(), 1);
+        let example = &times.by_domain[0];
+        assert_eq!(example.key, "example.com");
+        assert_eq!(example.theirs.count(), 1);
+        assert_eq!(example.theirs.median(), Some(2 * 3600));
+        assert_eq!(example.ours.median(), Some(30 * 60));
+        assert_eq!(times.by_year[0].key, "2020");
+
+        // Without the own address, there are no replies between the user and others
+        let mut without = fixture.config.clone();
+        without.sender_emails.clear();
+        db.recompute_is_send(&without).expect("Expected recompute");
+        let times = response_times(db, 4).expect("Expected response times");
+        assert!(times.by_year.is_empty());
+    }
+
+    #[test]
+    /// Test that tags are stored per mail and grouped and filtered by single tags
+    fn test_tags() {
+        use ps_core::{Filter, OtherQuery, ValueField, UNTAGGED};
+        let mails: [&[&str]; 5] = [
+            &["Inbox", "Work"],
+            &["Inbox/Archive"],
+            &["Work"],
+            &[],
+            // A tag with the name of the untagged segment
+            &[UNTAGGED],
+        ];
+        let mut files = Vec::new();
+        for (index, labels) in mails.iter().enumerate() {
+            let mail = format!(
+                "From: friend@example.com\r\nDate: Thu, 12 Mar 2020 1{}:00:00 +0100\r\nSubject: Hi\r\n\r\nBody\r\n",
+                index
+            );
+            files.push((format!("All/{}.eml", index), mail));
+            let meta = ps_core::serde_json::json!({
+                "msg_id": index.to_string(),
+                "subject": "Hi",
+                "labels": labels,
+                "flags": [],
+                "internal_date": 1584000000 + index,
+            });
+            files.push((format!("All/{}.meta", index), meta.to_string()));
+        }
+        let fixture = import_fixture(FormatType::GmailVault, &files);
+
+        let db = &fixture.database;
+        // A mail with two tags counts in both, a mail without tags in the untagged segment
+        let query = Query::Grouped {
+            filters: vec![],
+            group_by: Field::MetaTags,
+            timezone: Default::default(),
+        };
+        let mut groups: Vec<(String, usize)> = db
+            .query(&query)
+            .unwrap()
+            .into_iter()
+            .filter_map(|result| match result {
+                QueryResult::Grouped { value, count } => Some((value.to_string(), count)),
+                _ => None,
+            })
+            .collect();
+        groups.sort();
+        assert_eq!(
+            groups,
+            vec![
+                (UNTAGGED.to_string(), 1),
+                (UNTAGGED.to_string(), 1),
+                ("Inbox".to_string(), 1),
+                ("Inbox/Archive".to_string(), 1),
+                ("Work".to_string(), 2)
+            ]
+        );
+
+        // Tag filters match whole tags only
+        let count = |filter: Filter| {
+            let query = Query::Normal {
+                fields: vec![Field::Subject],
+                filters: vec![filter],
+                range: 0..10,
+                timezone: Default::default(),
+            };
+            db.query(&query).unwrap().len()
+        };
+        assert_eq!(
+            count(Filter::Like(ValueField::string(&Field::MetaTags, "Inbox"))),
+            1
+        );
+        assert_eq!(
+            count(Filter::NotLike(ValueField::string(
+                &Field::MetaTags,
+                "Inbox"
+            ))),
+            4
+        );
+        assert_eq!(
+            count(Filter::Like(ValueField::string(&Field::MetaTags, "Work"))),
+            2
+        );
+        assert_eq!(count(Filter::Like(ValueField::untagged())), 1);
+        assert_eq!(count(Filter::NotLike(ValueField::untagged())), 4);
+        assert_eq!(
+            count(Filter::Like(ValueField::string(&Field::MetaTags, UNTAGGED))),
+            1
+        );
+
+        let query = Query::Other {
+            query: OtherQuery::All(Field::MetaTags),
+        };
+        assert_eq!(db.query(&query).unwrap().len(), 4);
+    }
+
+    #[test]
+    /// Test that the maildir flags and Dovecot keywords are imported
+    fn test_maildir_flags() {
+        use ps_core::{Filter, ValueField};
+        let mut files = vec![(
+            "dovecot-keywords".to_string(),
+            "0 $Junk\n1 Work\n".to_string(),
+        )];
+        for (index, flags) in ["FRSb", "DS", "T", "Sa"].iter().enumerate() {
+            let mail = format!(
+                "From: friend@example.com\r\nDate: Thu, 12 Mar 2020 1{}:00:00 +0100\r\nSubject: Hi\r\n\r\nBody\r\n",
+                index
+            );
+            files.push((format!("cur/{}.a:2,{}", index, flags), mail));
+        }
+        let fixture = import_fixture(FormatType::Maildir, &files);
+
+        let db = &fixture.database;
+        let count = |filter: Filter| {
+            let query = Query::Normal {
+                fields: vec![Field::Subject],
+                filters: vec![filter],
+                range: 0..10,
+                timezone: Default::default(),
+            };
+            db.query(&query).unwrap().len()
+        };
+        assert_eq!(
+            count(Filter::Is(ValueField::bool(&Field::MetaIsSeen, true))),
+            3
+        );
+        assert_eq!(
+            count(Filter::Is(ValueField::bool(&Field::MetaIsFlagged, true))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Is(ValueField::bool(&Field::MetaIsAnswered, true))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Is(ValueField::bool(&Field::MetaIsDraft, true))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Is(ValueField::bool(&Field::MetaIsTrashed, true))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Like(ValueField::string(&Field::MetaTags, "Work"))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Like(ValueField::string(&Field::MetaTags, "$Junk"))),
+            1
+        );
+    }
+
+    #[test]
+    /// Test the Apple Mail mailbox hierarchy, the flags and partial emlx files
+    fn test_applemail_mailboxes() {
+        use ps_core::{Filter, MailLocation, ValueField};
+        let account = "E4B5C1D2-6A2B-4C7D-9E8F-0A1B2C3D4E5F";
+        let archive = format!(
+            "{}/Archive.mbox/2020.mbox/0A1B2C3D-6A2B-4C7D-9E8F-E4B5C1D2AAAA/Data",
+            account
+        );
+        let emlx = |message: &str, flags: u64| {
+            let plist = format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict><key>flags</key><integer>{}</integer></dict></plist>"#,
+                flags
+            );
+            format!("{}\n{}{}", message.len(), message, plist).into_bytes()
+        };
+        // Read, flagged and answered, without the attachment
+        let partial = "From: friend@example.com\r\nDate: Thu, 12 Mar 2020 10:00:00 +0100\r\nSubject: Report\r\nContent-Type: multipart/mixed; boundary=\"Apple-Mail=_B1\"\r\n\r\n--Apple-Mail=_B1\r\nContent-Type: text/plain\r\n\r\nSee attached\r\n--Apple-Mail=_B1\r\nContent-Type: application/pdf; name=report.pdf\r\nContent-Disposition: attachment; filename=report.pdf\r\nContent-Transfer-Encoding: base64\r\n\r\n\r\n--Apple-Mail=_B1--\r\n";
+        // Unread junk
+        let junk = "From: spam@example.com\r\nDate: Thu, 12 Mar 2020 11:00:00 +0100\r\nSubject: Offer\r\n\r\nBuy\r\n";
+        let partial_path = format!("{}/Messages/1.partial.emlx", archive);
+        let files = [
+            (partial_path.clone(), emlx(partial, 1 | 1 << 2 | 1 << 4)),
+            (
+                format!("{}/Attachments/1/2/report.pdf", archive),
+                vec![7u8; 1000],
+            ),
+            (
+                "Mailboxes/INBOX.mbox/Messages/2.emlx".to_string(),
+                emlx(junk, 1 << 24),
+            ),
+        ];
+        let fixture = import_fixture(FormatType::AppleMail, &files);
+
+        let db = &fixture.database;
+        let count = |filter: Filter| {
+            let query = Query::Normal {
+                fields: vec![Field::Subject],
+                filters: vec![filter],
+                range: 0..10,
+                timezone: Default::default(),
+            };
+            db.query(&query).unwrap().len()
+        };
+        assert_eq!(
+            count(Filter::Like(ValueField::string(
+                &Field::MetaTags,
+                "Archive/2020"
+            ))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Like(ValueField::string(&Field::MetaTags, account))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Like(ValueField::string(&Field::MetaTags, "INBOX"))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Like(ValueField::string(&Field::MetaTags, "$Junk"))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Is(ValueField::bool(&Field::MetaIsSeen, true))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Is(ValueField::bool(&Field::MetaIsFlagged, true))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Is(ValueField::bool(&Field::MetaIsAnswered, true))),
+            1
+        );
+
+        // The attachment of the partial emlx is part of the message again
+        let location = MailLocation {
+            path: fixture.config.emails_folder_path.join(&partial_path),
+            mbox_location: None,
+        };
+        let preview =
+            ps_importer::load_preview(&FormatType::AppleMail, &location).expect("Expected Preview");
+        assert_eq!(preview.attachments.len(), 1);
+        assert_eq!(preview.attachments[0].name, "report.pdf");
+        assert_eq!(preview.attachments[0].size, 1000);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    /// Test that the tags of a `notmuch dump` are imported, if the dump is selected
+    fn test_notmuch_tags() {
+        use ps_core::{Filter, TagSource, ValueField};
+        let mut files: Vec<(String, String)> = (0..3)
+            .map(|index| {
+                let mail = format!(
+                    "From: friend@example.com\r\nMessage-Id: <{}@example.com>\r\nDate: Thu, 12 Mar 2020 1{}:00:00 +0100\r\nSubject: Hi\r\n\r\nBody\r\n",
+                    index, index
+                );
+                (format!("cur/{}.a:2,S", index), mail)
+            })
+            .collect();
+        files.push((
+            "notmuch-dump".to_string(),
+            "#notmuch-dump batch-tag:3 tags\n+inbox +project%2fx -- id:0@example.com\n+inbox -- id:1@example.com\n".to_string(),
+        ));
+        let fixture = import_fixture_with(FormatType::Maildir, &files, |config| {
+            let dump = config.emails_folder_path.join("notmuch-dump");
+            config.tag_source = Some(TagSource::NotmuchDump(dump));
+        });
+
+        let config = &fixture.config;
+        let stored = Database::config(&config.database_path).expect("Config");
+        assert_eq!(stored.tag_source, config.tag_source);
+        let db = &fixture.database;
+        let count = |tag: &str| {
+            let query = Query::Normal {
+                fields: vec![Field::Subject],
+                filters: vec![Filter::Like(ValueField::string(&Field::MetaTags, tag))],
+                range: 0..10,
+                timezone: Default::default(),
+            };
+            db.query(&query).unwrap().len()
+        };
+        assert_eq!(count("inbox"), 2);
+        assert_eq!(count("project/x"), 1);
+    }
+
+    #[test]
+    /// Test that MH folders and their sequences are imported
+    fn test_mh_import() {
+        use ps_core::{Filter, ValueField};
+        let mut files: Vec<(String, String)> = [("inbox", 1), ("inbox", 2), ("inbox", 3), ("inbox/work", 1)]
+            .iter()
+            .map(|(folder, number)| {
+                let mail = format!(
+                    "From: friend@example.com\nDate: Thu, 12 Mar 2020 1{}:00:00 +0100\nSubject: Hi\n\nBody\n",
+                    number
+                );
+                (format!("{}/{}", folder, number), mail)
+            })
+            .collect();
+        files.push((
+            "inbox/.mh_sequences".to_string(),
+            "cur: 1\nunseen: 2-3\nflagged: 3\nreplied: 1\ntodo: 1 3\n".to_string(),
+        ));
+        // Not a mail
+        files.push(("inbox/notes.txt".to_string(), "Hi".to_string()));
+        let fixture = import_fixture(FormatType::Mh, &files);
+
+        let detected =
+            ps_importer::probe_format(&fixture.config.emails_folder_path).expect("Candidates");
+        assert_eq!(
+            detected[0].format,
+            ps_importer::ProbedFormat::Supported(FormatType::Mh)
+        );
+
+        let db = &fixture.database;
+        let count = |filter: Filter| {
+            let query = Query::Normal {
+                fields: vec![Field::Subject],
+                filters: vec![filter],
+                range: 0..10,
+                timezone: Default::default(),
+            };
+            db.query(&query).unwrap().len()
+        };
+        assert_eq!(
+            count(Filter::Like(ValueField::string(&Field::MetaTags, "inbox"))),
+            3
+        );
+        assert_eq!(
+            count(Filter::Like(ValueField::string(
+                &Field::MetaTags,
+                "inbox/work"
+            ))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Like(ValueField::string(&Field::MetaTags, "todo"))),
+            2
+        );
+        assert_eq!(
+            count(Filter::Is(ValueField::bool(&Field::MetaIsSeen, true))),
+            2
+        );
+        assert_eq!(
+            count(Filter::Is(ValueField::bool(&Field::MetaIsFlagged, true))),
+            1
+        );
+        assert_eq!(
+            count(Filter::Is(ValueField::bool(&Field::MetaIsAnswered, true))),
+            1
+        );
+    }
+
+    #[test]
+    /// Test that the mbox variants are split and unescaped, and that
+    /// regions which are not mails are recorded as import errors
+    fn test_mbox_variants() {
+        use ps_core::{Filter, ImportErrorKind, ValueField};
+        let header = |subject: &str| {
+            format!(
+                "From: friend@example.com\nDate: Thu, 12 Mar 2020 10:00:00 +0100\nSubject: {}\n",
+                subject
+            )
+        };
+        // `mboxrd`, without an extension
+        let mboxrd = format!(
+            "From friend@example.com Thu Mar 12 10:00:00 2020\n{}\n>>From quoted\n>From escaped\n\nFrom friend@example.com Thu Mar 12 10:00:00 2020\n{}\nBody\n",
+            header("rd 1"),
+            header("rd 2")
+        );
+        // `mboxcl2`, with an unquoted `From ` line in the body
+        let body = "From the start\n\nFrom here\n";
+        let mboxcl2 = format!(
+            "From friend@example.com Thu Mar 12 10:00:00 2020\n{}Content-Length: {}\n\n{}\nFrom friend@example.com Thu Mar 12 10:00:00 2020\n{}Content-Length: 5\n\nBody\n",
+            header("cl2 1"),
+            body.len(),
+            body,
+            header("cl2 2")
+        );
+        // Garbage in front of the first mail
+        let broken = format!(
+            "garbage\nFrom friend@example.com Thu Mar 12 10:00:00 2020\n{}\nBody\n",
+            header("broken")
+        );
+        let fixture = import_fixture(
+            FormatType::Mbox,
+            &[
+                ("archive", mboxrd),
+                ("cl2.mbox", mboxcl2),
+                ("broken.mbox", broken),
+                // Not an mbox
+                ("notes.txt", "From here\n".to_string()),
+            ],
+        );
+
+        let db = &fixture.database;
+        assert_eq!(db.total_mails().expect("Expected total mails"), 5);
+        let errors = db.import_errors().expect("Expected errors");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, ImportErrorKind::Parse);
+        assert!(errors[0].path.ends_with("broken.mbox"));
+        assert!(errors[0].snippet.as_ref().unwrap().contains("garbage"));
+
+        let content = |subject: &str| {
+            let query = Query::Normal {
+                fields: vec![Field::Path, Field::MboxOffset, Field::MboxLength],
+                filters: vec![Filter::Like(ValueField::string(&Field::Subject, subject))],
+                range: 0..1,
+                timezone: Default::default(),
+            };
+            let row = match db.query(&query).unwrap().pop() {
+                Some(QueryResult::Normal(row)) => row,
+                _ => panic!("Missing {}", subject),
+            };
+            let location = MailLocation::from_row(&row).expect("Location");
+            let data = ps_importer::read_message(&FormatType::Mbox, &location).unwrap();
+            String::from_utf8(data).unwrap()
+        };
+        assert!(content("rd 1").ends_with("\n>From quoted\nFrom escaped\n"));
+        assert!(content("rd 2").ends_with("\nBody\n"));
+        assert!(content("cl2 1").ends_with(body));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    /// Test that mails with large attachments are imported from their headers,
+    /// while the progress still counts the whole mails
+    fn test_header_only_import() {
+        use ps_core::Message;
+        let attachment = "QUJD\r\n".repeat(100_000);
+        let mail = format!(
+            "From: A <a@example.com>\r\nDate: Thu, 12 Mar 2020 10:00:00 +0100\r\nSubject: Attachment\r\nContent-Type: multipart/mixed; boundary=\"b\"\r\n\r\n--b\r\nContent-Type: text/plain\r\n\r\nHi\r\n--b\r\nContent-Type: application/octet-stream\r\nContent-Transfer-Encoding: base64\r\n\r\n{}--b--\r\n",
+            attachment
+        );
+        let fixture = import_fixture(FormatType::Maildir, &[("cur/1.a:2,S", &mail)]);
+
+        let bytes: usize = fixture
+            .messages
+            .iter()
+            .filter_map(|message| match message {
+                Message::WriteOne { bytes, .. } => Some(bytes),
+                _ => None,
+            })
+            .sum();
+        assert_eq!(bytes, mail.len());
+        let db = &fixture.database;
+        assert_eq!(db.total_mails().expect("Expected total mails"), 1);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    /// Test that mails with broken charsets or headers are imported
+    /// on a best-effort basis and marked as lossy
+    fn test_lossy_import() {
+        use ps_core::{Filter, ParseQuality, ValueField};
+        let date = "Date: Thu, 12 Mar 2020 10:00:00 +0100";
+        let exact = format!("From: a@example.com\n{}\nSubject: Exact\n\nBody\n", date);
+        // Latin-1 without a charset
+        let latin1 = [
+            format!("From: a@example.com\n{}\nSubject: caf", date).as_bytes(),
+            b"\xe9\n\nBody\n",
+        ]
+        .concat();
+        let no_date = b"From: a@example.com\nSubject: No date\n\nBody\n";
+        let fixture = import_fixture(
+            FormatType::Maildir,
+            &[
+                ("cur/1.a:2,", exact.as_bytes()),
+                ("cur/2.b:2,", &latin1[..]),
+                ("cur/3.c:2,", &no_date[..]),
+            ],
+        );
+
+        let db = &fixture.database;
+        assert_eq!(db.total_mails().expect("Expected total mails"), 2);
+        assert_eq!(db.import_errors().expect("Expected errors").len(), 1);
+        let subjects = |quality: ParseQuality| {
+            let query = Query::Normal {
+                fields: vec![Field::Subject],
+                filters: vec![Filter::Is(ValueField::string(
+                    &Field::ParseQuality,
+                    quality.as_str(),
+                ))],
+                range: 0..10,
+                timezone: Default::default(),
+            };
+            db.query(&query)
+                .unwrap()
+                .into_iter()
+                .map(|result| match result {
+                    QueryResult::Normal(row) => row[&Field::Subject].to_string(),
+                    _ => panic!(),
+                })
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(subjects(ParseQuality::Exact), vec!["Exact"]);
+        assert_eq!(subjects(ParseQuality::Lossy), vec!["café"]);
+    }
+
+    #[test]
+    /// Test that the import filter skips folders, tags and
+    /// mails outside of the date window
+    fn test_filtered_import() {
+        use ps_core::ImportFilter;
+        let mails = [
+            ("inbox", 1, "2014"),
+            ("inbox", 2, "2016"),
+            ("inbox", 3, "2018"),
+            ("inbox", 4, "2021"),
+            ("inbox/work", 1, "2017"),
+            ("Spam", 1, "2017"),
+            ("Drafts", 1, "2019"),
+        ];
+        let mut files: Vec<(String, String)> = mails
+            .iter()
+            .map(|(folder, number, year)| {
+                let name = folder.rsplit('/').next().unwrap();
+                let mail = format!(
+                    "From: friend@example.com\nDate: Thu, 12 Mar {} 10:00:00 +0100\nSubject: {} {}\n\nBody\n",
+                    year, name, number
+                );
+                (format!("{}/{}", folder, number), mail)
+            })
+            .collect();
+        files.push((
+            "inbox/.mh_sequences".to_string(),
+            "todo-later: 3\n".to_string(),
+        ));
+        let filter = ImportFilter {
+            exclude_folders: ImportFilter::parse_list("spam, Drafts").unwrap(),
+            from_date: ImportFilter::parse_date("2015-01-01").unwrap(),
+            until_date: ImportFilter::parse_date("2020-12-31").unwrap(),
+            skip_tags: ImportFilter::parse_list("TODO*").unwrap(),
+            ..Default::default()
+        };
+        let fixture = import_fixture_with(FormatType::Mh, &files, |config| {
+            config.filter = filter.clone();
+        });
+
+        let db = &fixture.database;
+        let query = Query::Normal {
+            fields: vec![Field::Subject],
+            filters: vec![],
+            range: 0..10,
+            timezone: Default::default(),
+        };
+        let mut subjects: Vec<String> = db
+            .query(&query)
+            .unwrap()
+            .into_iter()
+            .map(|result| match result {
+                QueryResult::Normal(row) => row[&Field::Subject].to_string(),
+                _ => panic!(),
+            })
+            .collect();
+        subjects.sort();
+        assert_eq!(subjects, vec!["inbox 2", "work 1"]);
+        let stored = Database::config(&fixture.config.database_path).expect("Config");
+        assert_eq!(stored.filter, filter);
+    }
 }
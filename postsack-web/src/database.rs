@@ -6,8 +6,8 @@ use std::{ops::Range, path::Path};
 use ps_core::{
     crossbeam_channel::Sender,
     eyre::{bail, Result},
-    Config, DBMessage, DatabaseLike, DatabaseQuery, Field, Filter, Query, QueryResult, Value,
-    ValueField,
+    Config, DBMessage, DatabaseLike, DatabaseQuery, Field, Filter, ImportError, Query, QueryResult,
+    Value, ValueField,
 };
 use ps_core::{OtherQuery, QueryRow};
 
@@ -32,23 +32,41 @@ impl Entry {
         match field {
             Field::Path => Value::String("".to_string()),
             Field::SenderDomain => Value::String(self.sender_domain.to_string()),
+            Field::SenderOrganization => Value::String(self.sender_domain.to_string()),
             Field::SenderLocalPart => Value::String(self.sender_local_part.to_string()),
             Field::SenderName => Value::String(self.sender_name.to_string()),
             Field::Subject => Value::String(self.subject.to_string()),
             Field::ToName => Value::String(self.to_name.to_string()),
             Field::ToAddress => Value::String(self.to_address.to_string()),
             Field::ToGroup => Value::String("".to_string()),
+            Field::Source => Value::String("".to_string()),
+            Field::ParseQuality => Value::String("exact".to_string()),
+            Field::Recipients => Value::Array(Vec::new()),
+            Field::ListId => Value::String("".to_string()),
+            Field::ListUnsubscribe => Value::String("".to_string()),
 
             Field::Year => Value::Number(self.year.into()),
             Field::Month => Value::Number(self.month.into()),
             Field::Day => Value::Number(self.day.into()),
+            Field::Hour => Value::Number(((self.timestamp / 3600) % 24).into()),
             Field::Timestamp => Value::Number(self.timestamp.into()),
+            Field::TzOffset => Value::Number(0.into()),
 
             Field::IsReply => Value::Bool(self.is_reply),
             Field::IsSend => Value::Bool(self.is_send),
+            Field::IsBulk => Value::Bool(false),
+            Field::ReplyLatency => Value::Null,
+            Field::ReplyDomain => Value::String("".to_string()),
 
             Field::MetaIsSeen => Value::Bool(false),
+            Field::MetaIsFlagged => Value::Bool(false),
+            Field::MetaIsAnswered => Value::Bool(false),
+            Field::MetaIsDraft => Value::Bool(false),
+            Field::MetaIsTrashed => Value::Bool(false),
             Field::MetaTags => Value::Array(Vec::new()),
+
+            Field::MboxOffset => Value::Null,
+            Field::MboxLength => Value::Null,
         }
     }
 
@@ -107,9 +125,17 @@ impl FakeDatabase {
 
     fn query_grouped(&self, filters: &[Filter], group_by: &Field) -> Vec<QueryResult> {
         let mut map = HashMap::<HashedValue, usize>::new();
+        // Mails with multiple tags count once per tag
         for entry in self
             .filtered(filters)
-            .map(|e| HashedValue(e.value(group_by)))
+            .flat_map(|e| match e.value(group_by) {
+                Value::Array(values) if values.is_empty() && *group_by == Field::MetaTags => {
+                    vec![Value::Null]
+                }
+                Value::Array(values) => values,
+                value => vec![value],
+            })
+            .map(HashedValue)
         {
             let entry = map.entry(entry).or_insert(0);
             *entry += 1;
@@ -146,6 +172,16 @@ impl FakeDatabase {
             for filter in filters {
                 // Go through all filters and escape early if they don't match
                 match filter {
+                    Filter::Like(vf) | Filter::Is(vf) if *vf.field() == Field::MetaTags => {
+                        if !has_tag(entry, vf) {
+                            return false;
+                        }
+                    }
+                    Filter::NotLike(vf) if *vf.field() == Field::MetaTags => {
+                        if has_tag(entry, vf) {
+                            return false;
+                        }
+                    }
                     Filter::Like(vf) => {
                         let other = entry.value(vf.field());
                         if vf.value() != &other {
@@ -188,6 +224,15 @@ impl FakeDatabase {
     }
 }
 
+/// Does `entry` have the tag of `tag`, or no tags for [`ValueField::untagged`]
+fn has_tag(entry: &Entry, tag: &ValueField) -> bool {
+    match entry.value(tag.field()) {
+        Value::Array(tags) if tag.is_untagged() => tags.is_empty(),
+        Value::Array(tags) => tags.contains(tag.value()),
+        _ => false,
+    }
+}
+
 impl Clone for FakeDatabase {
     fn clone(&self) -> Self {
         FakeDatabase
@@ -201,8 +246,11 @@ impl DatabaseQuery for FakeDatabase {
                 fields,
                 filters,
                 range,
+                ..
             } => Ok(self.query_normal(fields, filters, range)),
-            Query::Grouped { filters, group_by } => Ok(self.query_grouped(filters, group_by)),
+            Query::Grouped {
+                filters, group_by, ..
+            } => Ok(self.query_grouped(filters, group_by)),
             Query::Other {
                 query: OtherQuery::All(q),
             } => Ok(self.query_other(q)),
@@ -235,6 +283,18 @@ impl DatabaseLike for FakeDatabase {
     fn save_config(&self, _config: Config) -> Result<()> {
         Ok(())
     }
+
+    fn create_indexes(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn import_errors(&self) -> Result<Vec<ImportError>> {
+        Ok(Vec::new())
+    }
+
+    fn recompute_is_send(&self, _config: &Config) -> Result<usize> {
+        Ok(0)
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
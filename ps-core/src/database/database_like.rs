@@ -4,7 +4,7 @@ use std::thread::JoinHandle;
 use crossbeam_channel::Sender;
 use eyre::Result;
 
-use crate::Config;
+use crate::{Config, ImportError};
 
 use super::{db_message::DBMessage, query::Query, query_result::QueryResult};
 
@@ -22,4 +22,12 @@ pub trait DatabaseLike: DatabaseQuery + Clone {
     fn total_mails(&self) -> Result<usize>;
     fn import(self) -> (Sender<DBMessage>, JoinHandle<Result<usize>>);
     fn save_config(&self, config: Config) -> Result<()>;
+    /// Create the indexes used by the segmentation queries.
+    /// Called once the import finished writing.
+    fn create_indexes(&self) -> Result<()>;
+    /// All the mails that could not be imported
+    fn import_errors(&self) -> Result<Vec<ImportError>>;
+    /// Recompute `is_send` of all mails from the sender addresses of the
+    /// sources in `config` and store `config`. Returns the amount of send mails.
+    fn recompute_is_send(&self, config: &Config) -> Result<usize>;
 }
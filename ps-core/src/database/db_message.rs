@@ -1,13 +1,13 @@
-use eyre::Report;
-
-use crate::EmailEntry;
+use crate::{EmailEntry, ImportError};
 
 /// Parameter for sending work to the database during `import`.
 pub enum DBMessage {
     /// Send for a successfuly parsed mail
     Mail(Box<EmailEntry>),
     /// Send for any kind of error during reading / parsing
-    Error(Report),
+    Error(Box<ImportError>),
+    /// Send if the import was cancelled. Everything written so far is discarded.
+    Cancel,
     /// Send once all parsing is done.
     /// This is used to break out of the receiving loop
     Done,
@@ -8,6 +8,32 @@ use std::ops::Range;
 
 pub const AMOUNT_FIELD_NAME: &str = "amount";
 
+/// The tags of a mail as JSON array. Tags are stored in the `tags` table and
+/// linked to the mails in `email_tags`.
+const SQL_TAGS: &str = "(SELECT json_group_array(tags.name) FROM email_tags JOIN tags ON tags.id = email_tags.tag_id WHERE email_tags.email_id = emails.rowid)";
+
+/// Mails with exactly this tag
+const SQL_HAS_TAG: &str = "EXISTS (SELECT 1 FROM email_tags JOIN tags ON tags.id = email_tags.tag_id WHERE email_tags.email_id = emails.rowid AND tags.name = ?)";
+
+/// Mails without this tag
+const SQL_HAS_NOT_TAG: &str = "NOT EXISTS (SELECT 1 FROM email_tags JOIN tags ON tags.id = email_tags.tag_id WHERE email_tags.email_id = emails.rowid AND tags.name = ?)";
+
+/// Mails without any tag
+const SQL_HAS_NO_TAGS: &str =
+    "NOT EXISTS (SELECT 1 FROM email_tags WHERE email_tags.email_id = emails.rowid)";
+
+/// Mails with at least one tag
+const SQL_HAS_TAGS: &str =
+    "EXISTS (SELECT 1 FROM email_tags WHERE email_tags.email_id = emails.rowid)";
+
+/// The label of the `MetaTags` segment of the mails without tags.
+/// The segment itself has no value (see [`ValueField::untagged`]),
+/// so it doesn't merge with a tag of the same name.
+pub const UNTAGGED: &str = "(Untagged)";
+
+/// Mails with a tag that contains this text
+const SQL_HAS_TAG_CONTAINING: &str = "EXISTS (SELECT 1 FROM email_tags JOIN tags ON tags.id = email_tags.tag_id WHERE email_tags.email_id = emails.rowid AND lower(tags.name) LIKE ?)";
+
 #[derive(Clone, Debug)]
 pub enum Filter {
     /// A database Like Operation
@@ -26,30 +52,72 @@ pub enum Filter {
 pub enum Field {
     Path,
     SenderDomain,
+    /// The registrable domain of the sender, e.g. `google.com` for `mail.google.com`
+    SenderOrganization,
     SenderLocalPart,
     SenderName,
     Year,
     Month,
     Day,
+    Hour,
     Timestamp,
+    /// The offset in seconds east of UTC of the sender's `Date` header
+    TzOffset,
     ToGroup,
     ToName,
     ToAddress,
+    /// The name of the mail source the mail was imported from
+    Source,
+    /// `exact`, or `lossy` for mails that could only be decoded on a best-effort basis
+    ParseQuality,
+    /// The addresses of all `To`, `Cc` and `Bcc` recipients
+    Recipients,
+    /// The `List-Id` of mails from mailing lists
+    ListId,
+    /// The `mailto:` and `https:` links of the `List-Unsubscribe` header
+    ListUnsubscribe,
+    /// Mailing list mails and newsletters
+    IsBulk,
     IsReply,
     IsSend,
+    /// The seconds between this reply and the mail it replies to, if one of
+    /// both was sent by the user (see [`Field::ReplyDomain`])
+    ReplyLatency,
+    /// The sender domain of the correspondent of a reply with a [`Field::ReplyLatency`]:
+    /// the domain of the replied mail for replies by the user, otherwise the own sender domain
+    ReplyDomain,
     Subject,
     MetaIsSeen,
+    MetaIsFlagged,
+    /// Mails the user replied to, as marked by the mail client
+    MetaIsAnswered,
+    MetaIsDraft,
+    /// Mails marked for deletion
+    MetaIsTrashed,
     MetaTags,
+    MboxOffset,
+    MboxLength,
 }
 
 const INVALID_FIELDS: &[Field] = &[
     Field::Path,
     Field::Subject,
     Field::Timestamp,
+    Field::TzOffset,
     Field::IsReply,
     Field::IsSend,
+    Field::Recipients,
+    Field::ListUnsubscribe,
+    Field::IsBulk,
+    Field::ReplyLatency,
+    Field::ReplyDomain,
     Field::MetaIsSeen,
-    Field::MetaTags,
+    Field::MetaIsFlagged,
+    Field::MetaIsAnswered,
+    Field::MetaIsDraft,
+    Field::MetaIsTrashed,
+    Field::MboxOffset,
+    Field::MboxLength,
 ];
 
 impl Field {
@@ -63,20 +131,61 @@ impl Field {
         self.into()
     }
 
+    /// The SQL expression used to read this field.
+    /// For the date fields this depends on the `TimezoneMode`, all
+    /// other fields map to their column.
+    fn sql_expression(&self, timezone: TimezoneMode) -> &'static str {
+        use Field::*;
+        use TimezoneMode::*;
+        match (self, timezone) {
+            (Year, Local) => "CAST(strftime('%Y', timestamp, 'unixepoch', 'localtime') AS INTEGER)",
+            (Month, Local) => {
+                "CAST(strftime('%m', timestamp, 'unixepoch', 'localtime') AS INTEGER)"
+            }
+            (Day, Local) => "CAST(strftime('%d', timestamp, 'unixepoch', 'localtime') AS INTEGER)",
+            (Hour, Local) => "CAST(strftime('%H', timestamp, 'unixepoch', 'localtime') AS INTEGER)",
+            (Year, Sender) => "CAST(strftime('%Y', timestamp + tz_offset, 'unixepoch') AS INTEGER)",
+            (Month, Sender) => {
+                "CAST(strftime('%m', timestamp + tz_offset, 'unixepoch') AS INTEGER)"
+            }
+            (Day, Sender) => "CAST(strftime('%d', timestamp + tz_offset, 'unixepoch') AS INTEGER)",
+            (Hour, Sender) => "CAST(strftime('%H', timestamp + tz_offset, 'unixepoch') AS INTEGER)",
+            (MetaTags, _) => SQL_TAGS,
+            _ => self.as_str(),
+        }
+    }
+
+    /// The SQL used to select this field. Computed fields are
+    /// aliased to their column name so that rows can be read the same way.
+    fn sql_selection(&self, timezone: TimezoneMode) -> String {
+        let expression = self.sql_expression(timezone);
+        if expression == self.as_str() {
+            expression.to_string()
+        } else {
+            format!("{} AS {}", expression, self.as_str())
+        }
+    }
+
     /// A human readable name
     pub fn name(&self) -> &str {
         use Field::*;
         match self {
             SenderDomain => "Domain",
+            SenderOrganization => "Organization",
             SenderLocalPart => "Address",
             SenderName => "Name",
             ToGroup => "Group",
             ToName => "To name",
             ToAddress => "To address",
+            Source => "Source",
+            ParseQuality => "Decoding",
+            ListId => "List",
             Year => "Year",
             Month => "Month",
             Day => "Day",
+            Hour => "Hour",
             Subject => "Subject",
+            MetaTags => "Tags",
             _ => self.as_str(),
         }
     }
@@ -109,6 +218,14 @@ impl ValueField {
         }
     }
 
+    /// The `MetaTags` value of the mails without tags
+    pub fn untagged() -> ValueField {
+        ValueField {
+            field: Field::MetaTags,
+            value: Value::Null,
+        }
+    }
+
     pub fn bool(field: &Field, value: bool) -> ValueField {
         ValueField {
             field: *field,
@@ -138,15 +255,48 @@ impl ValueField {
         &self.value
     }
 
+    /// Is this the `MetaTags` value of the mails without tags
+    pub fn is_untagged(&self) -> bool {
+        self.field == Field::MetaTags && self.value.is_null()
+    }
+
     #[allow(clippy::inherent_to_string)]
     pub fn to_string(&self) -> String {
         match &self.value {
             Value::String(s) => s.clone(),
+            _ if self.is_untagged() => UNTAGGED.to_string(),
             _ => format!("{}", &self.value),
         }
     }
 }
 
+/// Which clock the `Year`, `Month`, `Day` and `Hour` fields are evaluated in
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, EnumIter)]
+pub enum TimezoneMode {
+    /// Coordinated Universal Time
+    #[default]
+    Utc,
+    /// The timezone of the machine running Postsack
+    Local,
+    /// The timezone the sender had when writing the email
+    Sender,
+}
+
+impl TimezoneMode {
+    pub fn all_cases() -> impl Iterator<Item = TimezoneMode> {
+        TimezoneMode::iter()
+    }
+
+    /// A human readable name
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimezoneMode::Utc => "UTC",
+            TimezoneMode::Local => "Local Time",
+            TimezoneMode::Sender => "Sender's Time",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum OtherQuery {
     /// Get all contents of a specific field
@@ -158,11 +308,13 @@ pub enum Query {
     Grouped {
         filters: Vec<Filter>,
         group_by: Field,
+        timezone: TimezoneMode,
     },
     Normal {
         fields: Vec<Field>,
         filters: Vec<Filter>,
         range: Range<usize>,
+        timezone: TimezoneMode,
     },
     Other {
         query: OtherQuery,
@@ -177,42 +329,90 @@ impl Query {
             Query::Other { .. } => &[],
         }
     }
+
+    fn timezone(&self) -> TimezoneMode {
+        match self {
+            Query::Grouped { timezone, .. } => *timezone,
+            Query::Normal { timezone, .. } => *timezone,
+            Query::Other { .. } => TimezoneMode::default(),
+        }
+    }
 }
 
 impl Query {
     pub fn to_sql(&self) -> (String, Vec<serde_json::Value>) {
+        let timezone = self.timezone();
         let mut conditions = {
             let mut whr = rsql_builder::B::new_where();
             for filter in self.filters() {
                 match filter {
-                    Filter::Like(f) => whr.like(f.field.into(), f.value()),
-                    Filter::NotLike(f) => whr.not_like(f.field.into(), f.value()),
+                    // A mail matches a tag if it is one of its tags
+                    Filter::Like(f) | Filter::Is(f) if f.is_untagged() => {
+                        whr.push_sql(SQL_HAS_NO_TAGS)
+                    }
+                    Filter::NotLike(f) if f.is_untagged() => whr.push_sql(SQL_HAS_TAGS),
+                    Filter::Like(f) | Filter::Is(f) if f.field == Field::MetaTags => {
+                        whr.push(SQL_HAS_TAG, f.value())
+                    }
+                    Filter::NotLike(f) if f.field == Field::MetaTags => {
+                        whr.push(SQL_HAS_NOT_TAG, f.value())
+                    }
+                    Filter::Contains(f) if f.field == Field::MetaTags => whr.push(
+                        SQL_HAS_TAG_CONTAINING,
+                        &format!("%{}%", f.to_string().to_lowercase()),
+                    ),
+                    Filter::Like(f) => whr.like(f.field.sql_expression(timezone), f.value()),
+                    Filter::NotLike(f) => whr.not_like(f.field.sql_expression(timezone), f.value()),
                     Filter::Contains(f) => whr.like(
-                        f.field.into(),
+                        f.field.sql_expression(timezone),
                         &format!("%{}%", f.to_string().to_lowercase()),
                     ),
-                    Filter::Is(f) => whr.eq(f.field.into(), f.value()),
+                    Filter::Is(f) => whr.eq(f.field.sql_expression(timezone), f.value()),
                 };
             }
             whr
         };
 
         let (header, group_by) = match self {
+            // Every mail counts once for each of its tags. Mails without tags count
+            // once, in the group without a tag name.
+            Query::Grouped {
+                group_by: Field::MetaTags,
+                ..
+            } => (
+                format!(
+                    "SELECT count(path) as {}, {} AS {} FROM emails LEFT JOIN email_tags ON email_tags.email_id = emails.rowid LEFT JOIN tags ON tags.id = email_tags.tag_id",
+                    AMOUNT_FIELD_NAME,
+                    "tags.name",
+                    Field::MetaTags.as_str()
+                ),
+                "GROUP BY tags.name".to_string(),
+            ),
             Query::Grouped { group_by, .. } => (
                 format!(
                     "SELECT count(path) as {}, {} FROM emails",
                     AMOUNT_FIELD_NAME,
-                    group_by.as_str()
+                    group_by.sql_selection(timezone)
                 ),
-                format!("GROUP BY {}", group_by.as_str()),
+                format!("GROUP BY {}", group_by.sql_expression(timezone)),
             ),
             Query::Normal { fields, range, .. } => {
-                let fields: Vec<&str> = fields.iter().map(|e| e.into()).collect();
+                let fields: Vec<String> =
+                    fields.iter().map(|e| e.sql_selection(timezone)).collect();
                 (
                     format!("SELECT {} FROM emails", fields.join(", ")),
                     format!("LIMIT {}, {}", range.start, range.end - range.start),
                 )
             }
+            Query::Other {
+                query: OtherQuery::All(Field::MetaTags),
+            } => (
+                format!(
+                    "SELECT json_array(name) AS {} FROM tags",
+                    Field::MetaTags.as_str()
+                ),
+                String::new(),
+            ),
             Query::Other {
                 query: OtherQuery::All(field),
             } => (
@@ -247,7 +447,60 @@ mod tests {
                 Filter::Is(ValueField::usize(&Field::Year, 2021)),
             ],
             group_by: Field::Month,
+            timezone: TimezoneMode::Utc,
         };
         dbg!(&query.to_sql());
     }
+
+    #[test]
+    fn test_sender_timezone() {
+        let query = Query::Grouped {
+            filters: vec![Filter::Is(ValueField::usize(&Field::Year, 2021))],
+            group_by: Field::Hour,
+            timezone: TimezoneMode::Sender,
+        };
+        let (sql, values) = query.to_sql();
+        assert!(
+            sql.contains("strftime('%H', timestamp + tz_offset, 'unixepoch') AS INTEGER) AS hour")
+        );
+        assert!(sql.contains("strftime('%Y', timestamp + tz_offset, 'unixepoch') AS INTEGER)=?"));
+        assert_eq!(values.len(), 1);
+    }
+
+    #[test]
+    fn test_tag_filters() {
+        let query = Query::Grouped {
+            filters: vec![
+                Filter::Like(ValueField::string(&Field::MetaTags, "Inbox")),
+                Filter::Is(ValueField::usize(&Field::Year, 2021)),
+            ],
+            group_by: Field::MetaTags,
+            timezone: TimezoneMode::Utc,
+        };
+        let (sql, values) = query.to_sql();
+        assert!(sql.contains("LEFT JOIN tags ON tags.id = email_tags.tag_id"));
+        assert!(sql.contains("AND tags.name = ?)"));
+        assert!(!sql.contains("meta_tags like"));
+        assert!(sql.ends_with("GROUP BY tags.name"));
+        assert_eq!(values.len(), 2);
+
+        let query = Query::Grouped {
+            filters: vec![Filter::NotLike(ValueField::untagged())],
+            group_by: Field::Year,
+            timezone: TimezoneMode::Utc,
+        };
+        let (sql, values) = query.to_sql();
+        assert!(sql.contains(SQL_HAS_TAGS));
+        assert!(values.is_empty());
+
+        // A tag with the name of the untagged segment is an ordinary tag
+        let query = Query::Grouped {
+            filters: vec![Filter::Like(ValueField::string(&Field::MetaTags, UNTAGGED))],
+            group_by: Field::Year,
+            timezone: TimezoneMode::Utc,
+        };
+        let (sql, values) = query.to_sql();
+        assert!(!sql.contains(SQL_HAS_NO_TAGS));
+        assert_eq!(values.len(), 1);
+    }
 }
@@ -0,0 +1,384 @@
+//! Export the data behind the current view
+//!
+//! Both the `Segments` of a [`crate::model::Segmentation`] and the `items` of the
+//! current drill-down can be converted into an [`ExportTable`], which can then
+//! be written as CSV, JSON Lines or (outside of the web) Parquet.
+//!
+//! ```ignore
+//! let table = ExportTable::from_segmentation(engine.segmentations().last().unwrap());
+//! export_table(&table, DataExportFormat::Csv, Path::new("segments.csv"))?;
+//! ```
+
+use eyre::Result;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::database::{query::Field, query_result::QueryRow};
+use crate::model::Segmentation;
+
+/// The fields that are exported for every item of a drill-down
+pub const EXPORT_FIELDS: &[Field] = &[
+    Field::Path,
+    Field::SenderName,
+    Field::SenderLocalPart,
+    Field::SenderDomain,
+    Field::SenderOrganization,
+    Field::Timestamp,
+    Field::Year,
+    Field::Month,
+    Field::Day,
+    Field::Hour,
+    Field::TzOffset,
+    Field::Subject,
+    Field::ToName,
+    Field::ToAddress,
+    Field::ToGroup,
+    Field::Source,
+    Field::IsReply,
+    Field::IsSend,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DataExportFormat {
+    Csv,
+    JsonLines,
+    #[cfg(not(target_arch = "wasm32"))]
+    Parquet,
+}
+
+impl DataExportFormat {
+    pub fn all_cases() -> &'static [DataExportFormat] {
+        &[
+            DataExportFormat::Csv,
+            DataExportFormat::JsonLines,
+            #[cfg(not(target_arch = "wasm32"))]
+            DataExportFormat::Parquet,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataExportFormat::Csv => "CSV",
+            DataExportFormat::JsonLines => "JSON Lines",
+            #[cfg(not(target_arch = "wasm32"))]
+            DataExportFormat::Parquet => "Parquet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DataExportFormat::Csv => "csv",
+            DataExportFormat::JsonLines => "jsonl",
+            #[cfg(not(target_arch = "wasm32"))]
+            DataExportFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// A simple table of named columns. Every row has one value per column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl ExportTable {
+    /// One row per `Segment` with its value, count and the percentage of
+    /// all items of the `Segmentation`. The range of the `Segmentation` is ignored.
+    pub fn from_segmentation(segmentation: &Segmentation) -> Self {
+        let total = segmentation.element_count();
+        let rows = segmentation
+            .segments()
+            .iter()
+            .map(|segment| {
+                let percent = if total == 0 {
+                    0.0
+                } else {
+                    segment.count as f64 / total as f64 * 100.0
+                };
+                vec![
+                    segment.field.value().clone(),
+                    Value::from(segment.count),
+                    Value::from(percent),
+                ]
+            })
+            .collect();
+        ExportTable {
+            columns: vec![
+                "value".to_string(),
+                "count".to_string(),
+                "percent".to_string(),
+            ],
+            rows,
+        }
+    }
+
+    /// One row per item with one column per field. Fields missing from
+    /// a row are exported as `null`.
+    pub fn from_rows(fields: &[Field], rows: &[QueryRow]) -> Self {
+        let rows = rows
+            .iter()
+            .map(|row| {
+                fields
+                    .iter()
+                    .map(|field| {
+                        row.get(field)
+                            .map(|value| value.value().clone())
+                            .unwrap_or(Value::Null)
+                    })
+                    .collect()
+            })
+            .collect();
+        ExportTable {
+            columns: fields.iter().map(|f| f.as_str().to_string()).collect(),
+            rows,
+        }
+    }
+
+    /// Write the table as CSV with a header row
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<()> {
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(&self.columns)?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(plain_string))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the table as one JSON object per line, with the keys in column order
+    pub fn write_json_lines<W: Write>(&self, mut writer: W) -> Result<()> {
+        for row in &self.rows {
+            let object = JsonRow {
+                columns: &self.columns,
+                values: row,
+            };
+            serde_json::to_writer(&mut writer, &object)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the table as a Parquet file with a single row group.
+    /// The column types are inferred from the values.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn write_parquet(&self, file: File) -> Result<()> {
+        parquet_export::write(self, file)
+    }
+}
+
+/// A row as a JSON object. A `serde_json::Map` would sort the keys.
+struct JsonRow<'a> {
+    columns: &'a [String],
+    values: &'a [Value],
+}
+
+impl<'a> Serialize for JsonRow<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.columns.len()))?;
+        for (column, value) in self.columns.iter().zip(self.values) {
+            map.serialize_entry(column, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Write `table` into a file at `path`, replacing an existing file.
+/// The save dialogs already asked the user to confirm the replacement.
+pub fn export_table(table: &ExportTable, format: DataExportFormat, path: &Path) -> Result<()> {
+    let file = File::create(path)?;
+    let result = match format {
+        DataExportFormat::Csv => table.write_csv(BufWriter::new(file)),
+        DataExportFormat::JsonLines => table.write_json_lines(BufWriter::new(file)),
+        #[cfg(not(target_arch = "wasm32"))]
+        DataExportFormat::Parquet => table.write_parquet(file),
+    };
+    // Don't leave a partially written file behind
+    if result.is_err() {
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
+
+/// Strings without quotes, arrays joined by commas, `null` as an empty value
+fn plain_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(values) => values
+            .iter()
+            .map(plain_string)
+            .collect::<Vec<_>>()
+            .join(","),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod parquet_export {
+    use eyre::{eyre, Result};
+    use parquet::data_type::ByteArray;
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::{FileWriter, SerializedFileWriter};
+    use parquet::{column::writer::ColumnWriter, schema::parser::parse_message_type};
+    use serde_json::Value;
+
+    use std::fs::File;
+    use std::sync::Arc;
+
+    use super::{plain_string, ExportTable};
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    enum ColumnType {
+        Boolean,
+        Int64,
+        Double,
+        Utf8,
+    }
+
+    impl ColumnType {
+        /// The narrowest type that can hold all non-null values of a column
+        fn infer<'a>(values: impl Iterator<Item = &'a Value>) -> ColumnType {
+            let mut current: Option<ColumnType> = None;
+            for value in values {
+                let next = match value {
+                    Value::Null => continue,
+                    Value::Bool(_) => ColumnType::Boolean,
+                    Value::Number(n) if n.is_i64() => ColumnType::Int64,
+                    Value::Number(_) => ColumnType::Double,
+                    _ => ColumnType::Utf8,
+                };
+                current = Some(match (current, next) {
+                    (None, next) => next,
+                    (Some(a), b) if a == b => a,
+                    (Some(ColumnType::Int64), ColumnType::Double)
+                    | (Some(ColumnType::Double), ColumnType::Int64) => ColumnType::Double,
+                    _ => ColumnType::Utf8,
+                });
+            }
+            current.unwrap_or(ColumnType::Utf8)
+        }
+
+        fn schema(&self) -> &'static str {
+            match self {
+                ColumnType::Boolean => "BOOLEAN",
+                ColumnType::Int64 => "INT64",
+                ColumnType::Double => "DOUBLE",
+                ColumnType::Utf8 => "BYTE_ARRAY",
+            }
+        }
+    }
+
+    pub fn write(table: &ExportTable, file: File) -> Result<()> {
+        let types: Vec<ColumnType> = (0..table.columns.len())
+            .map(|index| ColumnType::infer(table.rows.iter().map(|row| &row[index])))
+            .collect();
+        let columns: Vec<String> = table
+            .columns
+            .iter()
+            .zip(types.iter())
+            .map(|(name, column_type)| match column_type {
+                ColumnType::Utf8 => format!("OPTIONAL BYTE_ARRAY {} (UTF8);", name),
+                _ => format!("OPTIONAL {} {};", column_type.schema(), name),
+            })
+            .collect();
+        let schema = parse_message_type(&format!("message export {{ {} }}", columns.join(" ")))?;
+        // The dictionary encoder of this parquet version performs unaligned reads
+        // which panic in debug builds, so plain encoding is used.
+        let properties = Arc::new(
+            WriterProperties::builder()
+                .set_dictionary_enabled(false)
+                .build(),
+        );
+        let mut writer = SerializedFileWriter::new(file, Arc::new(schema), properties)?;
+
+        let mut row_group = writer.next_row_group()?;
+        let mut index = 0;
+        while let Some(mut column) = row_group.next_column()? {
+            let values = table.rows.iter().map(|row| &row[index]);
+            // Optional columns use a definition level of 0 for `null`
+            let levels: Vec<i16> = values.clone().map(|v| (!v.is_null()) as i16).collect();
+            let values = values.filter(|v| !v.is_null());
+            match (&mut column, types[index]) {
+                (ColumnWriter::BoolColumnWriter(w), ColumnType::Boolean) => {
+                    let values: Vec<bool> = values.filter_map(Value::as_bool).collect();
+                    w.write_batch(&values, Some(&levels), None)?;
+                }
+                (ColumnWriter::Int64ColumnWriter(w), ColumnType::Int64) => {
+                    let values: Vec<i64> = values.filter_map(Value::as_i64).collect();
+                    w.write_batch(&values, Some(&levels), None)?;
+                }
+                (ColumnWriter::DoubleColumnWriter(w), ColumnType::Double) => {
+                    let values: Vec<f64> = values.filter_map(Value::as_f64).collect();
+                    w.write_batch(&values, Some(&levels), None)?;
+                }
+                (ColumnWriter::ByteArrayColumnWriter(w), ColumnType::Utf8) => {
+                    let values: Vec<ByteArray> = values
+                        .map(|v| ByteArray::from(plain_string(v).into_bytes()))
+                        .collect();
+                    w.write_batch(&values, Some(&levels), None)?;
+                }
+                _ => {
+                    return Err(eyre!(
+                        "Unexpected column writer for {}",
+                        table.columns[index]
+                    ))
+                }
+            }
+            row_group.close_column(column)?;
+            index += 1;
+        }
+        writer.close_row_group(row_group)?;
+        writer.close()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::query::ValueField;
+    use crate::model::Segment;
+
+    fn segmentation() -> Segmentation {
+        let segment = |value: &str, count: usize| Segment {
+            field: ValueField::string(&Field::SenderDomain, value),
+            count,
+            rect: treemap::Rect::new(),
+        };
+        Segmentation::new(vec![segment("a.com", 3), segment("b.com", 1)])
+    }
+
+    #[test]
+    fn test_segmentation_csv() {
+        let table = ExportTable::from_segmentation(&segmentation());
+        let mut output = Vec::new();
+        table.write_csv(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "value,count,percent\na.com,3,75.0\nb.com,1,25.0\n"
+        );
+    }
+
+    #[test]
+    fn test_rows_json_lines() {
+        let mut row = QueryRow::new();
+        row.insert(
+            Field::Subject,
+            ValueField::string(&Field::Subject, "Hi, \"you\""),
+        );
+        row.insert(Field::IsSend, ValueField::bool(&Field::IsSend, true));
+        let table = ExportTable::from_rows(&[Field::Subject, Field::IsSend, Field::Year], &[row]);
+        let mut output = Vec::new();
+        table.write_json_lines(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"subject\":\"Hi, \\\"you\\\"\",\"is_send\":true,\"year\":null}\n"
+        );
+    }
+}
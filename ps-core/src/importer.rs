@@ -1,18 +1,138 @@
 use eyre::Result;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread::JoinHandle;
 
 use crate::DatabaseLike;
 
-pub trait Importerlike {
+pub trait Importerlike: Sized {
     fn import<Database: DatabaseLike + 'static>(
         self,
         database: Database,
+    ) -> Result<(MessageReceiver, JoinHandle<Result<()>>)> {
+        self.cancellable_import(database, CancellationToken::default())
+    }
+
+    /// Like `import`, but the import can be paused or cancelled with `token`.
+    /// A cancelled import removes everything it wrote to the database.
+    fn cancellable_import<Database: DatabaseLike + 'static>(
+        self,
+        database: Database,
+        token: CancellationToken,
     ) -> Result<(MessageReceiver, JoinHandle<Result<()>>)>;
 }
 
+/// The error returned by an importer once it was cancelled
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The import was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    paused: Mutex<bool>,
+    resumed: Condvar,
+}
+
+/// Shared between the UI and a running import in order to pause, resume or
+/// cancel it. The importer calls [`CancellationToken::checkpoint`] between units of work.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<TokenState>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        // Wake up paused workers so that they can stop
+        self.resume();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn pause(&self) {
+        if let Ok(mut paused) = self.state.paused.lock() {
+            *paused = true;
+        }
+    }
+
+    pub fn resume(&self) {
+        if let Ok(mut paused) = self.state.paused.lock() {
+            *paused = false;
+        }
+        self.state.resumed.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.paused.lock().map(|p| *p).unwrap_or(false)
+    }
+
+    /// Blocks while the import is paused.
+    /// Returns a [`Cancelled`] error once the import was cancelled.
+    pub fn checkpoint(&self) -> Result<()> {
+        if let Ok(mut paused) = self.state.paused.lock() {
+            while *paused && !self.is_cancelled() {
+                paused = match self.state.resumed.wait(paused) {
+                    Ok(n) => n,
+                    Err(_) => break,
+                };
+            }
+        }
+        if self.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+}
+
+/// The phases of an import, in the order in which they happen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImportPhase {
+    /// Finding the folders and files that contain mails
+    Discovering,
+    /// Reading the mails from disk
+    Reading,
+    /// Parsing the mails and sending them to the database
+    Parsing,
+    /// Waiting for the database to write everything to disk
+    Writing,
+    /// Creating the database indexes
+    Indexing,
+}
+
+impl ImportPhase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportPhase::Discovering => "Discovering",
+            ImportPhase::Reading => "Reading",
+            ImportPhase::Parsing => "Parsing",
+            ImportPhase::Writing => "Writing",
+            ImportPhase::Indexing => "Indexing",
+        }
+    }
+}
+
 /// The message that informs of the importers progress
 #[derive(Debug)]
 pub enum Message {
+    /// The import entered a new phase
+    Phase(ImportPhase),
+    /// The file or folder that is currently being read
+    CurrentFile(PathBuf),
     /// How much progress are we making on reading the contents
     /// of the emails.
     /// The `usize` parameter marks the total amount of items to read - if it is known.
@@ -20,18 +140,20 @@ pub enum Message {
     /// A Gmail backup will list the folders and how many of them
     /// are already read. A mbox format will list other things as there
     /// no folders.
+    /// When several sources are imported, this is sent once per source
+    /// with the amount of items of that source.
     ReadTotal(usize),
     /// Whenever an item out of the total is read, this message will be emitted
     ReadOne,
     /// Similar to [`Message::ReadTotal`]
     WriteTotal(usize),
-    /// Similar to `ReadOne`
-    WriteOne,
-    /// Once everything has been written, we need to wait for the database
-    /// to sync
-    FinishingUp,
+    /// Similar to `ReadOne`. Contains the file the mail was read from and
+    /// the size of the mail in bytes.
+    WriteOne { path: PathBuf, bytes: usize },
     /// Finally, this indicates that we're done.
     Done,
+    /// The import was cancelled and everything written so far was removed
+    Cancelled,
     /// An error happened during processing
     Error(eyre::Report),
     /// A special case for macOS, where a permission error means we have to grant this app
@@ -42,3 +164,26 @@ pub enum Message {
 
 pub type MessageSender = crossbeam_channel::Sender<Message>;
 pub type MessageReceiver = crossbeam_channel::Receiver<Message>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        assert!(token.checkpoint().is_ok());
+
+        token.pause();
+        let worker = {
+            let token = token.clone();
+            std::thread::spawn(move || token.checkpoint())
+        };
+        // The worker stays blocked until it is cancelled
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!worker.is_finished());
+        token.cancel();
+        let result = worker.join().unwrap();
+        assert!(result.unwrap_err().downcast_ref::<Cancelled>().is_some());
+    }
+}
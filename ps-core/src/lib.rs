@@ -13,6 +13,11 @@
 //! Also, the required traits to implement a generic database type for the importer
 //! and the gui.
 //!
+//! ## export
+//!
+//! Write the `Segments` of a `Segmentation` or the items of the current drill-down
+//! as CSV, JSON Lines or Parquet.
+//!
 //! ## importer
 //!
 //! Types and traits that define how a data importer works. Types conforming to these
@@ -44,6 +49,7 @@
 //! egui. See: <https://github.com/terhechte/postsack/issues/11>
 
 mod database;
+pub mod export;
 mod importer;
 pub mod message_adapter;
 pub mod model;
@@ -51,10 +57,18 @@ mod types;
 
 pub use database::database_like::{DatabaseLike, DatabaseQuery};
 pub use database::db_message::DBMessage;
-pub use database::query::{Field, Filter, OtherQuery, Query, ValueField, AMOUNT_FIELD_NAME};
+pub use database::query::{
+    Field, Filter, OtherQuery, Query, TimezoneMode, ValueField, AMOUNT_FIELD_NAME, UNTAGGED,
+};
 pub use database::query_result::{QueryResult, QueryRow};
-pub use importer::{Importerlike, Message, MessageReceiver, MessageSender};
-pub use types::{Config, EmailEntry, EmailMeta, FormatType};
+pub use importer::{
+    CancellationToken, Cancelled, ImportPhase, Importerlike, Message, MessageReceiver,
+    MessageSender,
+};
+pub use types::{
+    Attachment, Config, EmailEntry, EmailMeta, EmailPreview, FormatType, ImportError,
+    ImportErrorKind, ImportFilter, MailLocation, ParseQuality, Source, TagSource,
+};
 
 // Re-Export some dependencies so they don't
 // need to be listed again in other Cargo tomls
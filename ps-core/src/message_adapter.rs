@@ -1,9 +1,11 @@
 use eyre::{bail, eyre, Report, Result};
 
+use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
-use crate::{DatabaseLike, Importerlike, Message};
+use crate::{CancellationToken, DatabaseLike, ImportPhase, Importerlike, Message};
 
 #[derive(Debug, Default)]
 struct Data {
@@ -11,8 +13,16 @@ struct Data {
     read: usize,
     total_write: usize,
     write: usize,
+    /// The bytes of all written mails
+    written_bytes: usize,
+    /// The finished phases and their duration
+    phases: Vec<(ImportPhase, Duration)>,
+    /// The current phase and when it started
+    phase: Option<(ImportPhase, Instant)>,
+    current_file: Option<PathBuf>,
     finishing: bool,
     done: bool,
+    cancelled: bool,
     error: Option<Report>,
     #[cfg(target_os = "macos")]
     missing_permissions: bool,
@@ -24,10 +34,126 @@ pub struct Progress {
     pub count: usize,
 }
 
+/// Timing and throughput information of a running import
+#[derive(Clone, Debug, Default)]
+pub struct Statistics {
+    pub phase: Option<ImportPhase>,
+    /// The elapsed time of each phase so far, including the current one
+    pub phases: Vec<(ImportPhase, Duration)>,
+    /// The file or folder that is currently processed
+    pub current_file: Option<PathBuf>,
+    /// Parsed mails per second
+    pub mails_per_second: f64,
+    /// Parsed bytes per second
+    pub bytes_per_second: f64,
+    /// The estimated remaining time of the current phase, if it can be estimated
+    pub eta: Option<Duration>,
+}
+
+impl Statistics {
+    /// The total duration of all phases
+    pub fn elapsed(&self) -> Duration {
+        self.phases.iter().map(|(_, duration)| *duration).sum()
+    }
+
+    /// E.g. `1,200 mails/s, 4.5 MB/s`
+    pub fn throughput_label(&self) -> Option<String> {
+        if self.mails_per_second <= 0.0 {
+            return None;
+        }
+        Some(format!(
+            "{:.0} mails/s, {:.1} MB/s",
+            self.mails_per_second,
+            self.bytes_per_second / 1_000_000.0
+        ))
+    }
+
+    /// E.g. `About 3m 20s remaining`
+    pub fn eta_label(&self) -> Option<String> {
+        self.eta
+            .map(|eta| format!("About {} remaining", format_duration(eta)))
+    }
+
+    /// E.g. `Discovering 0.1s, Reading 12s, Parsing 1m 03s`
+    pub fn phases_label(&self) -> String {
+        self.phases
+            .iter()
+            .map(|(phase, duration)| format!("{} {}", phase.name(), format_duration(*duration)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// A short human readable duration, e.g. `0.4s`, `12s`, `3m 20s` or `1h 05m`
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=9 => format!("{:.1}s", duration.as_secs_f64()),
+        10..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3600, (seconds % 3600) / 60),
+    }
+}
+
+impl Data {
+    fn enter(&mut self, phase: ImportPhase) {
+        let now = Instant::now();
+        if let Some((previous, started)) = self.phase.take() {
+            self.phases.push((previous, now - started));
+        }
+        self.phase = Some((phase, now));
+    }
+
+    /// Close the current phase
+    fn finish(&mut self) {
+        if let Some((previous, started)) = self.phase.take() {
+            self.phases.push((previous, started.elapsed()));
+        }
+        self.current_file = None;
+    }
+
+    fn statistics(&self) -> Statistics {
+        let mut phases = self.phases.clone();
+        let mut statistics = Statistics {
+            current_file: self.current_file.clone(),
+            ..Default::default()
+        };
+        if let Some((phase, started)) = self.phase {
+            let elapsed = started.elapsed();
+            phases.push((phase, elapsed));
+            statistics.phase = Some(phase);
+            let seconds = elapsed.as_secs_f64();
+            // Estimate the remaining time from the progress of the current phase
+            let (done, total) = match phase {
+                ImportPhase::Reading => (self.read, self.total_read),
+                ImportPhase::Parsing => (self.write, self.total_write),
+                _ => (0, 0),
+            };
+            if done > 0 && total > done && seconds > 0.0 {
+                let remaining = (total - done) as f64 * seconds / done as f64;
+                statistics.eta = Some(Duration::from_secs_f64(remaining));
+            }
+        }
+        let parsing: Duration = phases
+            .iter()
+            .filter(|(phase, _)| *phase == ImportPhase::Parsing)
+            .map(|(_, duration)| *duration)
+            .sum();
+        if parsing.as_secs_f64() > 0.0 {
+            statistics.mails_per_second = self.write as f64 / parsing.as_secs_f64();
+            statistics.bytes_per_second = self.written_bytes as f64 / parsing.as_secs_f64();
+        }
+        statistics.phases = phases;
+        statistics
+    }
+}
+
 #[derive(Clone, Debug, Copy)]
 pub struct State {
     pub finishing: bool,
     pub done: bool,
+    /// The import was cancelled. `done` is also set in this case.
+    pub cancelled: bool,
     pub written: usize,
     #[cfg(target_os = "macos")]
     pub missing_permissions: bool,
@@ -39,6 +165,7 @@ pub struct State {
 pub struct Adapter {
     producer_lock: Arc<RwLock<Data>>,
     consumer_lock: Arc<RwLock<Data>>,
+    token: CancellationToken,
 }
 
 impl Adapter {
@@ -53,6 +180,7 @@ impl Adapter {
         Self {
             producer_lock,
             consumer_lock,
+            token: CancellationToken::new(),
         }
     }
 
@@ -63,7 +191,7 @@ impl Adapter {
         database: Database,
         importer: Importer,
     ) -> Result<JoinHandle<Result<()>>> {
-        let (receiver, handle) = importer.import(database)?;
+        let (receiver, handle) = importer.cancellable_import(database, self.token.clone())?;
         let lock = self.producer_lock.clone();
         let handle = std::thread::spawn(move || {
             'outer: loop {
@@ -73,7 +201,8 @@ impl Adapter {
                 };
                 for entry in receiver.try_iter() {
                     match entry {
-                        Message::ReadTotal(n) => write_guard.total_read = n,
+                        // Totals are sent per source, so they add to what was already processed
+                        Message::ReadTotal(n) => write_guard.total_read = write_guard.read + n,
                         Message::ReadOne => {
                             write_guard.read += 1;
                             // Depending on the implementation, we may receive read calls before
@@ -83,11 +212,29 @@ impl Adapter {
                                 write_guard.total_read = write_guard.read + 1;
                             }
                         }
-                        Message::WriteTotal(n) => write_guard.total_write = n,
-                        Message::WriteOne => write_guard.write += 1,
-                        Message::FinishingUp => write_guard.finishing = true,
+                        Message::WriteTotal(n) => write_guard.total_write = write_guard.write + n,
+                        Message::WriteOne { path, bytes } => {
+                            write_guard.write += 1;
+                            write_guard.written_bytes += bytes;
+                            write_guard.current_file = Some(path);
+                        }
+                        Message::Phase(phase) => {
+                            if matches!(phase, ImportPhase::Writing | ImportPhase::Indexing) {
+                                write_guard.finishing = true;
+                            }
+                            write_guard.current_file = None;
+                            write_guard.enter(phase);
+                        }
+                        Message::CurrentFile(path) => write_guard.current_file = Some(path),
                         Message::Done => {
                             write_guard.done = true;
+                            write_guard.finish();
+                            break 'outer;
+                        }
+                        Message::Cancelled => {
+                            write_guard.cancelled = true;
+                            write_guard.done = true;
+                            write_guard.finish();
                             break 'outer;
                         }
                         Message::Error(e) => {
@@ -129,12 +276,35 @@ impl Adapter {
         Ok(State {
             finishing: item.finishing,
             done: item.done,
+            cancelled: item.cancelled,
             written: item.write,
             #[cfg(target_os = "macos")]
             missing_permissions: item.missing_permissions,
         })
     }
 
+    pub fn statistics(&self) -> Result<Statistics> {
+        let item = self.consumer_lock.read().map_err(|e| eyre!("{:?}", &e))?;
+        Ok(item.statistics())
+    }
+
+    /// Stop the import. The database will not contain any of the imported mails.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn pause(&self) {
+        self.token.pause();
+    }
+
+    pub fn resume(&self) {
+        self.token.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.token.is_paused()
+    }
+
     pub fn error(&self) -> Result<Option<Report>> {
         // We take the error of out of the write lock only if there is an error.
         let item = self.consumer_lock.read().map_err(|e| eyre!("{:?}", &e))?;
@@ -148,3 +318,39 @@ impl Adapter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statistics() {
+        let mut data = Data::default();
+        data.enter(ImportPhase::Discovering);
+        data.enter(ImportPhase::Parsing);
+        data.total_write = 100;
+        data.write = 25;
+        data.written_bytes = 25_000;
+        // Pretend that the parsing started a second ago
+        data.phase = Some((
+            ImportPhase::Parsing,
+            Instant::now() - Duration::from_secs(1),
+        ));
+
+        let statistics = data.statistics();
+        assert_eq!(statistics.phase, Some(ImportPhase::Parsing));
+        assert_eq!(statistics.phases.len(), 2);
+        assert!(statistics.mails_per_second > 20.0 && statistics.mails_per_second <= 25.0);
+        // 75 more mails at ~25 mails/s
+        let eta = statistics.eta.unwrap().as_secs_f64();
+        assert!(eta > 2.5 && eta < 3.5);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(400)), "0.4s");
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(200)), "3m 20s");
+        assert_eq!(format_duration(Duration::from_secs(3900)), "1h 05m");
+    }
+}
@@ -0,0 +1,364 @@
+//! The network of who writes to whom, for tools like Gephi or NetworkX.
+//!
+//! Every address is a node, every sender -> recipient pair of a mail an edge.
+//! The graph is built from the items of the current drill-down (see
+//! [`super::items::items_query_with_fields`] with [`GRAPH_FIELDS`]), so it
+//! respects the active filters, and can be written as GraphML, GEXF or a JSON edge list.
+
+use chrono::prelude::*;
+use eyre::Result;
+use serde_json::{json, Value};
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::database::query::Field;
+use crate::database::query_result::QueryRow;
+
+/// The fields needed to build a [`ContactGraph`]
+pub const GRAPH_FIELDS: &[Field] = &[
+    Field::SenderLocalPart,
+    Field::SenderDomain,
+    Field::Recipients,
+    Field::Timestamp,
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContactNode {
+    /// The mails sent by this address
+    pub sent: usize,
+    /// The mails received by this address
+    pub received: usize,
+}
+
+impl ContactNode {
+    pub fn count(&self) -> usize {
+        self.sent + self.received
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContactEdge {
+    /// The amount of mails from the sender to the recipient
+    pub weight: usize,
+    /// The unix timestamp of the first mail
+    pub first: i64,
+    /// The unix timestamp of the last mail
+    pub last: i64,
+}
+
+/// A directed graph of addresses. Addresses are lowercased.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContactGraph {
+    pub nodes: BTreeMap<String, ContactNode>,
+    /// Keyed by (sender, recipient)
+    pub edges: BTreeMap<(String, String), ContactEdge>,
+}
+
+impl ContactGraph {
+    /// Build the graph from rows with the [`GRAPH_FIELDS`]
+    pub fn from_rows(rows: &[QueryRow]) -> Self {
+        let mut graph = ContactGraph::default();
+        for row in rows {
+            let string = |field: &Field| {
+                row.get(field)
+                    .map(|value| value.to_string())
+                    .unwrap_or_default()
+            };
+            let sender = format!(
+                "{}@{}",
+                string(&Field::SenderLocalPart),
+                string(&Field::SenderDomain)
+            )
+            .to_lowercase();
+            let timestamp = row
+                .get(&Field::Timestamp)
+                .and_then(|value| value.value().as_i64())
+                .unwrap_or_default();
+            let recipients: Vec<String> = match row.get(&Field::Recipients).map(|e| e.value()) {
+                Some(Value::Array(recipients)) => recipients
+                    .iter()
+                    .filter_map(|e| e.as_str())
+                    .map(|e| e.to_lowercase())
+                    .collect(),
+                _ => Vec::new(),
+            };
+            graph.add_mail(sender, &recipients, timestamp);
+        }
+        graph
+    }
+
+    fn add_mail(&mut self, sender: String, recipients: &[String], timestamp: i64) {
+        self.nodes.entry(sender.clone()).or_default().sent += 1;
+        for recipient in recipients {
+            self.nodes.entry(recipient.clone()).or_default().received += 1;
+            let edge = self
+                .edges
+                .entry((sender.clone(), recipient.clone()))
+                .or_insert(ContactEdge {
+                    weight: 0,
+                    first: timestamp,
+                    last: timestamp,
+                });
+            edge.weight += 1;
+            edge.first = edge.first.min(timestamp);
+            edge.last = edge.last.max(timestamp);
+        }
+    }
+
+    /// Write the graph as GraphML
+    pub fn write_graphml<W: Write>(&self, mut writer: W) -> Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        for (id, target, name, kind) in [
+            ("count", "node", "count", "int"),
+            ("sent", "node", "sent", "int"),
+            ("received", "node", "received", "int"),
+            ("weight", "edge", "weight", "int"),
+            ("first", "edge", "first", "string"),
+            ("last", "edge", "last", "string"),
+        ] {
+            writeln!(
+                writer,
+                r#"  <key id="{}" for="{}" attr.name="{}" attr.type="{}"/>"#,
+                id, target, name, kind
+            )?;
+        }
+        writeln!(writer, r#"  <graph id="contacts" edgedefault="directed">"#)?;
+        for (address, node) in &self.nodes {
+            writeln!(writer, r#"    <node id="{}">"#, escape(address))?;
+            writeln!(writer, r#"      <data key="count">{}</data>"#, node.count())?;
+            writeln!(writer, r#"      <data key="sent">{}</data>"#, node.sent)?;
+            writeln!(
+                writer,
+                r#"      <data key="received">{}</data>"#,
+                node.received
+            )?;
+            writeln!(writer, "    </node>")?;
+        }
+        for ((source, target), edge) in &self.edges {
+            writeln!(
+                writer,
+                r#"    <edge source="{}" target="{}">"#,
+                escape(source),
+                escape(target)
+            )?;
+            writeln!(writer, r#"      <data key="weight">{}</data>"#, edge.weight)?;
+            writeln!(
+                writer,
+                r#"      <data key="first">{}</data>"#,
+                date(edge.first)
+            )?;
+            writeln!(
+                writer,
+                r#"      <data key="last">{}</data>"#,
+                date(edge.last)
+            )?;
+            writeln!(writer, "    </edge>")?;
+        }
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")?;
+        Ok(())
+    }
+
+    /// Write the graph as GEXF 1.2
+    pub fn write_gexf<W: Write>(&self, mut writer: W) -> Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<gexf xmlns="http://www.gexf.net/1.2draft" version="1.2">"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <graph mode="static" defaultedgetype="directed">"#
+        )?;
+        writeln!(writer, r#"    <attributes class="node">"#)?;
+        writeln!(
+            writer,
+            r#"      <attribute id="0" title="count" type="integer"/>"#
+        )?;
+        writeln!(
+            writer,
+            r#"      <attribute id="1" title="sent" type="integer"/>"#
+        )?;
+        writeln!(
+            writer,
+            r#"      <attribute id="2" title="received" type="integer"/>"#
+        )?;
+        writeln!(writer, "    </attributes>")?;
+        writeln!(writer, r#"    <attributes class="edge">"#)?;
+        writeln!(
+            writer,
+            r#"      <attribute id="0" title="first" type="string"/>"#
+        )?;
+        writeln!(
+            writer,
+            r#"      <attribute id="1" title="last" type="string"/>"#
+        )?;
+        writeln!(writer, "    </attributes>")?;
+        writeln!(writer, "    <nodes>")?;
+        for (address, node) in &self.nodes {
+            let address = escape(address);
+            writeln!(
+                writer,
+                r#"      <node id="{}" label="{}">"#,
+                address, address
+            )?;
+            writeln!(writer, "        <attvalues>")?;
+            for (id, value) in [(0, node.count()), (1, node.sent), (2, node.received)] {
+                writeln!(
+                    writer,
+                    r#"          <attvalue for="{}" value="{}"/>"#,
+                    id, value
+                )?;
+            }
+            writeln!(writer, "        </attvalues>")?;
+            writeln!(writer, "      </node>")?;
+        }
+        writeln!(writer, "    </nodes>")?;
+        writeln!(writer, "    <edges>")?;
+        for (index, ((source, target), edge)) in self.edges.iter().enumerate() {
+            writeln!(
+                writer,
+                r#"      <edge id="{}" source="{}" target="{}" weight="{}">"#,
+                index,
+                escape(source),
+                escape(target),
+                edge.weight
+            )?;
+            writeln!(writer, "        <attvalues>")?;
+            writeln!(
+                writer,
+                r#"          <attvalue for="0" value="{}"/>"#,
+                date(edge.first)
+            )?;
+            writeln!(
+                writer,
+                r#"          <attvalue for="1" value="{}"/>"#,
+                date(edge.last)
+            )?;
+            writeln!(writer, "        </attvalues>")?;
+            writeln!(writer, "      </edge>")?;
+        }
+        writeln!(writer, "    </edges>")?;
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</gexf>")?;
+        Ok(())
+    }
+
+    /// Write the edges as a JSON array of
+    /// `{"source", "target", "weight", "first", "last"}` objects
+    pub fn write_json_edges<W: Write>(&self, writer: W) -> Result<()> {
+        let edges: Vec<Value> = self
+            .edges
+            .iter()
+            .map(|((source, target), edge)| {
+                json!({
+                    "source": source,
+                    "target": target,
+                    "weight": edge.weight,
+                    "first": date(edge.first),
+                    "last": date(edge.last),
+                })
+            })
+            .collect();
+        serde_json::to_writer_pretty(writer, &edges)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GraphExportFormat {
+    GraphMl,
+    Gexf,
+    JsonEdges,
+}
+
+impl GraphExportFormat {
+    pub fn all_cases() -> &'static [GraphExportFormat] {
+        &[
+            GraphExportFormat::GraphMl,
+            GraphExportFormat::Gexf,
+            GraphExportFormat::JsonEdges,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GraphExportFormat::GraphMl => "GraphML",
+            GraphExportFormat::Gexf => "GEXF",
+            GraphExportFormat::JsonEdges => "JSON Edges",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            GraphExportFormat::GraphMl => "graphml",
+            GraphExportFormat::Gexf => "gexf",
+            GraphExportFormat::JsonEdges => "json",
+        }
+    }
+}
+
+/// Write `graph` into a new file at `path`
+pub fn export_graph(graph: &ContactGraph, format: GraphExportFormat, path: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    match format {
+        GraphExportFormat::GraphMl => graph.write_graphml(&mut writer)?,
+        GraphExportFormat::Gexf => graph.write_gexf(&mut writer)?,
+        GraphExportFormat::JsonEdges => graph.write_json_edges(&mut writer)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// A unix timestamp as RFC 3339 date in UTC
+fn date(timestamp: i64) -> String {
+    Utc.timestamp(timestamp, 0)
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Escape a string for XML attributes and text
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contact_graph() {
+        let mut graph = ContactGraph::default();
+        let recipients = vec!["b@example.com".to_string(), "c&d@example.com".to_string()];
+        graph.add_mail("a@example.com".to_string(), &recipients, 86400);
+        graph.add_mail("a@example.com".to_string(), &recipients[..1], 0);
+        assert_eq!(graph.nodes["a@example.com"].sent, 2);
+        assert_eq!(graph.nodes["b@example.com"].received, 2);
+        let edge = &graph.edges[&("a@example.com".to_string(), "b@example.com".to_string())];
+        assert_eq!((edge.weight, edge.first, edge.last), (2, 0, 86400));
+
+        let mut output = Vec::new();
+        graph.write_graphml(&mut output).unwrap();
+        let graphml = String::from_utf8(output).unwrap();
+        assert!(graphml.contains(r#"<node id="c&amp;d@example.com">"#));
+        assert!(graphml.contains(r#"<data key="first">1970-01-01T00:00:00Z</data>"#));
+
+        let mut output = Vec::new();
+        graph.write_json_edges(&mut output).unwrap();
+        let edges: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(edges[0]["weight"], 2);
+        assert_eq!(edges[1]["target"], "c&d@example.com");
+        assert_eq!(edges[1]["last"], "1970-01-02T00:00:00Z");
+    }
+}
@@ -8,7 +8,7 @@ use eyre::{bail, Result};
 
 use lru::LruCache;
 
-use crate::database::query::{Field, Filter, OtherQuery, Query, ValueField};
+use crate::database::query::{Field, Filter, OtherQuery, Query, TimezoneMode, ValueField};
 use crate::model::link::Response;
 use crate::types::Config;
 
@@ -41,11 +41,15 @@ pub struct Engine {
     pub(super) segmentations: Vec<Segmentation>,
     /// Additional filters. See [`segmentations::set_filters`]
     pub(super) filters: Vec<Filter>,
+    /// The timezone used for date segmentations. See [`segmentations::set_timezone`]
+    pub(super) timezone: TimezoneMode,
     /// This is a very simple cache from ranges to rows.
     /// It doesn't account for overlapping ranges.
     /// There's a lot of room for improvement here.
     pub(super) item_cache: LruCache<usize, LoadingState>,
     pub(super) known_tags: Vec<String>,
+    /// Does one of the imported formats have flags. See [`crate::FormatType::has_flags`]
+    pub(super) has_flags: bool,
 }
 
 impl Engine {
@@ -62,8 +66,10 @@ impl Engine {
             group_by_stack: vec![default_group_by_stack(0).unwrap()],
             segmentations: Vec::new(),
             filters: Vec::new(),
+            timezone: TimezoneMode::default(),
             item_cache: LruCache::new(10000),
             known_tags: Vec::new(),
+            has_flags: config.all_sources().iter().any(|s| s.format.has_flags()),
         };
         Ok(engine)
     }
@@ -92,7 +98,12 @@ impl Engine {
     /// Information on the underlying `Format`. Does it have `seen` information
     pub fn format_has_seen(&self) -> bool {
         // FIXME: The current implementation just assumes that the existance of meta tags also implies is_seen
-        !self.known_tags.is_empty()
+        self.has_flags || !self.known_tags.is_empty()
+    }
+
+    /// Information on the underlying `Format`. Does it have flags such as flagged or replied
+    pub fn format_has_flags(&self) -> bool {
+        self.has_flags
     }
 
     /// All the known tags in the current emails
@@ -127,10 +138,17 @@ impl Engine {
             .map(|p| p.field.clone())
             .collect();
 
-        // Add the next group by
+        // Add the next group by. An organization is always followed by its domains.
         let index = self.group_by_stack.len();
-        let next = default_group_by_stack(index)
-            .ok_or_else(|| eyre::eyre!("default group by stack out of bounds"))?;
+        let next = match self.group_by_stack.last() {
+            Some(Field::SenderOrganization)
+                if !self.group_by_stack.contains(&Field::SenderDomain) =>
+            {
+                Field::SenderDomain
+            }
+            _ => default_group_by_stack(index)
+                .ok_or_else(|| eyre::eyre!("default group by stack out of bounds"))?,
+        };
         self.group_by_stack.push(next);
 
         // Block UI & Wait for updates
@@ -241,10 +259,11 @@ impl Engine {
 pub fn default_group_by_stack(index: usize) -> Option<Field> {
     match index {
         0 => Some(Field::Year),
-        1 => Some(Field::SenderDomain),
-        2 => Some(Field::SenderLocalPart),
-        3 => Some(Field::Month),
-        4 => Some(Field::Day),
+        1 => Some(Field::SenderOrganization),
+        2 => Some(Field::SenderDomain),
+        3 => Some(Field::SenderLocalPart),
+        4 => Some(Field::Month),
+        5 => Some(Field::Day),
         _ => None,
     }
 }
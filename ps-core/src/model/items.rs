@@ -54,7 +54,7 @@ pub fn items(engine: &mut Engine, range: Option<Range<usize>>) -> Result<Vec<Opt
     }
     // Only if at least some data is missing do we perform the request
     if missing_data && !range.is_empty() {
-        let request = make_query(engine, range);
+        let request = make_query(engine, range, ITEM_FIELDS);
         engine.link.request(&request, Action::LoadItems)?;
     }
     Ok(rows)
@@ -73,21 +73,53 @@ pub fn count(engine: &Engine) -> usize {
     segmentation.element_count()
 }
 
+/// A query for all the `items` in the current `Segmentation`
+///
+/// This can be used to retrieve the items in one go outside of the engine,
+/// e.g. to export them.
+///
+/// # Arguments
+///
+/// * `engine` - The engine to use for retrieving data
+pub fn items_query(engine: &Engine) -> Query {
+    make_query(engine, 0..count(engine), ITEM_FIELDS)
+}
+
+/// Like [`items_query`] but retrieving the given `fields` of each item
+///
+/// # Arguments
+///
+/// * `engine` - The engine to use for retrieving data
+/// * `fields` - The fields to retrieve
+pub fn items_query_with_fields(engine: &Engine, fields: &[Field]) -> Query {
+    make_query(engine, 0..count(engine), fields)
+}
+
+/// The fields retrieved for the `items`
+const ITEM_FIELDS: &[Field] = &[
+    Field::SenderDomain,
+    Field::SenderLocalPart,
+    Field::Subject,
+    Field::Path,
+    Field::Timestamp,
+    Field::MboxOffset,
+    Field::MboxLength,
+    Field::Source,
+];
+
 /// Make the query for retrieving items
-fn make_query(engine: &Engine, range: Range<usize>) -> Query {
+fn make_query(engine: &Engine, range: Range<usize>, fields: &[Field]) -> Query {
     let mut filters = Vec::new();
     for entry in &engine.search_stack {
         filters.push(Filter::Like(entry.clone()));
     }
+    for entry in &engine.filters {
+        filters.push(entry.clone());
+    }
     Query::Normal {
         filters,
-        fields: vec![
-            Field::SenderDomain,
-            Field::SenderLocalPart,
-            Field::Subject,
-            Field::Path,
-            Field::Timestamp,
-        ],
+        fields: fields.to_vec(),
         range,
+        timezone: engine.timezone,
     }
 }
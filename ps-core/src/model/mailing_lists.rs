@@ -0,0 +1,168 @@
+//! The "unsubscribe candidates" report: all mailing lists and newsletters
+//! by volume, together with the links to unsubscribe from them.
+
+use eyre::Result;
+use serde_json::Value;
+
+use std::collections::HashMap;
+
+use crate::database::database_like::DatabaseQuery;
+use crate::database::query::{Field, Filter, Query, ValueField};
+use crate::database::query_result::{QueryResult, QueryRow};
+use crate::export::ExportTable;
+
+const FIELDS: &[Field] = &[
+    Field::ListId,
+    Field::ListUnsubscribe,
+    Field::SenderLocalPart,
+    Field::SenderDomain,
+];
+
+/// A mailing list or newsletter. Mails without a `List-Id` are grouped by their sender.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailingList {
+    /// The `List-Id`, or the sender address if the mails have none
+    pub name: String,
+    /// The sender address of the first mail
+    pub sender: String,
+    pub count: usize,
+    /// The unique `mailto:` and `https:` unsubscribe links of all mails
+    pub unsubscribe: Vec<String>,
+}
+
+impl MailingList {
+    fn add(&mut self, links: &str) {
+        self.count += 1;
+        for link in links.split_whitespace() {
+            if !self.unsubscribe.iter().any(|e| e == link) {
+                self.unsubscribe.push(link.to_string());
+            }
+        }
+    }
+}
+
+/// All mailing lists of the database, the most mails first.
+/// `total` is the amount of mails in the database.
+pub fn mailing_lists(database: &dyn DatabaseQuery, total: usize) -> Result<Vec<MailingList>> {
+    let query = Query::Normal {
+        fields: FIELDS.to_vec(),
+        filters: vec![Filter::Is(ValueField::bool(&Field::IsBulk, true))],
+        range: 0..total,
+        timezone: Default::default(),
+    };
+    let rows: Vec<QueryRow> = database
+        .query(&query)?
+        .into_iter()
+        .filter_map(|result| match result {
+            QueryResult::Normal(row) => Some(row),
+            _ => None,
+        })
+        .collect();
+    Ok(from_rows(&rows))
+}
+
+fn from_rows(rows: &[QueryRow]) -> Vec<MailingList> {
+    let string = |row: &QueryRow, field: &Field| {
+        row.get(field)
+            .map(|value| value.to_string())
+            .unwrap_or_default()
+    };
+    let mut lists: HashMap<String, MailingList> = HashMap::new();
+    for row in rows {
+        let sender = format!(
+            "{}@{}",
+            string(row, &Field::SenderLocalPart),
+            string(row, &Field::SenderDomain)
+        );
+        let name = match string(row, &Field::ListId) {
+            list_id if list_id.is_empty() => sender.clone(),
+            list_id => list_id,
+        };
+        lists
+            .entry(name.clone())
+            .or_insert_with(|| MailingList {
+                name,
+                sender,
+                count: 0,
+                unsubscribe: Vec::new(),
+            })
+            .add(&string(row, &Field::ListUnsubscribe));
+    }
+    let mut lists: Vec<MailingList> = lists.into_values().collect();
+    lists.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    lists
+}
+
+impl ExportTable {
+    /// One row per mailing list. Multiple unsubscribe links are separated by a space.
+    pub fn from_mailing_lists(lists: &[MailingList]) -> Self {
+        let rows = lists
+            .iter()
+            .map(|list| {
+                vec![
+                    Value::from(list.name.clone()),
+                    Value::from(list.sender.clone()),
+                    Value::from(list.count),
+                    Value::from(list.unsubscribe.join(" ")),
+                ]
+            })
+            .collect();
+        ExportTable {
+            columns: vec![
+                "list".to_string(),
+                "sender".to_string(),
+                "mails".to_string(),
+                "unsubscribe".to_string(),
+            ],
+            rows,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(list_id: &str, unsubscribe: &str, sender: (&str, &str)) -> QueryRow {
+        let mut row = QueryRow::new();
+        for (field, value) in [
+            (Field::ListId, list_id),
+            (Field::ListUnsubscribe, unsubscribe),
+            (Field::SenderLocalPart, sender.0),
+            (Field::SenderDomain, sender.1),
+        ] {
+            row.insert(field, ValueField::string(&field, value));
+        }
+        row
+    }
+
+    #[test]
+    fn test_mailing_lists_csv() {
+        let rows = vec![
+            row("", "", ("promo", "shop.example")),
+            row(
+                "news.example.com",
+                "mailto:u@example.com",
+                ("news", "example.com"),
+            ),
+            row(
+                "news.example.com",
+                "mailto:u@example.com https://example.com/u",
+                ("news", "example.com"),
+            ),
+        ];
+        let lists = from_rows(&rows);
+        assert_eq!(lists[0].name, "news.example.com");
+        assert_eq!(lists[0].count, 2);
+        assert_eq!(lists[1].name, "promo@shop.example");
+
+        let mut output = Vec::new();
+        ExportTable::from_mailing_lists(&lists)
+            .write_csv(&mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "list,sender,mails,unsubscribe\nnews.example.com,news@example.com,2,mailto:u@example.com https://example.com/u\npromo@shop.example,promo@shop.example,1,\n"
+        );
+    }
+}
@@ -1,6 +1,9 @@
+pub mod contact_graph;
 mod engine;
 pub mod items;
 mod link;
+pub mod mailing_lists;
+pub mod response_times;
 pub mod segmentations;
 mod types;
 
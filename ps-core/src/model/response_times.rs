@@ -0,0 +1,174 @@
+//! How long the user takes to reply to their correspondents, and how long
+//! they take to reply to the user. Based on the [`Field::ReplyLatency`]
+//! that is computed for every reply during the import.
+
+use eyre::Result;
+
+use std::collections::BTreeMap;
+
+use crate::database::database_like::DatabaseQuery;
+use crate::database::query::{Field, Filter, Query, ValueField};
+use crate::database::query_result::{QueryResult, QueryRow};
+
+const FIELDS: &[Field] = &[
+    Field::IsSend,
+    Field::ReplyDomain,
+    Field::ReplyLatency,
+    Field::Year,
+];
+
+/// The replies in one direction
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Latencies {
+    /// The reply latencies in seconds
+    latencies: Vec<i64>,
+}
+
+impl Latencies {
+    pub fn count(&self) -> usize {
+        self.latencies.len()
+    }
+
+    /// The median latency in seconds
+    pub fn median(&self) -> Option<i64> {
+        let mut sorted = self.latencies.clone();
+        sorted.sort_unstable();
+        match sorted.len() {
+            0 => None,
+            n if n % 2 == 0 => Some((sorted[n / 2 - 1] + sorted[n / 2]) / 2),
+            n => Some(sorted[n / 2]),
+        }
+    }
+}
+
+/// The response times for one correspondent domain or year
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseTime {
+    /// The domain or the year
+    pub key: String,
+    /// The replies of the user
+    pub ours: Latencies,
+    /// The replies to the user
+    pub theirs: Latencies,
+}
+
+impl ResponseTime {
+    fn add(&mut self, is_send: bool, latency: i64) {
+        match is_send {
+            true => self.ours.latencies.push(latency),
+            false => self.theirs.latencies.push(latency),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.ours.count() + self.theirs.count()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseTimes {
+    /// By correspondent domain, the most replies first
+    pub by_domain: Vec<ResponseTime>,
+    /// By year, the oldest first
+    pub by_year: Vec<ResponseTime>,
+}
+
+/// The response times of all replies in the database.
+/// `total` is the amount of mails in the database.
+pub fn response_times(database: &dyn DatabaseQuery, total: usize) -> Result<ResponseTimes> {
+    let query = Query::Normal {
+        fields: FIELDS.to_vec(),
+        // Only replies with a latency have a reply domain
+        filters: vec![Filter::Like(ValueField::string(&Field::ReplyDomain, "%"))],
+        range: 0..total,
+        timezone: Default::default(),
+    };
+    let rows: Vec<QueryRow> = database
+        .query(&query)?
+        .into_iter()
+        .filter_map(|result| match result {
+            QueryResult::Normal(row) => Some(row),
+            _ => None,
+        })
+        .collect();
+    Ok(from_rows(&rows))
+}
+
+fn from_rows(rows: &[QueryRow]) -> ResponseTimes {
+    let mut by_domain: BTreeMap<String, ResponseTime> = BTreeMap::new();
+    let mut by_year: BTreeMap<String, ResponseTime> = BTreeMap::new();
+    for row in rows {
+        let latency = match row
+            .get(&Field::ReplyLatency)
+            .and_then(|e| e.value().as_i64())
+        {
+            Some(n) => n,
+            None => continue,
+        };
+        let is_send = row
+            .get(&Field::IsSend)
+            .and_then(|e| e.value().as_bool())
+            .unwrap_or_default();
+        for (map, field) in [
+            (&mut by_domain, Field::ReplyDomain),
+            (&mut by_year, Field::Year),
+        ] {
+            let key = row.get(&field).map(|e| e.to_string()).unwrap_or_default();
+            map.entry(key.clone())
+                .or_insert_with(|| ResponseTime {
+                    key,
+                    ..Default::default()
+                })
+                .add(is_send, latency);
+        }
+    }
+    let mut by_domain: Vec<ResponseTime> = by_domain.into_values().collect();
+    by_domain.sort_by_key(|e| std::cmp::Reverse(e.count()));
+    ResponseTimes {
+        by_domain,
+        by_year: by_year.into_values().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn row(is_send: bool, domain: &str, latency: i64, year: usize) -> QueryRow {
+        let mut row = QueryRow::new();
+        row.insert(Field::IsSend, ValueField::bool(&Field::IsSend, is_send));
+        row.insert(
+            Field::ReplyDomain,
+            ValueField::string(&Field::ReplyDomain, domain),
+        );
+        row.insert(
+            Field::ReplyLatency,
+            ValueField::new(&Field::ReplyLatency, Value::from(latency)),
+        );
+        row.insert(Field::Year, ValueField::usize(&Field::Year, year));
+        row
+    }
+
+    #[test]
+    fn test_response_times() {
+        let rows = vec![
+            row(true, "a.com", 60, 2020),
+            row(true, "a.com", 180, 2021),
+            row(false, "a.com", 3600, 2021),
+            row(true, "b.com", 10, 2021),
+        ];
+        let times = from_rows(&rows);
+        assert_eq!(times.by_domain[0].key, "a.com");
+        assert_eq!(times.by_domain[0].ours.median(), Some(120));
+        assert_eq!(times.by_domain[0].theirs.median(), Some(3600));
+        assert_eq!(times.by_domain[1].theirs.median(), None);
+        let years: Vec<(&str, usize)> = times
+            .by_year
+            .iter()
+            .map(|e| (e.key.as_str(), e.count()))
+            .collect();
+        assert_eq!(years, vec![("2020", 1), ("2021", 3)]);
+        assert_eq!(times.by_year[1].ours.median(), Some(95));
+    }
+}
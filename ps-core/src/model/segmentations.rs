@@ -13,6 +13,11 @@
 //! - [`crate::model::segmentations::segments_range`]
 //! - [`crate::model::segmentations::set_segments_range`]
 //!
+//! The date fields of a `Segmentation` can be evaluated in different timezones.
+//!
+//! - [`crate::model::segmentations::timezone`]
+//! - [`crate::model::segmentations::set_timezone`]
+//!
 //! A [`crate::model::Segmentation`] has multiple [`crate::model::Segment`]s which each can be layouted
 //! to fit into a rectangle.
 //!
@@ -25,7 +30,7 @@ use super::{
     types::{self, Aggregation, Segment},
     Engine,
 };
-use crate::database::query::{Field, Filter, Query};
+use crate::database::query::{Field, Filter, Query, TimezoneMode};
 use std::ops::RangeInclusive;
 
 /// Filter the `Range` of segments of the current `Segmentation`
@@ -84,6 +89,24 @@ pub fn set_filters(engine: &mut Engine, filters: &[Filter]) -> Result<()> {
         .request(&make_query(engine)?, Action::RecalculateSegmentation)
 }
 
+/// The timezone in which the `Year`, `Month`, `Day` and `Hour` fields are evaluated
+pub fn timezone(engine: &Engine) -> TimezoneMode {
+    engine.timezone
+}
+
+/// Change the timezone in which the date fields are evaluated.
+///
+/// Setting this value will recalculate the current segmentations.
+pub fn set_timezone(engine: &mut Engine, timezone: TimezoneMode) -> Result<()> {
+    engine.timezone = timezone;
+
+    // Remove any rows that were cached for this Segmentation
+    engine.item_cache.clear();
+    engine
+        .link
+        .request(&make_query(engine)?, Action::RecalculateSegmentation)
+}
+
 /// The fields available for the given aggregation
 ///
 /// As the user `pushes` Segmentations and dives into the data,
@@ -206,5 +229,6 @@ pub(super) fn make_query(engine: &Engine) -> Result<Query> {
     Ok(Query::Grouped {
         filters,
         group_by: *last,
+        timezone: engine.timezone,
     })
 }
@@ -39,6 +39,11 @@ impl Segmentation {
         self.items.iter().map(|e| e.count).sum::<usize>()
     }
 
+    /// All `Segments` in this `Segmentation`, ignoring the range
+    pub fn segments(&self) -> &[Segment] {
+        &self.items
+    }
+
     /// The items in this `Segmentation`, with range applied
     pub fn items(&mut self) -> &mut [Segment] {
         match &self.range {
@@ -1,5 +1,5 @@
 use eyre::{eyre, Result};
-use serde_json::Value;
+use serde_json::{json, Value};
 use strum::{self, IntoEnumIterator};
 use strum_macros::{EnumIter, IntoStaticStr};
 
@@ -8,16 +8,21 @@ use std::iter::FromIterator;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+use super::ImportFilter;
+use crate::{Field, QueryRow};
+
 // FIXME: This abstraction should be in the `ps-importer` crate with only
 // a protocol here.
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, IntoStaticStr, EnumIter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, IntoStaticStr, EnumIter)]
 pub enum FormatType {
     AppleMail,
     GmailVault,
     Mbox,
     #[cfg(not(target_os = "windows"))]
     Maildir,
+    /// Numbered mail files, as used by nmh, Claws Mail and Sylpheed
+    Mh,
 }
 
 impl FormatType {
@@ -32,6 +37,18 @@ impl FormatType {
             FormatType::Mbox => "Mbox",
             #[cfg(not(target_os = "windows"))]
             FormatType::Maildir => "Maildir",
+            FormatType::Mh => "MH (nmh, Claws Mail, Sylpheed)",
+        }
+    }
+
+    /// Does this format store flags such as flagged, replied or seen
+    pub fn has_flags(&self) -> bool {
+        match self {
+            FormatType::AppleMail => true,
+            FormatType::Mh => true,
+            #[cfg(not(target_os = "windows"))]
+            FormatType::Maildir => true,
+            _ => false,
         }
     }
 }
@@ -66,6 +83,7 @@ impl From<&str> for FormatType {
             "mbox" => FormatType::Mbox,
             #[cfg(not(target_os = "windows"))]
             "maildir" => FormatType::Maildir,
+            "mh" => FormatType::Mh,
             _ => panic!("Unknown format: {}", &format),
         }
     }
@@ -79,10 +97,142 @@ impl From<FormatType> for String {
             FormatType::Mbox => "mbox".to_owned(),
             #[cfg(not(target_os = "windows"))]
             FormatType::Maildir => "maildir".to_owned(),
+            FormatType::Mh => "mh".to_owned(),
         }
     }
 }
 
+/// A folder of mails in one format. A database can contain the mails
+/// of several sources, e.g. an old Apple Mail archive and a current Maildir.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    /// The name under which the mails of this source can be segmented
+    pub name: String,
+    /// The importer format of this source
+    pub format: FormatType,
+    /// The path where the emails are
+    pub emails_folder_path: PathBuf,
+    /// The addresses used to send emails from this source
+    pub sender_emails: HashSet<String>,
+}
+
+impl Source {
+    pub fn new<A: AsRef<Path>>(
+        name: impl Into<String>,
+        format: FormatType,
+        mails: A,
+        sender_emails: Vec<String>,
+    ) -> Self {
+        Source {
+            name: name.into(),
+            format,
+            emails_folder_path: mails.as_ref().to_path_buf(),
+            sender_emails: sender_emails.into_iter().collect(),
+        }
+    }
+
+    /// The name of a source, if none was given: The name of the mail folder
+    pub fn default_name(mails: &Path, format: FormatType) -> String {
+        mails
+            .file_name()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_owned())
+            .unwrap_or_else(|| format.into())
+    }
+
+    fn from_value(value: &Value) -> Result<Source> {
+        let field = |name: &str| {
+            value
+                .get(name)
+                .ok_or_else(|| eyre!("Missing source field {}", name))
+        };
+        let string = |name: &str| {
+            field(name)?
+                .as_str()
+                .ok_or_else(|| eyre!("Invalid field type for source field {}", name))
+        };
+        let format = string("format")?;
+        let format = FormatType::all_cases()
+            .find(|f| String::from(*f) == format)
+            .ok_or_else(|| eyre!("Unknown source format {}", format))?;
+        let sender_emails = field("sender_emails")?
+            .as_array()
+            .ok_or_else(|| eyre!("Invalid field type for source field sender_emails"))?
+            .iter()
+            .filter_map(|e| e.as_str().map(|e| e.to_owned()))
+            .collect();
+        Ok(Source::new(
+            string("name")?,
+            format,
+            string("emails_folder_path")?,
+            sender_emails,
+        ))
+    }
+
+    fn to_value(&self) -> Option<Value> {
+        let format: String = self.format.into();
+        Some(json!({
+            "name": self.name,
+            "format": format,
+            "emails_folder_path": self.emails_folder_path.to_str()?,
+            "sender_emails": self.sender_emails.iter().collect::<Vec<_>>(),
+        }))
+    }
+}
+
+/// The index of a Maildir in which notmuch or mu keep the tags of its mails.
+/// The tags are only read if the user selects an index, as the mu index
+/// is global and would attach tags of unrelated mailboxes otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagSource {
+    /// A file exported with `notmuch dump`
+    NotmuchDump(PathBuf),
+    /// The notmuch database, i.e. the folder containing `.notmuch`
+    Notmuch(PathBuf),
+    /// The mu index, i.e. the `--muhome` folder
+    Mu(PathBuf),
+}
+
+impl TagSource {
+    /// The path of the dump file or the index
+    pub fn path(&self) -> &Path {
+        match self {
+            TagSource::NotmuchDump(path) | TagSource::Notmuch(path) | TagSource::Mu(path) => path,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            TagSource::NotmuchDump(_) => "notmuch_dump",
+            TagSource::Notmuch(_) => "notmuch",
+            TagSource::Mu(_) => "mu",
+        }
+    }
+
+    fn from_value(value: &Value) -> Result<TagSource> {
+        let string = |name: &str| {
+            value
+                .get(name)
+                .and_then(|e| e.as_str())
+                .ok_or_else(|| eyre!("Missing tag source field {}", name))
+        };
+        let path = PathBuf::from(string("path")?);
+        match string("kind")? {
+            "notmuch_dump" => Ok(TagSource::NotmuchDump(path)),
+            "notmuch" => Ok(TagSource::Notmuch(path)),
+            "mu" => Ok(TagSource::Mu(path)),
+            kind => Err(eyre!("Unknown tag source {}", kind)),
+        }
+    }
+
+    fn to_value(&self) -> Option<Value> {
+        Some(json!({
+            "kind": self.kind(),
+            "path": self.path().to_str()?,
+        }))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     /// The path to where the database should be stored
@@ -93,6 +243,14 @@ pub struct Config {
     pub sender_emails: HashSet<String>,
     /// The importer format we're using
     pub format: FormatType,
+    /// The name of the source at `emails_folder_path`
+    pub source_name: String,
+    /// Additional sources that are imported after the first one
+    pub sources: Vec<Source>,
+    /// Which folders and mails of the sources are imported
+    pub filter: ImportFilter,
+    /// The notmuch or mu index with the tags of the Maildir at `emails_folder_path`
+    pub tag_source: Option<TagSource>,
     /// Did the user intend to keep the database
     /// (e.g. is the database path temporary?)
     pub persistent: bool,
@@ -136,13 +294,41 @@ impl Config {
             .map(|e| e.as_bool())
             .flatten()
             .ok_or_else(|| eyre!("Missing config field persistent"))?;
-        Ok(Config {
+        // Multiple sources were added later
+        let source_name = fields
+            .get("source_name")
+            .and_then(|e| e.as_str())
+            .map(|e| e.to_owned())
+            .unwrap_or_else(|| Source::default_name(&emails_folder_path, format));
+        let sources = match fields.get("sources").and_then(|e| e.as_array()) {
+            Some(values) => values
+                .iter()
+                .map(Source::from_value)
+                .collect::<Result<Vec<Source>>>()?,
+            None => Vec::new(),
+        };
+        let tag_source = match fields.get("tag_source") {
+            Some(Value::Null) | None => None,
+            Some(value) => Some(TagSource::from_value(value)?),
+        };
+        // Import filters were added later
+        let filter = match fields.get("filter") {
+            Some(value) => ImportFilter::from_value(value)?,
+            None => ImportFilter::default(),
+        };
+        let config = Config {
             database_path: path.as_ref().to_path_buf(),
+            source_name,
             emails_folder_path,
             sender_emails: HashSet::from_iter(sender_emails.into_iter()),
             format,
+            sources,
+            filter,
+            tag_source,
             persistent,
-        })
+        };
+        config.check_source_names()?;
+        Ok(config)
     }
 
     pub fn new<A: AsRef<Path>>(
@@ -174,6 +360,10 @@ impl Config {
         };
         Ok(Config {
             database_path,
+            source_name: Source::default_name(mails.as_ref(), format),
+            sources: Vec::new(),
+            filter: ImportFilter::default(),
+            tag_source: None,
             emails_folder_path: mails.as_ref().to_path_buf(),
             sender_emails: HashSet::from_iter(sender_emails.into_iter()),
             format,
@@ -203,9 +393,90 @@ impl Config {
         );
         let format: String = self.format.into();
         new.insert("format".to_owned(), format.into());
+        new.insert("source_name".to_owned(), self.source_name.clone().into());
+        let sources = self
+            .sources
+            .iter()
+            .map(Source::to_value)
+            .collect::<Option<Vec<Value>>>()?;
+        new.insert("sources".to_owned(), sources.into());
+        new.insert("filter".to_owned(), self.filter.to_value());
+        let tag_source = match &self.tag_source {
+            Some(source) => source.to_value()?,
+            None => Value::Null,
+        };
+        new.insert("tag_source".to_owned(), tag_source);
 
         Some(new)
     }
+
+    /// All sources of this config, starting with the one at `emails_folder_path`
+    pub fn all_sources(&self) -> Vec<Source> {
+        let mut sources = vec![Source {
+            name: self.source_name.clone(),
+            format: self.format,
+            emails_folder_path: self.emails_folder_path.clone(),
+            sender_emails: self.sender_emails.clone(),
+        }];
+        sources.extend(self.sources.iter().cloned());
+        sources
+    }
+
+    /// The names of the sources identify their mails in the database,
+    /// so each name can only be used once
+    pub fn check_source_names(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        for source in self.all_sources() {
+            if !names.insert(source.name.clone()) {
+                return Err(eyre!("Duplicate source name {}", source.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// The format of the mails in `row`: the format of the source in its `Source` field.
+    /// Rows without a known source were imported before there were several
+    /// sources, with `format`.
+    pub fn format_of_row(&self, row: &QueryRow) -> FormatType {
+        let name = row.get(&Field::Source).and_then(|e| e.value().as_str());
+        match name {
+            Some(name) if name != self.source_name => self
+                .sources
+                .iter()
+                .find(|source| source.name == name)
+                .map(|source| source.format)
+                .unwrap_or(self.format),
+            _ => self.format,
+        }
+    }
+
+    /// A copy of this config that imports only `source`
+    pub fn for_source(&self, source: &Source) -> Config {
+        Config {
+            database_path: self.database_path.clone(),
+            emails_folder_path: source.emails_folder_path.clone(),
+            sender_emails: source.sender_emails.clone(),
+            format: source.format,
+            source_name: source.name.clone(),
+            sources: Vec::new(),
+            filter: self.filter.clone(),
+            // The tag index belongs to the first source
+            tag_source: match source.emails_folder_path == self.emails_folder_path {
+                true => self.tag_source.clone(),
+                false => None,
+            },
+            persistent: self.persistent,
+        }
+    }
+
+    /// Should the mails in `folder` (or the mbox file `folder`) be imported,
+    /// according to the folder patterns of the `filter`
+    pub fn includes_folder(&self, folder: &Path) -> bool {
+        let relative = folder
+            .strip_prefix(&self.emails_folder_path)
+            .unwrap_or(folder);
+        self.filter.includes_folder(relative)
+    }
 }
 
 fn random_filename() -> String {
@@ -1,6 +1,11 @@
 use chrono::prelude::*;
+use strum_macros::{EnumString, IntoStaticStr};
+
 use std::path::PathBuf;
 
+use crate::database::query::Field;
+use crate::database::query_result::QueryRow;
+
 pub type Tag = String;
 
 /// This is based on additional information in some systems such as
@@ -9,22 +14,30 @@ pub type Tag = String;
 pub struct EmailMeta {
     pub tags: Vec<Tag>,
     pub is_seen: bool,
+    pub is_flagged: bool,
+    /// Was this email replied to
+    pub is_answered: bool,
+    pub is_draft: bool,
+    /// Was this email marked for deletion
+    pub is_trashed: bool,
 }
 
-const TAG_SEP: &str = ":|:";
-
-impl EmailMeta {
-    pub fn tags_from_string(tag_string: &str) -> Vec<String> {
-        tag_string.split(TAG_SEP).map(|e| e.to_string()).collect()
-    }
-
-    pub fn from(is_seen: bool, tag_string: &str) -> Self {
-        let tags = EmailMeta::tags_from_string(tag_string);
-        EmailMeta { tags, is_seen }
-    }
+/// How well the headers of an email could be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum ParseQuality {
+    #[default]
+    Exact,
+    /// The mail had a broken charset, encoding or header structure and
+    /// was decoded on a best-effort basis. Some characters may be wrong.
+    Lossy,
+}
 
-    pub fn tags_string(&self) -> String {
-        self.tags.join(TAG_SEP)
+impl ParseQuality {
+    /// Just a wrapper to offer `into` without the type ambiguity
+    /// that sometimes arises
+    pub fn as_str(&self) -> &'static str {
+        self.into()
     }
 }
 
@@ -33,9 +46,13 @@ impl EmailMeta {
 pub struct EmailEntry {
     pub path: PathBuf,
     pub sender_domain: String,
+    /// The registrable domain of `sender_domain`, e.g. `google.com` for `mail.google.com`
+    pub sender_organization: String,
     pub sender_local_part: String,
     pub sender_name: String,
     pub datetime: chrono::DateTime<Utc>,
+    /// The offset in seconds east of UTC of the original `Date` header
+    pub tz_offset: i32,
     pub subject: String,
     /// The amount of `to:` adresses
     pub to_count: usize,
@@ -43,8 +60,54 @@ pub struct EmailEntry {
     pub to_group: Option<String>,
     /// The first address and name in `To`, if any
     pub to_first: Option<(String, String)>,
+    /// The addresses of all `To`, `Cc` and `Bcc` recipients
+    pub recipients: Vec<String>,
+    /// The `Message-Id`, without angle brackets
+    pub message_id: Option<String>,
+    /// The first id of the `In-Reply-To` header, without angle brackets
+    pub in_reply_to: Option<String>,
     pub is_reply: bool,
     /// Was this email send from the account we're importing?
     pub is_send: bool,
     pub meta: Option<EmailMeta>,
+    /// For mails inside of an mbox file, the byte offset and length
+    /// of the message in the file
+    pub mbox_location: Option<(usize, usize)>,
+    /// The name of the source this email was imported from
+    pub source: String,
+    /// The `List-Id` of mails from a mailing list
+    pub list_id: Option<String>,
+    /// The `mailto:` and `https:` links of the `List-Unsubscribe` header
+    pub list_unsubscribe: Vec<String>,
+    /// Is this a mailing list mail or newsletter?
+    /// (It has a `List-Id`, `List-Unsubscribe` or `Precedence: bulk`)
+    pub is_bulk: bool,
+    pub parse_quality: ParseQuality,
+}
+
+/// Where the original data of an imported email can be found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailLocation {
+    pub path: PathBuf,
+    /// For mails inside of an mbox file, the byte offset and length
+    /// of the message in the file
+    pub mbox_location: Option<(usize, usize)>,
+}
+
+impl MailLocation {
+    /// Read the location from a row that contains the `Path`,
+    /// `MboxOffset` and `MboxLength` fields.
+    pub fn from_row(row: &QueryRow) -> Option<Self> {
+        let path = row.get(&Field::Path)?.value().as_str()?;
+        let offset = row.get(&Field::MboxOffset).and_then(|e| e.value().as_u64());
+        let length = row.get(&Field::MboxLength).and_then(|e| e.value().as_u64());
+        let mbox_location = match (offset, length) {
+            (Some(offset), Some(length)) => Some((offset as usize, length as usize)),
+            _ => None,
+        };
+        Some(MailLocation {
+            path: PathBuf::from(path),
+            mbox_location,
+        })
+    }
 }
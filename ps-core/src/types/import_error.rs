@@ -0,0 +1,140 @@
+use eyre::Report;
+use strum::IntoEnumIterator;
+use strum_macros::{EnumIter, EnumString, IntoStaticStr};
+
+use std::path::{Path, PathBuf};
+
+use super::FormatType;
+
+/// The maximum number of characters of a mail that are kept as a snippet
+const SNIPPET_LENGTH: usize = 512;
+
+/// Why a mail could not be imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum ImportErrorKind {
+    /// The mail could not be read from disk
+    Io,
+    /// The mail (or its meta data) could not be parsed
+    Parse,
+    /// The `Date` header is missing or invalid
+    Date,
+    /// The sender address is missing or invalid
+    Address,
+}
+
+impl ImportErrorKind {
+    pub fn all_cases() -> impl Iterator<Item = ImportErrorKind> {
+        ImportErrorKind::iter()
+    }
+
+    /// A human readable name
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportErrorKind::Io => "IO",
+            ImportErrorKind::Parse => "Parse",
+            ImportErrorKind::Date => "Date",
+            ImportErrorKind::Address => "Address",
+        }
+    }
+
+    /// Just a wrapper to offer `into` without the type ambiguity
+    /// that sometimes arises
+    pub fn as_str(&self) -> &'static str {
+        self.into()
+    }
+}
+
+/// A mail that could not be imported. These are stored in the database
+/// so that they can be inspected after the import.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportError {
+    pub kind: ImportErrorKind,
+    /// The path of the file that contains the mail
+    pub path: PathBuf,
+    /// The format that was being imported
+    pub format: Option<FormatType>,
+    pub message: String,
+    /// The beginning of the mail, if it could be read
+    pub snippet: Option<String>,
+}
+
+impl ImportError {
+    pub fn new(kind: ImportErrorKind, path: &Path, message: impl Into<String>) -> Self {
+        ImportError {
+            kind,
+            path: path.to_path_buf(),
+            format: None,
+            message: message.into(),
+            snippet: None,
+        }
+    }
+
+    /// Convert any `Report` into an `ImportError`. If the report wraps an
+    /// `ImportError` that is used, otherwise the kind is derived from the error.
+    pub fn from_report(path: &Path, report: Report) -> Self {
+        let report = match report.downcast::<ImportError>() {
+            Ok(error) => return error,
+            Err(report) => report,
+        };
+        let kind = if report.downcast_ref::<std::io::Error>().is_some() {
+            ImportErrorKind::Io
+        } else {
+            ImportErrorKind::Parse
+        };
+        ImportError::new(kind, path, report.to_string())
+    }
+
+    pub fn with_format(mut self, format: FormatType) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Keep the beginning of the raw mail data for inspection
+    pub fn with_snippet(mut self, data: &[u8]) -> Self {
+        let content = String::from_utf8_lossy(data);
+        self.snippet = Some(content.chars().take(SNIPPET_LENGTH).collect());
+        self
+    }
+
+    /// The format as it is stored in the database
+    pub fn format_string(&self) -> Option<String> {
+        self.format.map(String::from)
+    }
+
+    /// The inverse of `format_string`
+    pub fn format_from_string(format: &str) -> Option<FormatType> {
+        FormatType::all_cases().find(|f| String::from(*f) == format)
+    }
+}
+
+impl std::fmt::Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::eyre;
+
+    #[test]
+    fn test_from_report() {
+        let path = Path::new("a/b");
+        let wrapped = Report::new(ImportError::new(ImportErrorKind::Date, path, "No date"));
+        let error = ImportError::from_report(path, wrapped);
+        assert_eq!(error.kind, ImportErrorKind::Date);
+        assert_eq!(error.message, "No date");
+
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        let error = ImportError::from_report(path, Report::new(io));
+        assert_eq!(error.kind, ImportErrorKind::Io);
+
+        let error = ImportError::from_report(path, eyre!("Broken"));
+        assert_eq!(error.kind, ImportErrorKind::Parse);
+        assert_eq!(error.message, "Broken");
+    }
+}
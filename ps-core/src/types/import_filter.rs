@@ -0,0 +1,237 @@
+use chrono::NaiveDate;
+use eyre::{eyre, Result};
+use glob::{MatchOptions, Pattern};
+use serde_json::{json, Value};
+
+use std::path::{Component, Path};
+
+use super::EmailEntry;
+
+/// The format of the dates of the date window
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Restricts which mails of a source are imported, e.g.
+/// "2015 onward, excluding Spam, Trash and Drafts".
+///
+/// The folder patterns are globs (`*`, `?`, `[a-z]`) that are matched
+/// case-insensitively against the path of a folder (or mbox file) relative
+/// to the email folder. A pattern without a `/` matches any folder in that
+/// path, so `Spam` also excludes `Archive/Spam` and all folders below `Spam`.
+/// The leading `.` of Maildir++ folders and the `.mbox` and `.sbd` extensions
+/// are ignored, so `Spam` matches `.Spam`, `Spam.mbox` and `Spam.sbd`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportFilter {
+    /// If not empty, only folders matching one of these patterns are imported
+    pub include_folders: Vec<String>,
+    /// Folders matching one of these patterns are skipped
+    pub exclude_folders: Vec<String>,
+    /// Skip mails sent before this day
+    pub from_date: Option<NaiveDate>,
+    /// Skip mails sent after this day
+    pub until_date: Option<NaiveDate>,
+    /// Skip mails with a tag (or Gmail label) matching one of these patterns
+    pub skip_tags: Vec<String>,
+}
+
+impl ImportFilter {
+    /// Does this filter import everything
+    pub fn is_empty(&self) -> bool {
+        self == &ImportFilter::default()
+    }
+
+    /// Should the mails in `folder`, relative to the email folder, be imported
+    pub fn includes_folder(&self, folder: &Path) -> bool {
+        let components = folder_components(folder);
+        let matches = |patterns: &[String]| {
+            patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern, &components))
+        };
+        if !self.include_folders.is_empty() && !matches(&self.include_folders) {
+            return false;
+        }
+        !matches(&self.exclude_folders)
+    }
+
+    /// Should the parsed `entry` be imported
+    pub fn includes_mail(&self, entry: &EmailEntry) -> bool {
+        let date = entry.datetime.naive_utc().date();
+        if self.from_date.map(|from| date < from).unwrap_or(false)
+            || self.until_date.map(|until| date > until).unwrap_or(false)
+        {
+            return false;
+        }
+        let tags = match &entry.meta {
+            Some(meta) => &meta.tags,
+            None => return true,
+        };
+        !tags.iter().any(|tag| {
+            self.skip_tags
+                .iter()
+                .any(|pattern| glob_matches(pattern, tag))
+        })
+    }
+
+    /// Split a comma separated list of folder patterns or tags,
+    /// and check that the patterns are valid globs
+    pub fn parse_list(list: &str) -> Result<Vec<String>> {
+        list.split(',')
+            .map(|e| e.trim())
+            .filter(|e| !e.is_empty())
+            .map(|e| match Pattern::new(e) {
+                Ok(_) => Ok(e.to_owned()),
+                Err(error) => Err(eyre!("Invalid pattern {}: {}", e, error)),
+            })
+            .collect()
+    }
+
+    /// Parse a `YYYY-MM-DD` date. An empty string is no date.
+    pub fn parse_date(date: &str) -> Result<Option<NaiveDate>> {
+        let date = date.trim();
+        if date.is_empty() {
+            return Ok(None);
+        }
+        NaiveDate::parse_from_str(date, DATE_FORMAT)
+            .map(Some)
+            .map_err(|e| eyre!("Invalid date {} (expected YYYY-MM-DD): {}", date, e))
+    }
+
+    pub(super) fn from_value(value: &Value) -> Result<ImportFilter> {
+        let list = |name: &str| -> Result<Vec<String>> {
+            match value.get(name) {
+                Some(Value::Array(values)) => Ok(values
+                    .iter()
+                    .filter_map(|e| e.as_str().map(|e| e.to_owned()))
+                    .collect()),
+                Some(_) => Err(eyre!("Invalid field type for filter field {}", name)),
+                None => Ok(Vec::new()),
+            }
+        };
+        let date = |name: &str| match value.get(name).and_then(|e| e.as_str()) {
+            Some(date) => ImportFilter::parse_date(date),
+            None => Ok(None),
+        };
+        Ok(ImportFilter {
+            include_folders: list("include_folders")?,
+            exclude_folders: list("exclude_folders")?,
+            from_date: date("from_date")?,
+            until_date: date("until_date")?,
+            skip_tags: list("skip_tags")?,
+        })
+    }
+
+    pub(super) fn to_value(&self) -> Value {
+        let date = |date: Option<NaiveDate>| date.map(|e| e.format(DATE_FORMAT).to_string());
+        json!({
+            "include_folders": self.include_folders,
+            "exclude_folders": self.exclude_folders,
+            "from_date": date(self.from_date),
+            "until_date": date(self.until_date),
+            "skip_tags": self.skip_tags,
+        })
+    }
+}
+
+/// The names of the folders in `folder`, without the Maildir++ dot
+/// and the mbox extensions
+fn folder_components(folder: &Path) -> Vec<String> {
+    folder
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .map(|name| {
+            let name = name.trim_start_matches('.');
+            let name = name
+                .strip_suffix(".mbox")
+                .or_else(|| name.strip_suffix(".sbd"))
+                .unwrap_or(name);
+            name.to_owned()
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Does `pattern` match one of the `components` or, if it contains a `/`,
+/// the path of the components or of one of their parents
+fn matches_pattern(pattern: &str, components: &[String]) -> bool {
+    let pattern = pattern.trim_matches('/');
+    match pattern.contains('/') {
+        true => (1..=components.len())
+            .any(|length| glob_matches(pattern, &components[..length].join("/"))),
+        false => components.iter().any(|name| glob_matches(pattern, name)),
+    }
+}
+
+/// Does the glob `pattern` match `text`, ignoring case
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let options = MatchOptions {
+        case_sensitive: false,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+    match Pattern::new(pattern) {
+        Ok(pattern) => pattern.matches_with(text, options),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_includes_folder() {
+        let filter = ImportFilter {
+            exclude_folders: vec![
+                "spam".to_owned(),
+                "Trash*".to_owned(),
+                "Work/Old".to_owned(),
+            ],
+            ..Default::default()
+        };
+        assert!(filter.includes_folder(Path::new("")));
+        assert!(filter.includes_folder(Path::new(".Work")));
+        assert!(!filter.includes_folder(Path::new(".Spam")));
+        assert!(!filter.includes_folder(Path::new("Archive/Spam.mbox")));
+        assert!(!filter.includes_folder(Path::new("Trash Can.sbd/2015")));
+        assert!(!filter.includes_folder(Path::new("Work/Old/2010")));
+        assert!(filter.includes_folder(Path::new("Old/Work")));
+
+        let filter = ImportFilter {
+            include_folders: vec!["Work".to_owned()],
+            exclude_folders: vec!["Old".to_owned()],
+            ..Default::default()
+        };
+        assert!(filter.includes_folder(Path::new("Work/2015")));
+        assert!(!filter.includes_folder(Path::new("Work/Old")));
+        assert!(!filter.includes_folder(Path::new("Inbox")));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            ImportFilter::parse_list(" Spam, Trash,,Drafts ").unwrap(),
+            vec!["Spam", "Trash", "Drafts"]
+        );
+        assert!(ImportFilter::parse_list("[Spam").is_err());
+        assert_eq!(
+            ImportFilter::parse_date("2015-01-01").unwrap(),
+            NaiveDate::from_ymd_opt(2015, 1, 1)
+        );
+        assert_eq!(ImportFilter::parse_date(" ").unwrap(), None);
+        assert!(ImportFilter::parse_date("01.01.2015").is_err());
+
+        let filter = ImportFilter {
+            exclude_folders: vec!["Spam".to_owned()],
+            from_date: NaiveDate::from_ymd_opt(2015, 1, 1),
+            skip_tags: vec!["Trash".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(
+            ImportFilter::from_value(&filter.to_value()).unwrap(),
+            filter
+        );
+    }
+}
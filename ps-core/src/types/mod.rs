@@ -1,5 +1,11 @@
 mod config;
 mod email;
 mod format_type;
-pub use config::{Config, FormatType};
-pub use email::{EmailEntry, EmailMeta};
+mod import_error;
+mod import_filter;
+mod preview;
+pub use config::{Config, FormatType, Source, TagSource};
+pub use email::{EmailEntry, EmailMeta, MailLocation, ParseQuality};
+pub use import_error::{ImportError, ImportErrorKind};
+pub use import_filter::ImportFilter;
+pub use preview::{Attachment, EmailPreview};
@@ -0,0 +1,20 @@
+/// A readable representation of a single email as it was
+/// loaded from its original location.
+#[derive(Debug, Clone, Default)]
+pub struct EmailPreview {
+    /// The most relevant headers, in display order
+    pub headers: Vec<(String, String)>,
+    /// The `text/plain` body. Mails that only have a `text/html`
+    /// body will have it converted to text
+    pub body: String,
+    pub attachments: Vec<Attachment>,
+}
+
+/// An attachment in an `EmailPreview`
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub name: String,
+    pub content_type: String,
+    /// The size of the decoded attachment in bytes
+    pub size: usize,
+}
@@ -5,7 +5,7 @@ use ps_core::eyre::{bail, eyre, Result};
 use ps_core::Value;
 use rusqlite::{self, types, Row};
 
-use ps_core::{EmailMeta, Field, QueryResult, ValueField, AMOUNT_FIELD_NAME};
+use ps_core::{serde_json, Field, QueryResult, ValueField, AMOUNT_FIELD_NAME};
 
 /// rusqlite does offer Serde to Value conversion, but it
 /// converts everything to strings!
@@ -37,6 +37,17 @@ pub trait RowConversion<'a>: Sized {
 impl<'a> RowConversion<'a> for QueryResult {
     fn grouped_from_row<'stmt>(field: &'a Field, row: &Row<'stmt>) -> Result<Self> {
         let amount: usize = row.get(AMOUNT_FIELD_NAME)?;
+        // Mails are grouped by each individual tag
+        if *field == Field::MetaTags {
+            // The mails without tags have no tag name
+            let tag: Option<String> = row.get(field.as_str())?;
+            return Ok(QueryResult::Grouped {
+                count: amount,
+                value: tag
+                    .map(|tag| ValueField::string(field, tag))
+                    .unwrap_or_else(ValueField::untagged),
+            });
+        }
         let values = values_from_fields(&[*field], row)?;
 
         Ok(QueryResult::Grouped {
@@ -66,30 +77,65 @@ pub fn value_from_field<'stmt>(field: &Field, row: &Row<'stmt>) -> Result<ValueF
     // Use type safety when unpacking
     match field {
         // String Fields
-        Path | SenderDomain | SenderLocalPart | SenderName | Subject => {
+        Path | SenderDomain | SenderOrganization | SenderLocalPart | SenderName | Subject
+        | Source | ListId => {
             let string: String = row.get::<&str, String>(field.as_str())?;
             Ok(ValueField::string(field, &string))
         }
         // Optional String Fields
-        ToGroup | ToName | ToAddress => {
+        ToGroup | ToName | ToAddress | ListUnsubscribe | ReplyDomain | ParseQuality => {
             let string: Option<String> = row.get::<&str, Option<String>>(field.as_str())?;
             Ok(ValueField::string(field, &string.unwrap_or("".to_string())))
         }
-        Year | Month | Day | Timestamp => {
+        Year | Month | Day | Hour | Timestamp => {
             return Ok(ValueField::usize(
                 field,
                 row.get::<&str, usize>(field.as_str())?,
             ));
         }
+        ReplyLatency => {
+            let latency: Option<i64> = row.get::<&str, Option<i64>>(field.as_str())?;
+            Ok(ValueField::new(
+                field,
+                latency.map(Value::from).unwrap_or(Value::Null),
+            ))
+        }
+        TzOffset => Ok(ValueField::new(
+            field,
+            Value::Number(row.get::<&str, i64>(field.as_str())?.into()),
+        )),
+        // Optional Number Fields
+        MboxOffset | MboxLength => {
+            let number: Option<usize> = row.get::<&str, Option<usize>>(field.as_str())?;
+            Ok(match number {
+                Some(n) => ValueField::usize(field, n),
+                None => ValueField::new(field, Value::Null),
+            })
+        }
+        Recipients => {
+            let recipients = row.get::<&str, String>(field.as_str())?;
+            Ok(ValueField::array(
+                field,
+                recipients
+                    .split_whitespace()
+                    .map(|e| Value::String(e.to_string()))
+                    .collect(),
+            ))
+        }
         MetaTags => {
-            let tag_string = row.get::<&str, String>(field.as_str())?;
-            let tags = EmailMeta::tags_from_string(&tag_string);
+            // A JSON array, see `Field::sql_expression`
+            let tags = row.get::<&str, Option<String>>(field.as_str())?;
+            let tags: Vec<String> = match tags {
+                Some(tags) => serde_json::from_str(&tags)?,
+                None => Vec::new(),
+            };
             Ok(ValueField::array(
                 field,
                 tags.into_iter().map(Value::String).collect(),
             ))
         }
-        IsReply | IsSend | MetaIsSeen => {
+        IsReply | IsSend | IsBulk | MetaIsSeen | MetaIsFlagged | MetaIsAnswered | MetaIsDraft
+        | MetaIsTrashed => {
             return Ok(ValueField::bool(
                 field,
                 row.get::<&str, bool>(field.as_str())?,
@@ -1,17 +1,19 @@
-use rusqlite::{self, params, Connection, Statement};
+use rusqlite::{self, params, params_from_iter, Connection, Statement};
 
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::{collections::HashMap, path::Path, thread::JoinHandle};
 
 use super::sql::*;
 use super::{value_from_field, RowConversion};
-use ps_core::chrono::Datelike;
-use ps_core::eyre::{self, bail, Report, Result};
+use ps_core::chrono::{Datelike, Timelike};
+use ps_core::eyre::{self, bail, Result};
 use ps_core::tracing;
 use ps_core::Value;
 use ps_core::{
     crossbeam_channel::{unbounded, Sender},
-    Config, DBMessage, DatabaseLike, DatabaseQuery, EmailEntry, OtherQuery, Query, QueryResult,
+    Config, DBMessage, DatabaseLike, DatabaseQuery, EmailEntry, ImportError, ImportErrorKind,
+    OtherQuery, Query, QueryResult,
 };
 
 #[derive(Debug)]
@@ -74,7 +76,7 @@ impl DatabaseLike for Database {
         connection.pragma_update(None, "journal_mode", &"memory")?;
         connection.pragma_update(None, "synchronous", &"OFF")?;
 
-        Self::create_tables(&connection)?;
+        Self::migrate(&connection)?;
 
         #[cfg(feature = "trace-sql")]
         connection.trace(Some(|query| {
@@ -111,6 +113,72 @@ impl DatabaseLike for Database {
         self.insert_config_fields(fields)
     }
 
+    fn create_indexes(&self) -> Result<()> {
+        let connection = match &self.connection {
+            Some(n) => n,
+            None => bail!("No connection to database available in query"),
+        };
+        connection.execute_batch(QUERY_CREATE_INDEXES)?;
+        Ok(())
+    }
+
+    fn import_errors(&self) -> Result<Vec<ImportError>> {
+        let connection = match &self.connection {
+            Some(n) => n,
+            None => bail!("No connection to database available in query"),
+        };
+        let mut stmt = connection.prepare(QUERY_SELECT_ERRORS)?;
+        let mut errors = Vec::new();
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let kind: String = row.get("kind")?;
+            let format: Option<String> = row.get("format")?;
+            errors.push(ImportError {
+                kind: ImportErrorKind::from_str(&kind)?,
+                path: PathBuf::from(row.get::<_, String>("path")?),
+                format: format.and_then(|f| ImportError::format_from_string(&f)),
+                message: row.get("message")?,
+                snippet: row.get("snippet")?,
+            });
+        }
+        Ok(errors)
+    }
+
+    fn recompute_is_send(&self, config: &Config) -> Result<usize> {
+        // The sources are updated by name, so a repeated name would overwrite
+        // the `is_send` of the earlier source
+        config.check_source_names()?;
+        let connection = match &self.connection {
+            Some(n) => n,
+            None => bail!("No connection to database available in query"),
+        };
+        let transaction = connection.unchecked_transaction()?;
+        for (index, source) in config.all_sources().iter().enumerate() {
+            let placeholders = vec!["?"; source.sender_emails.len()].join(", ");
+            // Mails imported before sources existed belong to the first source
+            let condition = match index {
+                0 => "source = ? OR source = ''",
+                _ => "source = ?",
+            };
+            let sql = format!(
+                "UPDATE emails SET is_send = (sender_local_part || '@' || sender_domain) IN ({}) WHERE {}",
+                placeholders, condition
+            );
+            let parameters = source
+                .sender_emails
+                .iter()
+                .chain(std::iter::once(&source.name));
+            transaction.execute(&sql, params_from_iter(parameters))?;
+        }
+        // Which replies are between the user and others depends on `is_send`
+        transaction.execute_batch(QUERY_UPDATE_REPLY_LATENCY)?;
+        transaction.commit()?;
+        self.save_config(config.clone())?;
+        let mut stmt = connection.prepare(QUERY_COUNT_SEND_MAILS)?;
+        let count: usize = stmt.query_row([], |q| q.get(0))?;
+        Ok(count)
+    }
+
     /// Begin the data import.
     /// This will consume the `Database`. A new one has to be opened
     /// afterwards in order to support multi-threading.
@@ -133,12 +201,18 @@ impl DatabaseLike for Database {
         // Import can only be called *once* on a database created with `new`.
         // Therefore there should always be a value to unwrap;
         let mut connection = self.connection.take().unwrap();
+        let path = self.path.clone();
         let handle = std::thread::spawn(move || {
             let mut counter = 0;
+            let mut cancelled = false;
             {
                 let transaction = connection.transaction()?;
                 {
-                    let mut mail_prepared = transaction.prepare(QUERY_EMAILS)?;
+                    let mut mail_prepared = MailStatements {
+                        mail: transaction.prepare(QUERY_EMAILS)?,
+                        tag: transaction.prepare(QUERY_INSERT_TAG)?,
+                        email_tag: transaction.prepare(QUERY_INSERT_EMAIL_TAG)?,
+                    };
                     let mut error_prepared = transaction.prepare(QUERY_ERRORS)?;
                     loop {
                         let next = match receiver.recv() {
@@ -153,7 +227,12 @@ impl DatabaseLike for Database {
                                 counter += 1;
                                 insert_mail(&mut mail_prepared, &mail)
                             }
-                            DBMessage::Error(report) => insert_error(&mut error_prepared, &report),
+                            DBMessage::Error(error) => insert_error(&mut error_prepared, &error),
+                            DBMessage::Cancel => {
+                                tracing::trace!("Received DBMessage::Cancel");
+                                cancelled = true;
+                                break;
+                            }
                             DBMessage::Done => {
                                 tracing::trace!("Received DBMessage::Done");
                                 break;
@@ -161,8 +240,12 @@ impl DatabaseLike for Database {
                         }?;
                     }
                 }
-                if let Err(e) = transaction.commit() {
-                    return Err(eyre::eyre!("Transaction Error: {:?}", &e));
+                // Dropping the transaction without a commit rolls it back
+                if !cancelled {
+                    transaction.execute_batch(QUERY_UPDATE_REPLY_LATENCY)?;
+                    if let Err(e) = transaction.commit() {
+                        return Err(eyre::eyre!("Transaction Error: {:?}", &e));
+                    }
                 }
             }
             // In case closing the database fails, we try again until we succeed
@@ -174,6 +257,12 @@ impl DatabaseLike for Database {
                     Err((a, _b)) => c = a,
                 }
             }
+            if cancelled {
+                // Don't leave an incomplete database behind
+                std::fs::remove_file(&path)?;
+                tracing::trace!("Removed cancelled database {}", path.display());
+                return Ok(0);
+            }
             tracing::trace!("Finished SQLITE: {}", &counter);
             Ok(counter)
         });
@@ -182,8 +271,98 @@ impl DatabaseLike for Database {
 }
 
 impl Database {
+    /// Create the tables of a new database, or bring the tables of a database
+    /// of an older release up to date
+    fn migrate(connection: &Connection) -> Result<()> {
+        let version: i64 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            bail!(
+                "The database was created by a newer version of Postsack (schema version {}, this version supports {})",
+                version,
+                SCHEMA_VERSION
+            );
+        }
+        let email_columns = Self::columns(connection, "emails")?;
+        let error_columns = Self::columns(connection, "errors")?;
+        Self::create_tables(connection)?;
+        // A new database has no columns before its tables are created
+        if version < SCHEMA_VERSION && !email_columns.is_empty() {
+            tracing::info!("Migrating database from schema version {}", version);
+            let transaction = connection.unchecked_transaction()?;
+            Self::add_columns(
+                &transaction,
+                "emails",
+                &email_columns,
+                MIGRATION_EMAIL_COLUMNS,
+            )?;
+            Self::add_columns(
+                &transaction,
+                "errors",
+                &error_columns,
+                MIGRATION_ERROR_COLUMNS,
+            )?;
+            if !email_columns.iter().any(|c| c == "hour") {
+                transaction.execute(QUERY_MIGRATE_HOUR, [])?;
+            }
+            if !email_columns.iter().any(|c| c == "sender_organization") {
+                transaction.execute(QUERY_MIGRATE_ORGANIZATION, [])?;
+            }
+            if email_columns.iter().any(|c| c == "meta_tags") {
+                Self::migrate_tags(&transaction)?;
+            }
+            transaction.commit()?;
+        }
+        connection.pragma_update(None, "user_version", &SCHEMA_VERSION)?;
+        Ok(())
+    }
+
+    /// The names of the columns of `table`, or none if it doesn't exist
+    fn columns(connection: &Connection, table: &str) -> Result<Vec<String>> {
+        let mut stmt = connection.prepare("SELECT name FROM pragma_table_info(?)")?;
+        let columns = stmt
+            .query_map([table], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(columns)
+    }
+
+    /// Add the `columns` that are missing from the `existing` columns of `table`
+    fn add_columns(
+        connection: &Connection,
+        table: &str,
+        existing: &[String],
+        columns: &[(&str, &str)],
+    ) -> Result<()> {
+        for (name, definition) in columns {
+            if existing.iter().any(|c| c == name) {
+                continue;
+            }
+            let sql = format!("ALTER TABLE {} ADD COLUMN {} {}", table, name, definition);
+            connection.execute(&sql, [])?;
+        }
+        Ok(())
+    }
+
+    /// Move the tags of `meta_tags` into the `tags` and `email_tags` tables
+    fn migrate_tags(connection: &Connection) -> Result<()> {
+        let mut select = connection.prepare(QUERY_SELECT_OLD_TAGS)?;
+        let mut insert_tag = connection.prepare(QUERY_INSERT_TAG)?;
+        let mut insert_email_tag = connection.prepare(QUERY_INSERT_EMAIL_TAG)?;
+        let mut rows = select.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let tags: String = row.get(1)?;
+            for tag in tags.split(OLD_TAG_SEPARATOR).filter(|e| !e.is_empty()) {
+                insert_tag.execute(params![tag])?;
+                insert_email_tag.execute(params![id, tag])?;
+            }
+        }
+        Ok(())
+    }
+
     fn create_tables(connection: &Connection) -> Result<()> {
         connection.execute(TBL_EMAILS, params![])?;
+        connection.execute(TBL_TAGS, params![])?;
+        connection.execute(TBL_EMAIL_TAGS, params![])?;
         connection.execute(TBL_ERRORS, params![])?;
         connection.execute(TBL_META, params![])?;
         Ok(())
@@ -215,25 +394,46 @@ impl Database {
             Some(n) => n,
             None => bail!("No connection to database available in query"),
         };
+        // Replace previously saved values
+        let mut delete = connection.prepare(QUERY_DELETE_META)?;
         let mut stmt = connection.prepare(QUERY_INSERT_META)?;
         for (key, value) in fields {
+            delete.execute(params![key])?;
             stmt.execute(params![key, value])?;
         }
         Ok(())
     }
 }
 
-fn insert_mail(statement: &mut Statement, entry: &EmailEntry) -> Result<()> {
+/// The prepared statements to insert a mail and its tags
+struct MailStatements<'conn> {
+    mail: Statement<'conn>,
+    tag: Statement<'conn>,
+    email_tag: Statement<'conn>,
+}
+
+fn insert_mail(statements: &mut MailStatements, entry: &EmailEntry) -> Result<()> {
     let path = entry.path.display().to_string();
     let year = entry.datetime.date().year();
     let month = entry.datetime.date().month();
     let day = entry.datetime.date().day();
+    let hour = entry.datetime.hour();
     let timestamp = entry.datetime.timestamp();
     let e = entry;
     let to_name = e.to_first.as_ref().map(|e| &e.1);
     let to_address = e.to_first.as_ref().map(|e| &e.0);
-    let meta_tags = e.meta.as_ref().map(|e| e.tags_string());
     let meta_is_seen = e.meta.as_ref().map(|e| e.is_seen);
+    let meta_is_flagged = e.meta.as_ref().map(|e| e.is_flagged);
+    let meta_is_answered = e.meta.as_ref().map(|e| e.is_answered);
+    let meta_is_draft = e.meta.as_ref().map(|e| e.is_draft);
+    let meta_is_trashed = e.meta.as_ref().map(|e| e.is_trashed);
+    let mbox_offset = e.mbox_location.map(|e| e.0);
+    let mbox_length = e.mbox_location.map(|e| e.1);
+    let list_id = e.list_id.as_deref().unwrap_or_default();
+    let list_unsubscribe = match e.list_unsubscribe.is_empty() {
+        true => None,
+        false => Some(e.list_unsubscribe.join(" ")),
+    };
     let p = params![
         path,
         e.sender_domain,
@@ -242,7 +442,9 @@ fn insert_mail(statement: &mut Statement, entry: &EmailEntry) -> Result<()> {
         year,
         month,
         day,
+        hour,
         timestamp,
+        e.tz_offset,
         e.subject,
         e.to_count,
         e.to_group,
@@ -250,16 +452,44 @@ fn insert_mail(statement: &mut Statement, entry: &EmailEntry) -> Result<()> {
         to_address,
         e.is_reply,
         e.is_send,
-        meta_tags,
-        meta_is_seen
+        meta_is_seen,
+        mbox_offset,
+        mbox_length,
+        e.source,
+        list_id,
+        list_unsubscribe,
+        e.is_bulk,
+        e.sender_organization,
+        e.recipients.join(" "),
+        e.message_id,
+        e.in_reply_to,
+        meta_is_flagged,
+        meta_is_answered,
+        meta_is_draft,
+        meta_is_trashed,
+        e.parse_quality.as_str()
     ];
-    statement.execute(p)?;
+    let email_id = statements.mail.insert(p)?;
+    for tag in e.meta.iter().flat_map(|m| m.tags.iter()) {
+        if tag.is_empty() {
+            continue;
+        }
+        statements.tag.execute(params![tag])?;
+        statements.email_tag.execute(params![email_id, tag])?;
+    }
     tracing::trace!("Insert Mail {}", &path);
     Ok(())
 }
 
-fn insert_error(statement: &mut Statement, message: &Report) -> Result<()> {
-    statement.execute(params![message.to_string()])?;
-    tracing::trace!("Insert Error {}", message);
+fn insert_error(statement: &mut Statement, error: &ImportError) -> Result<()> {
+    let path = error.path.display().to_string();
+    statement.execute(params![
+        path,
+        error.format_string(),
+        error.kind.as_str(),
+        error.message,
+        error.snippet
+    ])?;
+    tracing::trace!("Insert Error {}: {}", &path, error);
     Ok(())
 }
@@ -1,3 +1,7 @@
+/// The version of the tables, stored as `PRAGMA user_version`.
+/// The databases of the first release have the version 0.
+pub const SCHEMA_VERSION: i64 = 1;
+
 pub const TBL_EMAILS: &str = r#"
 CREATE TABLE IF NOT EXISTS emails (
   path TEXT NOT NULL,
@@ -7,7 +11,9 @@ CREATE TABLE IF NOT EXISTS emails (
   year INTEGER NOT NULL,
   month INTEGER NOT NULL,
   day INTEGER NOT NULL,
+  hour INTEGER NOT NULL,
   timestamp INTEGER NOT NULL,
+  tz_offset INTEGER NOT NULL DEFAULT 0,
   subject TEXT NOT NULL,
   to_count INTEGER NOT NULL,
   to_group TEXT NULL,
@@ -15,39 +21,155 @@ CREATE TABLE IF NOT EXISTS emails (
   to_address TEXT NULL,
   is_reply BOOL,
   is_send BOOL,
-  meta_tags TEXT NULL,
-  meta_is_seen BOOL NULL
+  meta_is_seen BOOL NULL,
+  mbox_offset INTEGER NULL,
+  mbox_length INTEGER NULL,
+  source TEXT NOT NULL DEFAULT '',
+  list_id TEXT NOT NULL DEFAULT '',
+  list_unsubscribe TEXT NULL,
+  is_bulk BOOL NOT NULL DEFAULT 0,
+  sender_organization TEXT NOT NULL DEFAULT '',
+  recipients TEXT NOT NULL DEFAULT '',
+  message_id TEXT NULL,
+  in_reply_to TEXT NULL,
+  reply_latency INTEGER NULL,
+  reply_domain TEXT NULL,
+  meta_is_flagged BOOL NULL,
+  meta_is_answered BOOL NULL,
+  meta_is_draft BOOL NULL,
+  meta_is_trashed BOOL NULL,
+  parse_quality TEXT NULL
 );"#;
 
+/// The columns of `emails` that were added after the first release, with
+/// the definitions under which they're added to the databases of that release
+pub const MIGRATION_EMAIL_COLUMNS: &[(&str, &str)] = &[
+    ("hour", "INTEGER NOT NULL DEFAULT 0"),
+    ("tz_offset", "INTEGER NOT NULL DEFAULT 0"),
+    ("mbox_offset", "INTEGER NULL"),
+    ("mbox_length", "INTEGER NULL"),
+    ("source", "TEXT NOT NULL DEFAULT ''"),
+    ("list_id", "TEXT NOT NULL DEFAULT ''"),
+    ("list_unsubscribe", "TEXT NULL"),
+    ("is_bulk", "BOOL NOT NULL DEFAULT 0"),
+    ("sender_organization", "TEXT NOT NULL DEFAULT ''"),
+    ("recipients", "TEXT NOT NULL DEFAULT ''"),
+    ("message_id", "TEXT NULL"),
+    ("in_reply_to", "TEXT NULL"),
+    ("reply_latency", "INTEGER NULL"),
+    ("reply_domain", "TEXT NULL"),
+    ("meta_is_flagged", "BOOL NULL"),
+    ("meta_is_answered", "BOOL NULL"),
+    ("meta_is_draft", "BOOL NULL"),
+    ("meta_is_trashed", "BOOL NULL"),
+    ("parse_quality", "TEXT NULL"),
+];
+
+/// The first release only stored the message of an error
+pub const MIGRATION_ERROR_COLUMNS: &[(&str, &str)] = &[
+    ("path", "TEXT NOT NULL DEFAULT ''"),
+    ("format", "TEXT NULL"),
+    ("kind", "TEXT NOT NULL DEFAULT 'parse'"),
+    ("snippet", "TEXT NULL"),
+];
+
+/// The first release didn't store the hour. The original offset is unknown,
+/// so the hour is in UTC.
+pub const QUERY_MIGRATE_HOUR: &str = r#"
+UPDATE emails SET hour = CAST(strftime('%H', timestamp, 'unixepoch') AS INTEGER)"#;
+
+/// The first release didn't group subdomains, so the organization is the domain
+pub const QUERY_MIGRATE_ORGANIZATION: &str = r#"
+UPDATE emails SET sender_organization = sender_domain"#;
+
+/// The first release joined the tags of a mail with `:|:` in `meta_tags`
+pub const QUERY_SELECT_OLD_TAGS: &str = r#"
+SELECT rowid, meta_tags FROM emails WHERE meta_tags IS NOT NULL AND meta_tags != ''"#;
+
+pub const OLD_TAG_SEPARATOR: &str = ":|:";
+
 pub const QUERY_EMAILS: &str = r#"
 INSERT INTO emails
     (
         path, sender_domain, sender_local_part, sender_name,
-        year, month, day, timestamp, subject,
+        year, month, day, hour, timestamp, tz_offset, subject,
         to_count, to_group, to_name, to_address,
         is_reply, is_send,
-        meta_tags, meta_is_seen
+        meta_is_seen,
+        mbox_offset, mbox_length,
+        source,
+        list_id, list_unsubscribe, is_bulk,
+        sender_organization, recipients,
+        message_id, in_reply_to,
+        meta_is_flagged, meta_is_answered, meta_is_draft, meta_is_trashed,
+        parse_quality
     )
 VALUES
     (
         ?, ?, ?, ?,
-        ?, ?, ?, ?, ?,
+        ?, ?, ?, ?, ?, ?, ?,
         ?, ?, ?, ?,
         ?, ?,
-        ?, ?
+        ?,
+        ?, ?,
+        ?,
+        ?, ?, ?,
+        ?, ?,
+        ?, ?,
+        ?, ?, ?, ?,
+        ?
     )
 "#;
 
+/// Every tag once. Mails are linked to their tags in `email_tags`.
+pub const TBL_TAGS: &str = r#"
+CREATE TABLE IF NOT EXISTS tags (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE
+);"#;
+
+/// `email_id` is the `rowid` of the mail in `emails`
+pub const TBL_EMAIL_TAGS: &str = r#"
+CREATE TABLE IF NOT EXISTS email_tags (
+  email_id INTEGER NOT NULL,
+  tag_id INTEGER NOT NULL,
+  PRIMARY KEY (email_id, tag_id)
+);"#;
+
+pub const QUERY_INSERT_TAG: &str = r#"
+INSERT OR IGNORE INTO tags (name) VALUES (?)"#;
+
+pub const QUERY_INSERT_EMAIL_TAG: &str = r#"
+INSERT OR IGNORE INTO email_tags (email_id, tag_id)
+SELECT ?, id FROM tags WHERE name = ?"#;
+
 pub const TBL_ERRORS: &str = r#"
 CREATE TABLE IF NOT EXISTS errors (
-  message TEXT NOT NULL
+  path TEXT NOT NULL,
+  format TEXT NULL,
+  kind TEXT NOT NULL,
+  message TEXT NOT NULL,
+  snippet TEXT NULL
 );"#;
 
 pub const QUERY_ERRORS: &str = r#"
 INSERT INTO errors
-    (message)
+    (path, format, kind, message, snippet)
 VALUES
-    (?)
+    (?, ?, ?, ?, ?)
+"#;
+
+pub const QUERY_SELECT_ERRORS: &str = r#"
+SELECT path, format, kind, message, snippet FROM errors"#;
+
+/// Indexes on the fields that are commonly filtered by
+pub const QUERY_CREATE_INDEXES: &str = r#"
+CREATE INDEX IF NOT EXISTS emails_sender_domain ON emails (sender_domain);
+CREATE INDEX IF NOT EXISTS emails_sender_organization ON emails (sender_organization);
+CREATE INDEX IF NOT EXISTS emails_sender_local_part ON emails (sender_local_part);
+CREATE INDEX IF NOT EXISTS emails_to_address ON emails (to_address);
+CREATE INDEX IF NOT EXISTS emails_date ON emails (year, month, day);
+CREATE INDEX IF NOT EXISTS email_tags_tag_id ON email_tags (tag_id);
 "#;
 
 pub const TBL_META: &str = r#"
@@ -63,9 +185,33 @@ VALUES
     (?, ?)
 "#;
 
+pub const QUERY_DELETE_META: &str = r#"
+DELETE FROM meta WHERE key = ?
+"#;
+
 pub const QUERY_SELECT_META: &str = r#"
 SELECT key, value FROM meta"#;
 
 pub const QUERY_COUNT_MAILS: &str = r#"
 SELECT count(path) FROM emails
 "#;
+
+pub const QUERY_COUNT_SEND_MAILS: &str = r#"
+SELECT count(path) FROM emails WHERE is_send
+"#;
+
+/// Pair each reply with the mail it replies to (by `In-Reply-To` and `Message-Id`)
+/// and store the time it took to reply. Only replies between the user and a
+/// correspondent count, so `is_send` has to be set beforehand.
+pub const QUERY_UPDATE_REPLY_LATENCY: &str = r#"
+CREATE INDEX IF NOT EXISTS emails_message_id ON emails (message_id);
+UPDATE emails SET reply_latency = NULL, reply_domain = NULL
+WHERE reply_latency IS NOT NULL OR reply_domain IS NOT NULL;
+UPDATE emails AS reply SET
+    reply_latency = reply.timestamp - parent.timestamp,
+    reply_domain = CASE WHEN reply.is_send THEN parent.sender_domain ELSE reply.sender_domain END
+FROM emails AS parent
+WHERE reply.in_reply_to = parent.message_id
+    AND reply.is_send != parent.is_send
+    AND reply.timestamp >= parent.timestamp;
+"#;
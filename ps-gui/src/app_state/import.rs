@@ -11,8 +11,8 @@ use super::super::widgets::background::{shadow_background, AnimatedBackground};
 use super::Textures;
 use super::{StateUIAction, StateUIVariant};
 use ps_core::{
-    message_adapter::{Adapter, State},
-    Config, DatabaseLike, FormatType,
+    message_adapter::{Adapter, State, Statistics},
+    Config, DatabaseLike, ImportPhase,
 };
 
 pub struct ImporterUI {
@@ -39,6 +39,12 @@ pub struct ImporterUI {
     progress_divisions: usize,
     /// we're done importing
     pub done_importing: bool,
+    /// Timing and throughput of the import
+    statistics: Statistics,
+    /// The user cancelled the import
+    cancelling: bool,
+    /// The import was cancelled and the database removed
+    cancelled: bool,
     /// Total amount of mails we imported
     pub total_mails: usize,
     /// Any errors during importing
@@ -54,6 +60,7 @@ impl ImporterUI {
         config: Config,
         database: Database,
     ) -> Result<Self> {
+        config.check_source_names()?;
         let cloned_config = config.clone();
         // Build a random distribution of elements
         // to animate the import process
@@ -70,32 +77,11 @@ impl ImporterUI {
         // The adapter that controls the syncing
         let adapter = Adapter::new();
 
-        // Could not figure out how to build this properly
-        // with dynamic dispatch. (to abstract away the match)
-        // Will try again when I'm online.
+        // All sources of the config are imported one after the other.
         // On Wasm, we just do nothing. Wasm is just a demo and
         // the importer will never be run.
-
         #[cfg(not(target_arch = "wasm32"))]
-        let handle = match config.format {
-            FormatType::AppleMail => {
-                let importer = ps_importer::applemail_importer(config);
-                adapter.process(database, importer)?
-            }
-            FormatType::GmailVault => {
-                let importer = ps_importer::gmail_importer(config);
-                adapter.process(database, importer)?
-            }
-            FormatType::Mbox => {
-                let importer = ps_importer::mbox_importer(config);
-                adapter.process(database, importer)?
-            }
-            #[cfg(not(target_os = "windows"))]
-            FormatType::Maildir => {
-                let importer = ps_importer::maildir_importer(config);
-                adapter.process(database, importer)?
-            }
-        };
+        let handle = adapter.process(database, ps_importer::sources_importer(config))?;
 
         #[cfg(target_arch = "wasm32")]
         let handle = std::thread::spawn(|| Ok(()));
@@ -111,6 +97,9 @@ impl ImporterUI {
             progress_blocks,
             progress_divisions,
             done_importing: false,
+            statistics: Statistics::default(),
+            cancelling: false,
+            cancelled: false,
             total_mails: 0,
             importer_error: None,
             missing_permissions: false,
@@ -130,6 +119,9 @@ impl StateUIVariant for ImporterUI {
                 report,
                 config: self.config.clone(),
             },
+            (_, true) if self.cancelled => StateUIAction::Close {
+                config: self.config.clone(),
+            },
             (_, true) => StateUIAction::ImportDone {
                 config: self.config.clone(),
                 total: self.total_mails,
@@ -147,7 +139,7 @@ impl ImporterUI {
 
         let available = ui.available_size();
 
-        let (label, progress, writing, done, written) = match self.handle_adapter() {
+        let (label, progress, writing, done, written, cancelled) = match self.handle_adapter() {
             Ok(state) => {
                 #[cfg(target_os = "macos")]
                 if state.missing_permissions {
@@ -160,13 +152,14 @@ impl ImporterUI {
                     writing,
                     written,
                     done,
+                    cancelled,
                     ..
                 } = state;
-                (label, progress, writing, done, written)
+                (label, progress, writing, done, written, cancelled)
             }
             Err(e) => {
                 // Generate a response signifying we're done - as there was an error
-                let response = (format!("Error {}", &e), 1.0, false, true, 0);
+                let response = (format!("Error {}", &e), 1.0, false, true, 0, false);
                 self.importer_error = Some(e);
                 response
             }
@@ -182,6 +175,7 @@ impl ImporterUI {
                 self.importer_error = handle.join().ok().map(|e| e.err()).flatten();
             }
             self.done_importing = true;
+            self.cancelled = cancelled;
             self.total_mails = written;
         }
 
@@ -199,7 +193,7 @@ impl ImporterUI {
             .draw_background(ui, available);
         }
 
-        let desired_height = 370.0 - (220.0 * growth) as f32;
+        let desired_height = 370.0 - (130.0 * growth) as f32;
         let desired_size = egui::vec2(330.0, desired_height);
 
         let paint_rect = Rect::from_min_size(
@@ -261,6 +255,37 @@ impl ImporterUI {
                 ui.add_space(20.0);
             }
             ui.small(label);
+            let statistics = &self.statistics;
+            let details = [
+                statistics.throughput_label(),
+                statistics.eta_label(),
+                statistics.current_file.as_ref().map(|path| {
+                    let name = path.file_name().unwrap_or_else(|| path.as_os_str());
+                    name.to_string_lossy().to_string()
+                }),
+            ];
+            for detail in details.iter().flatten() {
+                ui.small(detail);
+            }
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                if self.cancelling {
+                    ui.label("Cancelling...");
+                    return;
+                }
+                let paused = self.adapter.is_paused();
+                if ui.button(if paused { "Resume" } else { "Pause" }).clicked() {
+                    if paused {
+                        self.adapter.resume();
+                    } else {
+                        self.adapter.pause();
+                    }
+                }
+                if ui.button("Cancel").clicked() {
+                    self.cancelling = true;
+                    self.adapter.cancel();
+                }
+            });
         })
         .response
     }
@@ -302,6 +327,7 @@ struct InternalAdapterState {
     writing: bool,
     written: usize,
     done: bool,
+    cancelled: bool,
     #[cfg(target_os = "macos")]
     missing_permissions: bool,
 }
@@ -330,13 +356,23 @@ impl ImporterUI {
         let State {
             done,
             finishing,
+            cancelled,
             written,
             #[cfg(target_os = "macos")]
             missing_permissions,
         } = self.adapter.finished()?;
 
+        self.statistics = self.adapter.statistics()?;
+
         if finishing {
-            label = "Finishing Up".into();
+            label = match self.statistics.phase {
+                Some(phase) => format!("{}...", phase.name()),
+                None => "Finishing Up".into(),
+            };
+        } else if self.adapter.is_paused() {
+            label = "Paused".into();
+        } else if self.statistics.phase == Some(ImportPhase::Discovering) {
+            label = "Discovering emails...".into();
         }
         Ok(InternalAdapterState {
             label,
@@ -344,6 +380,7 @@ impl ImporterUI {
             writing,
             written,
             done,
+            cancelled,
             #[cfg(target_os = "macos")]
             missing_permissions,
         })
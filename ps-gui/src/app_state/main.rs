@@ -1,16 +1,32 @@
 use eframe::egui::{self, Stroke};
 use ps_core::eyre::{Report, Result};
 
+#[cfg(not(target_arch = "wasm32"))]
+use super::super::export_panel::{open_database, ExportPanel, ExportState, OpenDatabase};
+#[cfg(not(target_arch = "wasm32"))]
+use super::super::identities_panel::{
+    recompute_is_send, AddressesPanel, AddressesState, RecomputeIsSend,
+};
+use super::super::issues_panel::IssuesPanel;
+#[cfg(not(target_arch = "wasm32"))]
+use super::super::lists_panel::{ListsPanel, ListsState};
+use super::super::preview_panel::{PreviewPanel, PreviewState};
+#[cfg(not(target_arch = "wasm32"))]
+use super::super::replies_panel::{RepliesPanel, RepliesState};
 use super::super::widgets::{FilterState, Spinner};
 use super::Textures;
 use super::{StateUIAction, StateUIVariant};
-use ps_core::{model::Engine, Config, DatabaseLike};
+use ps_core::{model::Engine, Config, DatabaseLike, ImportError, QueryRow};
 
 #[derive(Default)]
 pub struct UIState {
     pub show_emails: bool,
     pub show_filters: bool,
     pub show_export: bool,
+    pub show_issues: bool,
+    pub show_addresses: bool,
+    pub show_lists: bool,
+    pub show_replies: bool,
     pub action_close: bool,
 }
 
@@ -21,19 +37,52 @@ pub struct MainUI {
     state: UIState,
     filter_state: FilterState,
     total: usize,
+    /// The mails that could not be imported
+    import_issues: Vec<ImportError>,
+    /// The mail selected in the mail list
+    selected_mail: Option<QueryRow>,
+    preview: Option<PreviewState>,
+    #[cfg(not(target_arch = "wasm32"))]
+    export: ExportState,
+    #[cfg(not(target_arch = "wasm32"))]
+    open_database: OpenDatabase,
+    #[cfg(not(target_arch = "wasm32"))]
+    addresses: AddressesState,
+    #[cfg(not(target_arch = "wasm32"))]
+    recompute_is_send: RecomputeIsSend,
+    #[cfg(not(target_arch = "wasm32"))]
+    lists: ListsState,
+    #[cfg(not(target_arch = "wasm32"))]
+    replies: RepliesState,
 }
 
 impl MainUI {
     pub fn new<Database: DatabaseLike>(config: Config, total: usize) -> Result<Self> {
         let mut engine = Engine::new::<Database>(&config)?;
         engine.start()?;
+        let import_issues = Database::new(&config.database_path)?.import_errors()?;
         Ok(Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            addresses: AddressesState::new(&config),
+            #[cfg(not(target_arch = "wasm32"))]
+            recompute_is_send: recompute_is_send::<Database>,
             config,
             engine,
             error: None,
             state: UIState::default(),
             filter_state: FilterState::new(),
             total,
+            import_issues,
+            selected_mail: None,
+            preview: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            export: ExportState::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            open_database: open_database::<Database>,
+            #[cfg(not(target_arch = "wasm32"))]
+            lists: ListsState::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            replies: RepliesState::default(),
         })
     }
 }
@@ -64,16 +113,41 @@ impl StateUIVariant for MainUI {
                     &mut self.state,
                     &mut self.filter_state,
                     self.total,
+                    self.import_issues.len(),
                 ));
             });
 
         if self.state.show_emails {
+            if let Some(row) = &self.selected_mail {
+                let is_loaded = self
+                    .preview
+                    .as_ref()
+                    .map(|p| p.is_for(row))
+                    .unwrap_or(false);
+                if !is_loaded {
+                    self.preview = PreviewState::load(&self.config.format_of_row(row), row);
+                }
+            }
+            if let Some(preview) = &self.preview {
+                let mut close = false;
+                egui::SidePanel::right("preview_panel")
+                    .default_width(400.0)
+                    .show(ctx, |ui| {
+                        ui.add(PreviewPanel::new(preview, &mut close));
+                    });
+                if close {
+                    self.selected_mail = None;
+                    self.preview = None;
+                }
+            }
+
             egui::SidePanel::right("left_panel")
                 .default_width(500.0)
                 .show(ctx, |ui| {
                     ui.add(super::super::mail_panel::MailPanel::new(
                         &mut self.engine,
                         &mut self.error,
+                        &mut self.selected_mail,
                     ));
                 });
         }
@@ -110,6 +184,88 @@ impl StateUIVariant for MainUI {
                 }
             });
 
+        egui::Window::new("Import Issues")
+            .open(&mut self.state.show_issues)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.add(IssuesPanel::new(&self.import_issues));
+            });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            egui::Window::new("Export")
+                .open(&mut self.state.show_export)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.add(ExportPanel::new(
+                        &self.engine,
+                        &self.config,
+                        self.open_database,
+                        &mut self.export,
+                    ));
+                });
+            if self.export.is_running() {
+                ctx.request_repaint();
+            }
+
+            egui::Window::new("Unsubscribe Candidates")
+                .open(&mut self.state.show_lists)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.add(ListsPanel::new(
+                        &self.config,
+                        self.open_database,
+                        self.total,
+                        &mut self.lists,
+                    ));
+                });
+            if self.lists.is_running() {
+                ctx.request_repaint();
+            }
+
+            egui::Window::new("Response Times")
+                .open(&mut self.state.show_replies)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.add(RepliesPanel::new(
+                        &self.config,
+                        self.open_database,
+                        self.total,
+                        &mut self.replies,
+                    ));
+                });
+            if self.replies.is_running() {
+                ctx.request_repaint();
+            }
+
+            let mut updated = None;
+            egui::Window::new("Your Addresses")
+                .open(&mut self.state.show_addresses)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.add(AddressesPanel::new(
+                        &self.config,
+                        self.recompute_is_send,
+                        &mut self.addresses,
+                        &mut updated,
+                    ));
+                });
+            if self.addresses.is_running() {
+                ctx.request_repaint();
+            }
+            // Reload everything, so that the segmentations and the
+            // response times use the new `is_send`
+            if let Some(config) = updated {
+                self.replies.reset();
+                return StateUIAction::ImportDone {
+                    config,
+                    total: self.total,
+                };
+            }
+        }
+
         // If we're waiting for a computation to succeed, we re-render again.
         if self.engine.is_busy() {
             ctx.request_repaint();
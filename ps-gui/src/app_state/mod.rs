@@ -13,7 +13,7 @@ pub use main::{MainUI, UIState};
 use ps_core::eyre::Report;
 pub use startup::StartupUI;
 
-use ps_core::{Config, DatabaseLike, FormatType};
+use ps_core::{Config, DatabaseLike, FormatType, ImportFilter};
 
 pub enum StateUIAction {
     CreateDatabase {
@@ -21,6 +21,7 @@ pub enum StateUIAction {
         emails_folder_path: PathBuf,
         sender_emails: Vec<String>,
         format: FormatType,
+        filter: ImportFilter,
     },
     OpenDatabase {
         database_path: PathBuf,
@@ -42,7 +43,7 @@ pub enum StateUIAction {
 pub enum StateUI {
     Startup(startup::StartupUI),
     Import(import::ImporterUI),
-    Main(main::MainUI),
+    Main(Box<main::MainUI>),
     Error(error::ErrorUI),
 }
 
@@ -75,12 +76,14 @@ impl StateUI {
                 emails_folder_path,
                 sender_emails,
                 format,
+                filter,
             } => {
                 *self = self.create_database::<Database>(
                     database_path,
                     emails_folder_path,
                     sender_emails,
                     format,
+                    filter,
                 )
             }
             StateUIAction::OpenDatabase { database_path } => {
@@ -88,7 +91,7 @@ impl StateUI {
             }
             StateUIAction::ImportDone { config, total } => {
                 *self = match main::MainUI::new::<Database>(config.clone(), total) {
-                    Ok(n) => StateUI::Main(n),
+                    Ok(n) => StateUI::Main(Box::new(n)),
                     Err(e) => StateUI::Error(ErrorUI::new(e, Some(config))),
                 };
             }
@@ -111,7 +114,9 @@ impl StateUI {
 
     #[cfg(target_arch = "wasm32")]
     pub fn new<Database: DatabaseLike>(config: Config, total: usize) -> StateUI {
-        StateUI::Main(main::MainUI::new::<Database>(config, total).unwrap())
+        StateUI::Main(Box::new(
+            main::MainUI::new::<Database>(config, total).unwrap(),
+        ))
     }
 
     pub fn create_database<Database: DatabaseLike>(
@@ -120,9 +125,10 @@ impl StateUI {
         emails_folder_path: PathBuf,
         sender_emails: Vec<String>,
         format: FormatType,
+        filter: ImportFilter,
     ) -> StateUI {
         let config = match Config::new(database_path, emails_folder_path, sender_emails, format) {
-            Ok(n) => n,
+            Ok(n) => Config { filter, ..n },
             Err(e) => {
                 return StateUI::Error(error::ErrorUI::new(e, None));
             }
@@ -148,7 +154,7 @@ impl StateUI {
         };
 
         match main::MainUI::new::<Database>(config.clone(), total) {
-            Ok(n) => StateUI::Main(n),
+            Ok(n) => StateUI::Main(Box::new(n)),
             Err(e) => StateUI::Error(ErrorUI::new(e, Some(config))),
         }
     }
@@ -5,11 +5,20 @@ use eframe::egui::{self, vec2, Color32, Pos2, Rect, Response, Stroke, TextStyle,
 use std::path::PathBuf;
 use std::str::FromStr;
 
+#[cfg(not(target_arch = "wasm32"))]
+use super::super::identities_panel::{split_addresses, CandidatesPanel, DetectionState};
 use super::super::platform::platform_colors;
 use super::super::widgets::background::{shadow_background, AnimatedBackground};
 use super::Textures;
 use super::{StateUIAction, StateUIVariant};
-use ps_core::{Config, FormatType};
+#[cfg(not(target_arch = "wasm32"))]
+use ps_core::crossbeam_channel::{unbounded, Receiver, TryRecvError};
+#[cfg(not(target_arch = "wasm32"))]
+use ps_core::eyre::eyre;
+use ps_core::eyre::Result;
+use ps_core::{Config, FormatType, ImportFilter};
+#[cfg(not(target_arch = "wasm32"))]
+use ps_importer::FormatCandidate;
 
 #[derive(Default)]
 pub struct StartupUI {
@@ -32,6 +41,26 @@ pub struct StartupUI {
     error_message: Option<String>,
     /// The result of the actions
     action: Option<StateUIAction>,
+    /// Detects the email addresses of the user in the email folder
+    #[cfg(not(target_arch = "wasm32"))]
+    detection: DetectionState,
+    /// Probes the format of the email folder on a background thread
+    #[cfg(not(target_arch = "wasm32"))]
+    probing: Option<Receiver<Result<Vec<FormatCandidate>>>>,
+    /// The format that was detected in the email folder
+    format_hint: Option<String>,
+    /// Should only some of the mails be imported
+    use_filter: bool,
+    /// The comma separated folder patterns to import
+    include_folders: String,
+    /// The comma separated folder patterns to skip
+    exclude_folders: String,
+    /// Skip mails before this `YYYY-MM-DD` date
+    from_date: String,
+    /// Skip mails after this `YYYY-MM-DD` date
+    until_date: String,
+    /// The comma separated tags to skip
+    skip_tags: String,
 }
 
 impl StartupUI {
@@ -48,7 +77,18 @@ impl StartupUI {
             true => (true, Some(config.database_path)),
             false => (false, None),
         };
+        let filter = &config.filter;
+        let date = |date: Option<ps_core::chrono::NaiveDate>| {
+            date.map(|e| e.format("%Y-%m-%d").to_string())
+                .unwrap_or_default()
+        };
         Self {
+            use_filter: !filter.is_empty(),
+            include_folders: filter.include_folders.join(", "),
+            exclude_folders: filter.exclude_folders.join(", "),
+            from_date: date(filter.from_date),
+            until_date: date(filter.until_date),
+            skip_tags: filter.skip_tags.join(", "),
             format: config.format,
             email_folder: Some(config.emails_folder_path),
             database_path,
@@ -70,6 +110,10 @@ impl StateUIVariant for StartupUI {
             .show(ctx, |ui| {
                 ui.add(|ui: &mut egui::Ui| self.ui(ui));
             });
+        #[cfg(not(target_arch = "wasm32"))]
+        self.detection_window(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_probe(ctx);
         // If we generated an action above, return it
         self.action.take().unwrap_or(StateUIAction::Nothing)
     }
@@ -94,7 +138,10 @@ impl StartupUI {
         // `ui.allocate_exact_size`
         // `ui.allocate_with_layout`
         // and variations. This, at least, worked.
-        let desired_size = egui::vec2(450.0, 370.0);
+        let desired_size = match self.use_filter {
+            true => egui::vec2(450.0, 510.0),
+            false => egui::vec2(450.0, 400.0),
+        };
 
         let paint_rect = Rect::from_min_size(
             Pos2 {
@@ -148,6 +195,13 @@ impl StartupUI {
                             .text_color(platform_colors().text_primary)
                             .text_style(TextStyle::Body),
                     );
+                    if let Some(hint) = &self.format_hint {
+                        ui.add(
+                            egui::widgets::Label::new(hint)
+                                .text_color(platform_colors().text_secondary)
+                                .text_style(TextStyle::Small),
+                        );
+                    }
                     ui.end_row();
 
                     ui.horizontal(|ui| {
@@ -178,8 +232,11 @@ impl StartupUI {
                         self.email_address = Some(txt);
                     }
 
-                    ui.small_button("?")
-                        .on_hover_text("Multiple addresses can be\nseparated by comma (,)");
+                    ui.horizontal(|ui| {
+                        ui.small_button("?")
+                            .on_hover_text("Multiple addresses can be\nseparated by comma (,)");
+                        self.detect_button(ui);
+                    });
                     ui.end_row();
 
                     ui.add(
@@ -210,6 +267,17 @@ impl StartupUI {
                     }
                     ui.end_row();
 
+                    ui.checkbox(&mut self.use_filter, "Only Import Some Mails?");
+                    ui.small_button("?").on_hover_text(
+                        "Skip folders, tags or mails\noutside of a date range.\nFolders and tags can be\nseparated by comma (,)\nand contain wildcards (*)",
+                    );
+                    ui.end_row();
+
+                    if self.use_filter {
+                        self.filter_fields(ui, center.width() * 0.7);
+                        ui.end_row();
+                    }
+
                     let button_size1: Vec2 = ((center.width() / 2.0) - 25.0, 25.0).into();
                     let button_size2: Vec2 = ((center.width() / 2.0) - 25.0, 25.0).into();
                     ui.horizontal(|ui| {
@@ -245,13 +313,159 @@ impl StartupUI {
         response.response
     }
 
+    /// The text fields of the import filter
+    fn filter_fields(&mut self, ui: &mut egui::Ui, width: f32) {
+        egui::Grid::new("import_filter_grid")
+            .spacing(vec2(10.0, 8.0))
+            .show(ui, |ui| {
+                let fields = [
+                    (
+                        "Folders:",
+                        &mut self.include_folders,
+                        "All, or e.g. Inbox, Work*",
+                    ),
+                    (
+                        "Skip Folders:",
+                        &mut self.exclude_folders,
+                        "e.g. Spam, Trash, Drafts",
+                    ),
+                    ("Skip Tags:", &mut self.skip_tags, "e.g. Spam, Trash"),
+                    ("From:", &mut self.from_date, "YYYY-MM-DD"),
+                    ("Until:", &mut self.until_date, "YYYY-MM-DD"),
+                ];
+                for (label, text, hint) in fields {
+                    ui.add(
+                        egui::widgets::Label::new(label)
+                            .text_color(platform_colors().text_secondary)
+                            .text_style(TextStyle::Small),
+                    );
+                    ui.add(
+                        egui::TextEdit::singleline(text)
+                            .hint_text(hint)
+                            .desired_width(width * 0.7),
+                    );
+                    ui.end_row();
+                }
+            });
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     fn set_default_path(&mut self) {
         self.email_folder = ps_importer::default_path(&self.format);
+        self.probe_format();
+    }
+
+    /// Probe the format of the email folder on a background thread,
+    /// as walking a large folder would block the UI
+    #[cfg(not(target_arch = "wasm32"))]
+    fn probe_format(&mut self) {
+        let folder = match &self.email_folder {
+            Some(n) => n.clone(),
+            None => return,
+        };
+        let (sender, receiver) = unbounded();
+        std::thread::spawn(move || {
+            // The receiver is gone if another folder was selected in the meantime
+            let _ = sender.send(ps_importer::probe_format(&folder));
+        });
+        self.probing = Some(receiver);
+        self.format_hint = Some("Detecting format...".to_string());
+    }
+
+    /// Preselect the format that was found in the email folder
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_probe(&mut self, ctx: &egui::CtxRef) {
+        let result = match self.probing.as_ref().map(|r| r.try_recv()) {
+            Some(Ok(result)) => result,
+            Some(Err(TryRecvError::Empty)) => {
+                ctx.request_repaint();
+                return;
+            }
+            Some(Err(TryRecvError::Disconnected)) => Err(eyre!("Could not detect the format")),
+            None => return,
+        };
+        self.probing = None;
+        let candidates = match result {
+            Ok(n) => n,
+            Err(e) => {
+                self.format_hint = Some(format!("{}", e));
+                return;
+            }
+        };
+        self.format_hint = match candidates.first() {
+            None => Some("No mails found".to_string()),
+            Some(candidate) => match candidate.supported() {
+                Some(format) => {
+                    self.format = format;
+                    Some(format!(
+                        "Detected {} ({:.0}%)",
+                        format.name(),
+                        candidate.confidence * 100.0
+                    ))
+                }
+                None => Some(format!("{} can't be imported", candidate.format.name())),
+            },
+        };
     }
 
     #[cfg(target_arch = "wasm32")]
     fn set_default_path(&mut self) {}
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn detect_button(&mut self, ui: &mut egui::Ui) {
+        let enabled = self.email_folder.is_some() && !self.detection.is_running();
+        let response = ui
+            .add_enabled(enabled, egui::Button::new("Detect").small())
+            .on_hover_text("Find your addresses in\nthe mails of the email folder");
+        if !response.clicked() {
+            return;
+        }
+        let folder = match &self.email_folder {
+            Some(n) => n,
+            None => return,
+        };
+        match Config::new(None, folder, Vec::new(), self.format) {
+            Ok(config) => self.detection.start(config),
+            Err(e) => self.error_message = Some(format!("{}", e)),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn detect_button(&mut self, _ui: &mut egui::Ui) {}
+
+    /// Let the user confirm the detected addresses
+    #[cfg(not(target_arch = "wasm32"))]
+    fn detection_window(&mut self, ctx: &egui::CtxRef) {
+        if !self.detection.is_running()
+            && !self.detection.has_candidates()
+            && !self.detection.has_error()
+        {
+            return;
+        }
+        let mut confirmed = None;
+        egui::Window::new("Your Addresses")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add(CandidatesPanel::new(&mut self.detection, &mut confirmed));
+            });
+        if let Some(confirmed) = confirmed {
+            let mut addresses = self
+                .email_address
+                .as_deref()
+                .map(split_addresses)
+                .unwrap_or_default();
+            for address in confirmed {
+                if !addresses.contains(&address) {
+                    addresses.push(address);
+                }
+            }
+            self.email_address = Some(addresses.join(", "));
+        }
+        if self.detection.is_running() {
+            ctx.request_repaint();
+        }
+    }
 }
 
 impl StartupUI {
@@ -278,14 +492,37 @@ impl StartupUI {
             return;
         }
 
+        let filter = match self.import_filter() {
+            Ok(n) => n,
+            Err(e) => {
+                self.error_message = Some(format!("{}", e));
+                return;
+            }
+        };
+
         self.action = Some(StateUIAction::CreateDatabase {
             database_path: self.database_path.clone(),
             emails_folder_path: email,
             sender_emails: emails,
             format: self.format,
+            filter,
         });
     }
 
+    /// The filter from the filter fields, if the filter is enabled
+    fn import_filter(&self) -> Result<ImportFilter> {
+        if !self.use_filter {
+            return Ok(ImportFilter::default());
+        }
+        Ok(ImportFilter {
+            include_folders: ImportFilter::parse_list(&self.include_folders)?,
+            exclude_folders: ImportFilter::parse_list(&self.exclude_folders)?,
+            from_date: ImportFilter::parse_date(&self.from_date)?,
+            until_date: ImportFilter::parse_date(&self.until_date)?,
+            skip_tags: ImportFilter::parse_list(&self.skip_tags)?,
+        })
+    }
+
     fn action_open_database(&mut self) {
         let path = match self.open_database_dialog() {
             Some(n) => n,
@@ -331,6 +568,7 @@ impl StartupUI {
             None => return,
         };
         self.email_folder = Some(path);
+        self.probe_format();
     }
 
     #[cfg(target_arch = "wasm32")]
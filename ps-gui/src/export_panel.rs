@@ -0,0 +1,325 @@
+//! A panel to export the mails or the data of the current selection
+use eframe::egui::{self, Widget};
+use ps_core::crossbeam_channel::{unbounded, Receiver};
+use ps_core::eyre::{Report, Result};
+use ps_core::{
+    export::{export_table, DataExportFormat, ExportTable, EXPORT_FIELDS},
+    model::{
+        contact_graph::{export_graph, ContactGraph, GraphExportFormat, GRAPH_FIELDS},
+        items, Engine,
+    },
+    Config, DatabaseLike, DatabaseQuery, Query, QueryResult, QueryRow,
+};
+use ps_importer::MailExportFormat;
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::widgets::Spinner;
+
+/// Opens a new connection to the database. As `MainUI` is not generic over
+/// the `Database`, this is how exports get their own connection for their thread.
+pub type OpenDatabase = fn(&Path) -> Result<Box<dyn DatabaseQuery>>;
+
+pub fn open_database<Database: DatabaseLike>(path: &Path) -> Result<Box<dyn DatabaseQuery>> {
+    Ok(Box::new(Database::new(path)?))
+}
+
+pub struct ExportState {
+    format: MailExportFormat,
+    data_format: DataExportFormat,
+    graph_format: GraphExportFormat,
+    /// Receives the status message of a running export
+    running: Option<Receiver<Result<String>>>,
+    /// The outcome of the last export
+    status: Option<String>,
+}
+
+impl Default for ExportState {
+    fn default() -> Self {
+        Self {
+            format: MailExportFormat::Mbox,
+            data_format: DataExportFormat::Csv,
+            graph_format: GraphExportFormat::GraphMl,
+            running: None,
+            status: None,
+        }
+    }
+}
+
+impl ExportState {
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    fn update(&mut self) {
+        let result = match self.running.as_ref().map(|r| r.try_recv()) {
+            Some(Ok(result)) => result,
+            _ => return,
+        };
+        self.running = None;
+        self.status = Some(match result {
+            Ok(message) => message,
+            Err(e) => format!("Export failed: {}", e),
+        });
+    }
+}
+
+pub struct ExportPanel<'a> {
+    engine: &'a Engine,
+    config: &'a Config,
+    open_database: OpenDatabase,
+    state: &'a mut ExportState,
+}
+
+impl<'a> ExportPanel<'a> {
+    pub fn new(
+        engine: &'a Engine,
+        config: &'a Config,
+        open_database: OpenDatabase,
+        state: &'a mut ExportState,
+    ) -> Self {
+        Self {
+            engine,
+            config,
+            open_database,
+            state,
+        }
+    }
+}
+
+impl<'a> Widget for ExportPanel<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let Self {
+            engine,
+            config,
+            open_database,
+            state,
+        } = self;
+        state.update();
+        ui.vertical(|ui| {
+            ui.label(format!(
+                "Export the {} mails of the current selection",
+                items::count(engine)
+            ));
+            ui.add_space(5.0);
+            for format in MailExportFormat::all_cases() {
+                ui.radio_value(&mut state.format, *format, format.name());
+            }
+            ui.add_space(5.0);
+            let running = state.is_running();
+            if ui
+                .add_enabled(!running, egui::Button::new("Export Mails..."))
+                .clicked()
+            {
+                if let Some(target) = select_target(state.format) {
+                    state.status = None;
+                    state.running = Some(export_mails(
+                        engine,
+                        config,
+                        open_database,
+                        state.format,
+                        target,
+                    ));
+                }
+            }
+            ui.separator();
+            ui.label("Export the segments or the items of the current selection as data");
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                for format in DataExportFormat::all_cases() {
+                    ui.radio_value(&mut state.data_format, *format, format.name());
+                }
+            });
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                let segmentation = engine.segmentations().last();
+                let can_export_segments = !running && segmentation.is_some();
+                if ui
+                    .add_enabled(can_export_segments, egui::Button::new("Export Segments..."))
+                    .clicked()
+                {
+                    if let (Some(segmentation), Some(target)) =
+                        (segmentation, select_data_target(state.data_format))
+                    {
+                        let table = ExportTable::from_segmentation(segmentation);
+                        state.status =
+                            Some(match export_table(&table, state.data_format, &target) {
+                                Ok(_) => format!("Exported {} segments", table.rows.len()),
+                                Err(e) => format!("Export failed: {}", e),
+                            });
+                    }
+                }
+                if ui
+                    .add_enabled(!running, egui::Button::new("Export Items..."))
+                    .clicked()
+                {
+                    if let Some(target) = select_data_target(state.data_format) {
+                        state.status = None;
+                        state.running = Some(export_items(
+                            engine,
+                            config,
+                            open_database,
+                            state.data_format,
+                            target,
+                        ));
+                    }
+                }
+            });
+            ui.separator();
+            ui.label("Export who writes to whom in the current selection as a graph");
+            ui.add_space(5.0);
+            ui.horizontal(|ui| {
+                for format in GraphExportFormat::all_cases() {
+                    ui.radio_value(&mut state.graph_format, *format, format.name());
+                }
+            });
+            ui.add_space(5.0);
+            if ui
+                .add_enabled(!running, egui::Button::new("Export Contacts..."))
+                .clicked()
+            {
+                if let Some(target) = select_graph_target(state.graph_format) {
+                    state.status = None;
+                    state.running = Some(export_contacts(
+                        engine,
+                        config,
+                        open_database,
+                        state.graph_format,
+                        target,
+                    ));
+                }
+            }
+            if running {
+                ui.horizontal(|ui| {
+                    ui.add(Spinner::new(egui::vec2(15.0, 15.0)));
+                    ui.label("Exporting...");
+                });
+            }
+            if let Some(status) = &state.status {
+                ui.label(status);
+            }
+        })
+        .response
+    }
+}
+
+/// Run the mail export on a background thread
+fn export_mails(
+    engine: &Engine,
+    config: &Config,
+    open_database: OpenDatabase,
+    format: MailExportFormat,
+    target: PathBuf,
+) -> Receiver<Result<String>> {
+    let source_config = config.clone();
+    run_export(
+        items::items_query(engine),
+        config,
+        open_database,
+        move |rows| {
+            let count = ps_importer::export_mails(&source_config, &rows, format, &target)?;
+            Ok(format!("Exported {} mails", count))
+        },
+    )
+}
+
+/// Run the item data export on a background thread
+fn export_items(
+    engine: &Engine,
+    config: &Config,
+    open_database: OpenDatabase,
+    format: DataExportFormat,
+    target: PathBuf,
+) -> Receiver<Result<String>> {
+    run_export(
+        items::items_query_with_fields(engine, EXPORT_FIELDS),
+        config,
+        open_database,
+        move |rows| {
+            let table = ExportTable::from_rows(EXPORT_FIELDS, &rows);
+            export_table(&table, format, &target)?;
+            Ok(format!("Exported {} items", table.rows.len()))
+        },
+    )
+}
+
+/// Run the contact graph export on a background thread
+fn export_contacts(
+    engine: &Engine,
+    config: &Config,
+    open_database: OpenDatabase,
+    format: GraphExportFormat,
+    target: PathBuf,
+) -> Receiver<Result<String>> {
+    run_export(
+        items::items_query_with_fields(engine, GRAPH_FIELDS),
+        config,
+        open_database,
+        move |rows| {
+            let graph = ContactGraph::from_rows(&rows);
+            export_graph(&graph, format, &target)?;
+            Ok(format!(
+                "Exported {} contacts and {} connections",
+                graph.nodes.len(),
+                graph.edges.len()
+            ))
+        },
+    )
+}
+
+/// Query the rows with a new database connection and hand them to `action`
+fn run_export(
+    query: Query,
+    config: &Config,
+    open_database: OpenDatabase,
+    action: impl FnOnce(Vec<QueryRow>) -> Result<String> + Send + 'static,
+) -> Receiver<Result<String>> {
+    let (sender, receiver) = unbounded();
+    let database_path = config.database_path.clone();
+    std::thread::spawn(move || {
+        let run = || -> Result<String> {
+            let database = open_database(&database_path)?;
+            let rows: Vec<_> = database
+                .query(&query)?
+                .into_iter()
+                .filter_map(|result| match result {
+                    QueryResult::Normal(row) => Some(row),
+                    _ => None,
+                })
+                .collect();
+            action(rows)
+        };
+        if let Err(e) = sender.send(run()) {
+            ps_core::tracing::error!("Could not send export result: {}", Report::new(e));
+        }
+    });
+    receiver
+}
+
+fn select_target(format: MailExportFormat) -> Option<PathBuf> {
+    let default_path = shellexpand::tilde("~/").to_string();
+    let selection = if format.is_folder() {
+        tinyfiledialogs::select_folder_dialog("Select export folder", &default_path)
+    } else {
+        let default_file = format!("{}export.mbox", default_path);
+        tinyfiledialogs::save_file_dialog("Select export file", &default_file)
+    };
+    PathBuf::from_str(&selection?).ok()
+}
+
+fn select_data_target(format: DataExportFormat) -> Option<PathBuf> {
+    let default_file = format!("{}export.{}", shellexpand::tilde("~/"), format.extension());
+    let selection = tinyfiledialogs::save_file_dialog("Select export file", &default_file)?;
+    PathBuf::from_str(&selection).ok()
+}
+
+fn select_graph_target(format: GraphExportFormat) -> Option<PathBuf> {
+    let default_file = format!(
+        "{}contacts.{}",
+        shellexpand::tilde("~/"),
+        format.extension()
+    );
+    let selection = tinyfiledialogs::save_file_dialog("Select export file", &default_file)?;
+    PathBuf::from_str(&selection).ok()
+}
@@ -0,0 +1,268 @@
+//! Detect the addresses of the user and recompute which mails were sent by them
+use eframe::egui::{self, Widget};
+use ps_core::crossbeam_channel::{unbounded, Receiver};
+use ps_core::eyre::Result;
+use ps_core::{Config, DatabaseLike};
+use ps_importer::IdentityCandidate;
+
+use std::collections::HashSet;
+
+pub type RecomputeIsSend = fn(&Config) -> Result<usize>;
+
+pub fn recompute_is_send<Database: DatabaseLike>(config: &Config) -> Result<usize> {
+    Database::new(&config.database_path)?.recompute_is_send(config)
+}
+
+/// Runs the detection of the addresses of the user on a background thread
+#[derive(Default)]
+pub struct DetectionState {
+    running: Option<Receiver<Result<Vec<IdentityCandidate>>>>,
+    /// The detected candidates and whether they're selected
+    candidates: Vec<(IdentityCandidate, bool)>,
+    error: Option<String>,
+}
+
+impl DetectionState {
+    pub fn start(&mut self, config: Config) {
+        let (sender, receiver) = unbounded();
+        std::thread::spawn(move || {
+            if let Err(e) = sender.send(ps_importer::detect_identities(&config)) {
+                ps_core::tracing::error!("Could not send detected addresses: {}", e);
+            }
+        });
+        self.running = Some(receiver);
+        self.candidates.clear();
+        self.error = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Are there candidates to confirm?
+    pub fn has_candidates(&self) -> bool {
+        !self.candidates.is_empty()
+    }
+
+    /// Did the last detection fail or find nothing?
+    pub fn has_error(&self) -> bool {
+        self.error.is_some()
+    }
+
+    fn update(&mut self) {
+        let result = match self.running.as_ref().map(|r| r.try_recv()) {
+            Some(Ok(result)) => result,
+            _ => return,
+        };
+        self.running = None;
+        match result {
+            Ok(candidates) if candidates.is_empty() => {
+                self.error = Some("No addresses found".to_string())
+            }
+            Ok(candidates) => {
+                // Preselect the candidates with a strong hint
+                self.candidates = candidates
+                    .into_iter()
+                    .map(|c| {
+                        let selected = c.score() > 0;
+                        (c, selected)
+                    })
+                    .collect();
+            }
+            Err(e) => self.error = Some(format!("Detection failed: {}", e)),
+        }
+    }
+}
+
+/// Shows the progress of the detection and lets the user confirm the detected
+/// addresses. The confirmed addresses are written into `confirmed`.
+pub struct CandidatesPanel<'a> {
+    state: &'a mut DetectionState,
+    confirmed: &'a mut Option<Vec<String>>,
+}
+
+impl<'a> CandidatesPanel<'a> {
+    pub fn new(state: &'a mut DetectionState, confirmed: &'a mut Option<Vec<String>>) -> Self {
+        Self { state, confirmed }
+    }
+}
+
+impl<'a> Widget for CandidatesPanel<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        self.state.update();
+        ui.vertical(|ui| {
+            if self.state.is_running() {
+                ui.horizontal(|ui| {
+                    ui.add(super::widgets::Spinner::new(egui::vec2(12.0, 12.0)));
+                    ui.label("Scanning mails for your addresses...");
+                });
+            }
+            if let Some(error) = self.state.error.clone() {
+                ui.horizontal(|ui| {
+                    ui.label(error);
+                    if ui.button("Close").clicked() {
+                        self.state.error = None;
+                    }
+                });
+            }
+            if !self.state.has_candidates() {
+                return;
+            }
+            ui.label("Select the addresses that belong to you:");
+            egui::ScrollArea::vertical()
+                .max_height(250.0)
+                .show(ui, |ui| {
+                    for (candidate, selected) in self.state.candidates.iter_mut() {
+                        ui.checkbox(selected, &candidate.address)
+                            .on_hover_text(candidate.reasons());
+                    }
+                });
+            ui.horizontal(|ui| {
+                if ui.button("Use Selected").clicked() {
+                    let addresses = self
+                        .state
+                        .candidates
+                        .drain(..)
+                        .filter(|(_, selected)| *selected)
+                        .map(|(candidate, _)| candidate.address)
+                        .collect();
+                    *self.confirmed = Some(addresses);
+                }
+                if ui.button("Dismiss").clicked() {
+                    self.state.candidates.clear();
+                }
+            });
+        })
+        .response
+    }
+}
+
+/// The sender addresses of every source of a database
+pub struct AddressesState {
+    /// The comma separated addresses of each source
+    addresses: Vec<String>,
+    detection: DetectionState,
+    status: Option<String>,
+}
+
+impl AddressesState {
+    pub fn new(config: &Config) -> Self {
+        let addresses = config
+            .all_sources()
+            .iter()
+            .map(|source| join_addresses(&source.sender_emails))
+            .collect();
+        Self {
+            addresses,
+            detection: DetectionState::default(),
+            status: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.detection.is_running()
+    }
+}
+
+/// Edit the sender addresses and recompute the send mails of the database.
+/// Once that happened, the updated config is written into `updated`.
+pub struct AddressesPanel<'a> {
+    config: &'a Config,
+    recompute: RecomputeIsSend,
+    state: &'a mut AddressesState,
+    updated: &'a mut Option<Config>,
+}
+
+impl<'a> AddressesPanel<'a> {
+    pub fn new(
+        config: &'a Config,
+        recompute: RecomputeIsSend,
+        state: &'a mut AddressesState,
+        updated: &'a mut Option<Config>,
+    ) -> Self {
+        Self {
+            config,
+            recompute,
+            state,
+            updated,
+        }
+    }
+}
+
+impl<'a> Widget for AddressesPanel<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let AddressesPanel {
+            config,
+            recompute,
+            state,
+            updated,
+        } = self;
+        ui.vertical(|ui| {
+            ui.label("Mails from these addresses count as sent by you.");
+            ui.label("Multiple addresses are separated by comma (,)");
+            ui.separator();
+            let sources = config.all_sources();
+            egui::Grid::new("addresses_grid").show(ui, |ui| {
+                for (source, addresses) in sources.iter().zip(state.addresses.iter_mut()) {
+                    ui.label(&source.name);
+                    ui.text_edit_singleline(addresses);
+                    ui.end_row();
+                }
+            });
+
+            let mut confirmed = None;
+            ui.add_enabled_ui(!state.detection.is_running(), |ui| {
+                if ui.button("Detect Addresses...").clicked() {
+                    state.detection.start(config.clone());
+                }
+            });
+            ui.add(CandidatesPanel::new(&mut state.detection, &mut confirmed));
+            // The detected addresses are used for all sources
+            for addresses in confirmed.into_iter().flatten() {
+                for field in state.addresses.iter_mut() {
+                    let mut current = split_addresses(field);
+                    if !current.contains(&addresses) {
+                        current.push(addresses.clone());
+                    }
+                    *field = current.join(", ");
+                }
+            }
+
+            ui.separator();
+            if ui.button("Recompute Sent Mails").clicked() {
+                let mut config = config.clone();
+                config.sender_emails = split_addresses(&state.addresses[0]).into_iter().collect();
+                for (source, addresses) in config.sources.iter_mut().zip(&state.addresses[1..]) {
+                    source.sender_emails = split_addresses(addresses).into_iter().collect();
+                }
+                match recompute(&config) {
+                    Ok(_) => *updated = Some(config),
+                    Err(e) => state.status = Some(format!("Could not recompute: {}", e)),
+                }
+            }
+            if let Some(status) = &state.status {
+                ui.label(status);
+            }
+        })
+        .response
+    }
+}
+
+fn join_addresses(addresses: &HashSet<String>) -> String {
+    let mut addresses: Vec<&str> = addresses
+        .iter()
+        .map(|e| e.as_str())
+        .filter(|e| !e.is_empty())
+        .collect();
+    addresses.sort_unstable();
+    addresses.join(", ")
+}
+
+/// Split by comma, remove whitespace
+pub fn split_addresses(addresses: &str) -> Vec<String> {
+    addresses
+        .split(',')
+        .map(|e| e.trim().to_string())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
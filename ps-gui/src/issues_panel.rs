@@ -0,0 +1,74 @@
+//! A panel listing the mails that could not be imported
+use eframe::egui::{self, Widget};
+use num_format::{Locale, ToFormattedString};
+use ps_core::{ImportError, ImportErrorKind};
+
+/// The maximum number of issues listed per kind, to keep the panel responsive
+const MAX_LISTED: usize = 500;
+
+pub struct IssuesPanel<'a> {
+    issues: &'a [ImportError],
+}
+
+impl<'a> IssuesPanel<'a> {
+    pub fn new(issues: &'a [ImportError]) -> Self {
+        Self { issues }
+    }
+}
+
+impl<'a> Widget for IssuesPanel<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.vertical(|ui| {
+            if self.issues.is_empty() {
+                ui.label("All mails were imported without issues");
+                return;
+            }
+            ui.label(format!(
+                "{} mails could not be imported",
+                self.issues.len().to_formatted_string(&Locale::en)
+            ));
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .max_height(500.0)
+                .show(ui, |ui| {
+                    for kind in ImportErrorKind::all_cases() {
+                        let issues: Vec<&ImportError> =
+                            self.issues.iter().filter(|i| i.kind == kind).collect();
+                        if issues.is_empty() {
+                            continue;
+                        }
+                        let title = format!(
+                            "{} ({})",
+                            kind.name(),
+                            issues.len().to_formatted_string(&Locale::en)
+                        );
+                        egui::CollapsingHeader::new(title)
+                            .id_source(kind.as_str())
+                            .show(ui, |ui| {
+                                for (index, issue) in issues.iter().take(MAX_LISTED).enumerate() {
+                                    issue_row(ui, kind, index, issue);
+                                }
+                                if issues.len() > MAX_LISTED {
+                                    ui.label(format!("... and {} more", issues.len() - MAX_LISTED));
+                                }
+                            });
+                    }
+                });
+        })
+        .response
+    }
+}
+
+fn issue_row(ui: &mut egui::Ui, kind: ImportErrorKind, index: usize, issue: &ImportError) {
+    egui::CollapsingHeader::new(issue.path.display().to_string())
+        .id_source((kind.as_str(), index))
+        .show(ui, |ui| {
+            if let Some(format) = issue.format {
+                ui.label(format!("Format: {}", format.name()));
+            }
+            ui.add(egui::Label::new(&issue.message).wrap(true));
+            if let Some(snippet) = &issue.snippet {
+                ui.add(egui::Label::new(snippet).monospace().wrap(true));
+            }
+        });
+}
@@ -1,8 +1,18 @@
 mod app;
 mod app_state;
+#[cfg(not(target_arch = "wasm32"))]
+mod export_panel;
+#[cfg(not(target_arch = "wasm32"))]
+mod identities_panel;
+mod issues_panel;
+#[cfg(not(target_arch = "wasm32"))]
+mod lists_panel;
 mod mail_panel;
 mod navigation_bar;
 mod platform;
+mod preview_panel;
+#[cfg(not(target_arch = "wasm32"))]
+mod replies_panel;
 mod segmentation_bar;
 mod textures;
 pub(crate) mod widgets;
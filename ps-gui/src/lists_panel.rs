@@ -0,0 +1,156 @@
+//! The mailing lists and newsletters by volume, with their unsubscribe links
+use eframe::egui::{self, Widget};
+use ps_core::crossbeam_channel::{unbounded, Receiver};
+use ps_core::export::{export_table, DataExportFormat, ExportTable};
+use ps_core::eyre::Result;
+use ps_core::model::mailing_lists::{mailing_lists, MailingList};
+use ps_core::Config;
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use super::export_panel::OpenDatabase;
+use super::widgets::Spinner;
+
+#[derive(Default)]
+pub struct ListsState {
+    running: Option<Receiver<Result<Vec<MailingList>>>>,
+    lists: Option<Vec<MailingList>>,
+    status: Option<String>,
+}
+
+impl ListsState {
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    fn start(&mut self, config: &Config, open_database: OpenDatabase, total: usize) {
+        let (sender, receiver) = unbounded();
+        let path = config.database_path.clone();
+        std::thread::spawn(move || {
+            let run = || mailing_lists(open_database(&path)?.as_ref(), total);
+            if let Err(e) = sender.send(run()) {
+                ps_core::tracing::error!("Could not send mailing lists: {}", e);
+            }
+        });
+        self.running = Some(receiver);
+    }
+
+    fn update(&mut self) {
+        let result = match self.running.as_ref().map(|r| r.try_recv()) {
+            Some(Ok(result)) => result,
+            _ => return,
+        };
+        self.running = None;
+        match result {
+            Ok(lists) => self.lists = Some(lists),
+            Err(e) => self.status = Some(format!("Could not load mailing lists: {}", e)),
+        }
+    }
+}
+
+/// Loads the mailing lists on first display
+pub struct ListsPanel<'a> {
+    config: &'a Config,
+    open_database: OpenDatabase,
+    total: usize,
+    state: &'a mut ListsState,
+}
+
+impl<'a> ListsPanel<'a> {
+    pub fn new(
+        config: &'a Config,
+        open_database: OpenDatabase,
+        total: usize,
+        state: &'a mut ListsState,
+    ) -> Self {
+        Self {
+            config,
+            open_database,
+            total,
+            state,
+        }
+    }
+}
+
+impl<'a> Widget for ListsPanel<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let ListsPanel {
+            config,
+            open_database,
+            total,
+            state,
+        } = self;
+        state.update();
+        if state.lists.is_none() && !state.is_running() && state.status.is_none() {
+            state.start(config, open_database, total);
+        }
+        ui.vertical(|ui| {
+            let lists = match &state.lists {
+                Some(lists) => lists,
+                None => {
+                    match &state.status {
+                        Some(status) => ui.label(status),
+                        None => ui.add(Spinner::new(egui::vec2(20.0, 20.0))),
+                    };
+                    return;
+                }
+            };
+            if lists.is_empty() {
+                ui.label("No mailing lists or newsletters found");
+                return;
+            }
+            let mails: usize = lists.iter().map(|list| list.count).sum();
+            ui.label(format!(
+                "{} mailing lists and newsletters with {} mails",
+                lists.len(),
+                mails
+            ));
+            egui::ScrollArea::vertical()
+                .max_height(400.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("lists_grid").striped(true).show(ui, |ui| {
+                        ui.strong("List");
+                        ui.strong("Mails");
+                        ui.strong("Unsubscribe");
+                        ui.end_row();
+                        for list in lists {
+                            ui.label(&list.name).on_hover_text(&list.sender);
+                            ui.label(list.count.to_string());
+                            ui.horizontal(|ui| {
+                                for link in &list.unsubscribe {
+                                    let label = match link.starts_with("mailto:") {
+                                        true => "Mail",
+                                        false => "Web",
+                                    };
+                                    ui.hyperlink_to(label, link).on_hover_text(link);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                });
+            ui.separator();
+            if ui.button("Export CSV...").clicked() {
+                if let Some(target) = select_target() {
+                    let table = ExportTable::from_mailing_lists(lists);
+                    state.status =
+                        Some(match export_table(&table, DataExportFormat::Csv, &target) {
+                            Ok(_) => format!("Exported {} lists", lists.len()),
+                            Err(e) => format!("Export failed: {}", e),
+                        });
+                }
+            }
+            if let Some(status) = &state.status {
+                ui.label(status);
+            }
+        })
+        .response
+    }
+}
+
+fn select_target() -> Option<PathBuf> {
+    let default_file = format!("{}mailing_lists.csv", shellexpand::tilde("~/"));
+    let selection = tinyfiledialogs::save_file_dialog("Select export file", &default_file)?;
+    PathBuf::from_str(&selection).ok()
+}
@@ -8,11 +8,21 @@ use super::widgets::Table;
 pub struct MailPanel<'a> {
     engine: &'a mut Engine,
     error: &'a mut Option<Report>,
+    /// The mail that was last clicked in the list
+    selected: &'a mut Option<QueryRow>,
 }
 
 impl<'a> MailPanel<'a> {
-    pub fn new(engine: &'a mut Engine, error: &'a mut Option<Report>) -> Self {
-        MailPanel { engine, error }
+    pub fn new(
+        engine: &'a mut Engine,
+        error: &'a mut Option<Report>,
+        selected: &'a mut Option<QueryRow>,
+    ) -> Self {
+        MailPanel {
+            engine,
+            error,
+            selected,
+        }
     }
 }
 impl<'a> Widget for MailPanel<'a> {
@@ -26,11 +36,8 @@ impl<'a> Widget for MailPanel<'a> {
                     &mut selected_row,
                     items::count(self.engine),
                     |selected: &Option<QueryRow>| {
-                        if let Some(n) = selected {
-                            if let Some(p) = n.get(&Field::Path) {
-                                // FIXME: how to open the email in different operating systems?
-                                println!("Selected: {:?}", p.value());
-                            }
+                        if selected.is_some() {
+                            *self.selected = selected.clone();
                         }
                     },
                     |range| {
@@ -14,6 +14,7 @@ pub struct NavigationBar<'a> {
     state: &'a mut UIState,
     filter_state: &'a mut FilterState,
     total_mails: usize,
+    total_issues: usize,
 }
 
 impl<'a> NavigationBar<'a> {
@@ -23,6 +24,7 @@ impl<'a> NavigationBar<'a> {
         state: &'a mut UIState,
         filter_state: &'a mut FilterState,
         total_mails: usize,
+        total_issues: usize,
     ) -> Self {
         NavigationBar {
             engine,
@@ -30,6 +32,7 @@ impl<'a> NavigationBar<'a> {
             state,
             filter_state,
             total_mails,
+            total_issues,
         }
     }
 }
@@ -69,6 +72,30 @@ impl<'a> Widget for NavigationBar<'a> {
                 self.total_mails.to_formatted_string(&Locale::en)
             )));
 
+            if self.total_issues > 0 {
+                let issues_text = format!(
+                    "\u{26A0} {} Import Issues",
+                    self.total_issues.to_formatted_string(&Locale::en)
+                );
+                if ui.add(navigation_button(&issues_text)).clicked() {
+                    self.state.show_issues = !self.state.show_issues;
+                }
+            }
+
+            // The database can't be changed on the web
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.add(navigation_button("\u{1F464} Addresses")).clicked() {
+                self.state.show_addresses = !self.state.show_addresses;
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.add(navigation_button("\u{2709} Lists")).clicked() {
+                self.state.show_lists = !self.state.show_lists;
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.add(navigation_button("\u{23F1} Replies")).clicked() {
+                self.state.show_replies = !self.state.show_replies;
+            }
+
             // This is a hack to get right-alignment.
             // we can't size the button, we can only size text. We will size text
             // and then use ~that for these buttons
@@ -81,19 +108,26 @@ impl<'a> Widget for NavigationBar<'a> {
                 Color32::WHITE,
             );
 
-            // FIXME: Add support for exporting the selected mails as deletion rules
-            // let filter_text = "\u{1F5B9} Export";
-            // let filter_galley = ui.painter().layout_no_wrap(
-            //     filter_text.to_owned(),
-            //     egui::TextStyle::Button,
-            //     Color32::WHITE,
-            // );
+            #[cfg(not(target_arch = "wasm32"))]
+            let export_text = "\u{1F5B9} Export";
 
             w -= mail_galley.size().x + ui.spacing().button_padding.x * 4.0;
-            //w -= filter_galley.size().x + ui.spacing().button_padding.x * 4.0;
+            // Exporting is not possible on the web
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let export_galley = ui.painter().layout_no_wrap(
+                    export_text.to_owned(),
+                    egui::TextStyle::Button,
+                    Color32::WHITE,
+                );
+                w -= export_galley.size().x + ui.spacing().button_padding.x * 4.0;
+            }
             ui.add_space(w);
 
-            //ui.add(navigation_button(filter_text));
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.add(navigation_button(export_text)).clicked() {
+                self.state.show_export = !self.state.show_export;
+            }
 
             if ui.add(navigation_button(mail_text)).clicked() {
                 self.state.show_emails = !self.state.show_emails;
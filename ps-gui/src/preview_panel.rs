@@ -0,0 +1,101 @@
+//! Displays the original contents of the mail selected in the `MailPanel`
+use eframe::egui::{self, Widget};
+use num_format::{Locale, ToFormattedString};
+use ps_core::eyre::Result;
+use ps_core::{EmailPreview, FormatType, MailLocation, QueryRow};
+
+/// The loaded preview of a mail
+pub struct PreviewState {
+    location: MailLocation,
+    preview: Result<EmailPreview, String>,
+}
+
+impl PreviewState {
+    /// Load the preview for the mail in `row`
+    pub fn load(format: &FormatType, row: &QueryRow) -> Option<Self> {
+        let location = MailLocation::from_row(row)?;
+        let preview = load(format, &location).map_err(|e| e.to_string());
+        Some(PreviewState { location, preview })
+    }
+
+    /// Is this the preview for the mail in `row`
+    pub fn is_for(&self, row: &QueryRow) -> bool {
+        MailLocation::from_row(row).as_ref() == Some(&self.location)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load(format: &FormatType, location: &MailLocation) -> Result<EmailPreview> {
+    ps_importer::load_preview(format, location)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn load(_format: &FormatType, _location: &MailLocation) -> Result<EmailPreview> {
+    ps_core::eyre::bail!("Mails can't be previewed in the web demo")
+}
+
+pub struct PreviewPanel<'a> {
+    state: &'a PreviewState,
+    close: &'a mut bool,
+}
+
+impl<'a> PreviewPanel<'a> {
+    pub fn new(state: &'a PreviewState, close: &'a mut bool) -> Self {
+        PreviewPanel { state, close }
+    }
+}
+
+impl<'a> Widget for PreviewPanel<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Preview");
+                if ui.button("Close").clicked() {
+                    *self.close = true;
+                }
+            });
+            ui.separator();
+
+            let preview = match &self.state.preview {
+                Ok(n) => n,
+                Err(e) => {
+                    ui.label(format!(
+                        "Could not load {}:\n{}",
+                        self.state.location.path.display(),
+                        e
+                    ));
+                    return;
+                }
+            };
+
+            egui::Grid::new("preview_headers")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    for (name, value) in &preview.headers {
+                        ui.strong(name);
+                        ui.add(egui::Label::new(value).wrap(true));
+                        ui.end_row();
+                    }
+                });
+
+            if !preview.attachments.is_empty() {
+                ui.separator();
+                ui.strong("Attachments");
+                for attachment in &preview.attachments {
+                    ui.label(format!(
+                        "{} ({}, {} bytes)",
+                        attachment.name,
+                        attachment.content_type,
+                        attachment.size.to_formatted_string(&Locale::en)
+                    ));
+                }
+            }
+
+            ui.separator();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.add(egui::Label::new(&preview.body).wrap(true));
+            });
+        })
+        .response
+    }
+}
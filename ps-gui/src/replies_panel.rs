@@ -0,0 +1,187 @@
+//! The median time it takes to reply, by correspondent domain and by year
+use eframe::egui::{self, Widget};
+use ps_core::crossbeam_channel::{unbounded, Receiver};
+use ps_core::eyre::Result;
+use ps_core::model::response_times::{response_times, Latencies, ResponseTime, ResponseTimes};
+use ps_core::Config;
+
+use super::export_panel::OpenDatabase;
+use super::widgets::Spinner;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Grouping {
+    Domain,
+    Year,
+}
+
+pub struct RepliesState {
+    running: Option<Receiver<Result<ResponseTimes>>>,
+    times: Option<ResponseTimes>,
+    grouping: Grouping,
+    error: Option<String>,
+}
+
+impl Default for RepliesState {
+    fn default() -> Self {
+        Self {
+            running: None,
+            times: None,
+            grouping: Grouping::Domain,
+            error: None,
+        }
+    }
+}
+
+impl RepliesState {
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Forget the response times, e.g. after the sent mails were recomputed,
+    /// so that they're loaded again on the next display
+    pub fn reset(&mut self) {
+        self.running = None;
+        self.times = None;
+        self.error = None;
+    }
+
+    fn start(&mut self, config: &Config, open_database: OpenDatabase, total: usize) {
+        let (sender, receiver) = unbounded();
+        let path = config.database_path.clone();
+        std::thread::spawn(move || {
+            let run = || response_times(open_database(&path)?.as_ref(), total);
+            if let Err(e) = sender.send(run()) {
+                ps_core::tracing::error!("Could not send response times: {}", e);
+            }
+        });
+        self.running = Some(receiver);
+    }
+
+    fn update(&mut self) {
+        let result = match self.running.as_ref().map(|r| r.try_recv()) {
+            Some(Ok(result)) => result,
+            _ => return,
+        };
+        self.running = None;
+        match result {
+            Ok(times) => self.times = Some(times),
+            Err(e) => self.error = Some(format!("Could not load response times: {}", e)),
+        }
+    }
+}
+
+/// Loads the response times on first display
+pub struct RepliesPanel<'a> {
+    config: &'a Config,
+    open_database: OpenDatabase,
+    total: usize,
+    state: &'a mut RepliesState,
+}
+
+impl<'a> RepliesPanel<'a> {
+    pub fn new(
+        config: &'a Config,
+        open_database: OpenDatabase,
+        total: usize,
+        state: &'a mut RepliesState,
+    ) -> Self {
+        Self {
+            config,
+            open_database,
+            total,
+            state,
+        }
+    }
+}
+
+impl<'a> Widget for RepliesPanel<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let RepliesPanel {
+            config,
+            open_database,
+            total,
+            state,
+        } = self;
+        state.update();
+        if state.times.is_none() && !state.is_running() && state.error.is_none() {
+            state.start(config, open_database, total);
+        }
+        ui.vertical(|ui| {
+            let times = match (&state.times, &state.error) {
+                (Some(times), _) => times,
+                (None, Some(error)) => {
+                    ui.label(error);
+                    return;
+                }
+                (None, None) => {
+                    ui.add(Spinner::new(egui::vec2(20.0, 20.0)));
+                    return;
+                }
+            };
+            if times.by_year.is_empty() {
+                ui.label("No replies between you and others found.");
+                ui.label("Replies are only found if your addresses are set.");
+                return;
+            }
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut state.grouping, Grouping::Domain, "By Domain");
+                ui.radio_value(&mut state.grouping, Grouping::Year, "By Year");
+            });
+            let (title, rows) = match state.grouping {
+                Grouping::Domain => ("Domain", &times.by_domain),
+                Grouping::Year => ("Year", &times.by_year),
+            };
+            egui::ScrollArea::vertical()
+                .max_height(400.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("replies_grid")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong(title);
+                            ui.strong("You reply after");
+                            ui.strong("They reply after");
+                            ui.end_row();
+                            for row in rows.iter() {
+                                response_row(ui, row);
+                            }
+                        });
+                });
+            ui.label("The median time, and the amount of replies in brackets");
+        })
+        .response
+    }
+}
+
+fn response_row(ui: &mut egui::Ui, row: &ResponseTime) {
+    ui.label(&row.key);
+    ui.label(latency_text(&row.ours));
+    ui.label(latency_text(&row.theirs));
+    ui.end_row();
+}
+
+/// E.g. `2h 5m (12)`
+fn latency_text(latencies: &Latencies) -> String {
+    match latencies.median() {
+        Some(median) => format!("{} ({})", duration_text(median), latencies.count()),
+        None => "-".to_string(),
+    }
+}
+
+/// The two largest units of `seconds`, e.g. `3d 4h` or `5m`
+fn duration_text(seconds: i64) -> String {
+    let units = [(86400, "d"), (3600, "h"), (60, "m"), (1, "s")];
+    let mut remaining = seconds;
+    let parts: Vec<String> = units
+        .iter()
+        .filter_map(|(size, unit)| {
+            let amount = remaining / size;
+            remaining %= size;
+            (amount > 0).then(|| format!("{}{}", amount, unit))
+        })
+        .take(2)
+        .collect();
+    match parts.is_empty() {
+        true => "0s".to_string(),
+        false => parts.join(" "),
+    }
+}
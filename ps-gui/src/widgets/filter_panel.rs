@@ -4,7 +4,7 @@ use ps_core::eyre::Report;
 
 use ps_core::{
     model::{segmentations, Engine},
-    Field, Filter, ValueField,
+    Field, Filter, ParseQuality, TimezoneMode, ValueField,
 };
 
 /// Filter values for the UI.
@@ -17,6 +17,11 @@ pub struct FilterState {
     is_send: Option<bool>,
     is_reply: Option<bool>,
     is_seen: Option<bool>,
+    is_flagged: Option<bool>,
+    /// Mails the user replied to
+    is_answered: Option<bool>,
+    /// Mails that could only be decoded on a best-effort basis
+    is_lossy: Option<bool>,
     subject_contains: Option<String>,
     tags_contains: Option<String>,
 }
@@ -38,9 +43,25 @@ impl FilterState {
         if let Some(val) = self.is_seen {
             filters.push(Filter::Is(ValueField::bool(&Field::MetaIsSeen, val)));
         }
+        if let Some(val) = self.is_flagged {
+            filters.push(Filter::Is(ValueField::bool(&Field::MetaIsFlagged, val)));
+        }
+        if let Some(val) = self.is_answered {
+            filters.push(Filter::Is(ValueField::bool(&Field::MetaIsAnswered, val)));
+        }
         if let Some(val) = self.is_reply {
             filters.push(Filter::Is(ValueField::bool(&Field::IsReply, val)));
         }
+        if let Some(val) = self.is_lossy {
+            let quality = match val {
+                true => ParseQuality::Lossy,
+                false => ParseQuality::Exact,
+            };
+            filters.push(Filter::Is(ValueField::string(
+                &Field::ParseQuality,
+                quality.as_str(),
+            )));
+        }
         // FIXME: The system currently doesn't allow searching for multiple tags
         // (e.g. (x like tag1 or x like tag2))
         // this would require a `Filter::Expression` that is just added verbatim
@@ -63,6 +84,9 @@ impl FilterState {
         self.is_send = None;
         self.is_reply = None;
         self.is_seen = None;
+        self.is_flagged = None;
+        self.is_answered = None;
+        self.is_lossy = None;
         self.subject_contains = None;
         self.tags_contains = None;
     }
@@ -98,7 +122,7 @@ impl<'a> Widget for FilterPanel<'a> {
         egui::Frame::none()
             .margin(vec2(15.0, 10.5))
             .show(ui, |ui| {
-                FilterPanel::filter_panel_contents(ui, engine, state);
+                FilterPanel::filter_panel_contents(ui, engine, state, error);
                 ui.add_space(5.0);
                 ui.horizontal(|ui| {
                     if ui.button("Apply").clicked() {
@@ -120,6 +144,7 @@ impl FilterPanel<'_> {
         ui: &mut egui::Ui,
         engine: &mut Engine,
         state: &mut FilterState,
+        error: &mut Option<Report>,
     ) -> Response {
         egui::ScrollArea::vertical()
             .max_height(330.0)
@@ -145,6 +170,21 @@ impl FilterPanel<'_> {
                                     ui.end_row();
                                 }
 
+                                ui.label("Time Zone");
+                                let current = segmentations::timezone(engine);
+                                let mut selected = current;
+                                egui::ComboBox::from_id_source("timezone_mode")
+                                    .selected_text(selected.name())
+                                    .show_ui(ui, |ui| {
+                                        for mode in TimezoneMode::all_cases() {
+                                            ui.selectable_value(&mut selected, mode, mode.name());
+                                        }
+                                    });
+                                if selected != current {
+                                    *error = segmentations::set_timezone(engine, selected).err();
+                                }
+                                ui.end_row();
+
                                 input_block(ui, "Subject", &mut state.subject_contains);
                                 ui.end_row();
                             });
@@ -181,6 +221,32 @@ impl FilterPanel<'_> {
                             ui.end_row();
                         }
 
+                        if engine.format_has_flags() {
+                            radio_group(
+                                ui,
+                                "Flagged",
+                                &["Yes", "No", "All"],
+                                &mut state.is_flagged,
+                            );
+                            ui.end_row();
+
+                            radio_group(
+                                ui,
+                                "Replied",
+                                &["Yes", "No", "All"],
+                                &mut state.is_answered,
+                            );
+                            ui.end_row();
+                        }
+
+                        radio_group(
+                            ui,
+                            "Decoding",
+                            &["Lossy", "Exact", "All"],
+                            &mut state.is_lossy,
+                        );
+                        ui.end_row();
+
                         ui.end_row();
                     })
             })
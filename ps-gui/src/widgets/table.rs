@@ -18,7 +18,7 @@ pub struct Table<
     R,
     C: AsRef<[Option<R>]>,
     RowMaker: FnMut(Range<usize>) -> C,
-    RowSelection: Fn(&Option<R>),
+    RowSelection: FnMut(&Option<R>),
 > {
     id_source: Id,
     columns: Vec<Column<R>>,
@@ -42,7 +42,7 @@ impl<
         R,
         C: AsRef<[Option<R>]>,
         RowMaker: FnMut(Range<usize>) -> C,
-        RowSelection: Fn(&Option<R>),
+        RowSelection: FnMut(&Option<R>),
     > Table<'static, R, C, RowMaker, RowSelection>
 {
     #[allow(dead_code)]
@@ -66,7 +66,7 @@ impl<
         R,
         C: AsRef<[Option<R>]>,
         RowMaker: FnMut(Range<usize>) -> C,
-        RowSelection: Fn(&Option<R>),
+        RowSelection: FnMut(&Option<R>),
     > Table<'s, R, C, RowMaker, RowSelection>
 {
     pub fn new_selectable(
@@ -173,7 +173,7 @@ impl<
         R: std::fmt::Debug,
         C: AsRef<[Option<R>]>,
         RowMaker: FnMut(Range<usize>) -> C,
-        RowAction: Fn(&Option<R>),
+        RowAction: FnMut(&Option<R>),
     > Widget for Table<'s, R, C, RowMaker, RowAction>
 {
     fn ui(mut self, ui: &mut Ui) -> Response {
@@ -285,7 +285,7 @@ impl<
 
                     if let Some(selected_row) = self.selected_row.as_mut() {
                         if row_response.clicked() {
-                            if let Some(ref n) = self.row_action {
+                            if let Some(ref mut n) = self.row_action {
                                 if let Some(a) = rows.as_ref().get(row_idx) {
                                     n(a)
                                 }
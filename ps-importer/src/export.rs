@@ -0,0 +1,137 @@
+//! Export imported emails from their original location into a new
+//! mbox file, a folder of `.eml` files or a Maildir.
+
+use ps_core::chrono::prelude::*;
+use ps_core::eyre::{eyre, Result, WrapErr};
+use ps_core::tracing;
+use ps_core::{Config, Field, MailLocation, QueryRow};
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::source::{escape_from_lines, read_message};
+
+/// The formats mails can be exported into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailExportFormat {
+    /// A single mbox file
+    Mbox,
+    /// A folder with one `.eml` file per mail
+    Eml,
+    /// A new Maildir with `cur`, `new` and `tmp` folders
+    Maildir,
+}
+
+impl MailExportFormat {
+    pub fn all_cases() -> &'static [MailExportFormat] {
+        &[
+            MailExportFormat::Mbox,
+            MailExportFormat::Eml,
+            MailExportFormat::Maildir,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MailExportFormat::Mbox => "Mbox File",
+            MailExportFormat::Eml => "EML Folder",
+            MailExportFormat::Maildir => "Maildir",
+        }
+    }
+
+    /// Does this format export into a folder (instead of a file)
+    pub fn is_folder(&self) -> bool {
+        !matches!(self, MailExportFormat::Mbox)
+    }
+}
+
+/// Read the mails in `rows` from their original location and write them into `target`.
+///
+/// `config` is the config the mails were imported with, each mail is read in the format
+/// of its source. `rows` need to contain the fields required by [`MailLocation::from_row`]
+/// and the `Source` field. Useful rows can be retrieved with
+/// [`ps_core::model::items::items_query`].
+///
+/// Returns the amount of exported mails. Fails on the first mail that can't be exported.
+pub fn export_mails(
+    config: &Config,
+    rows: &[QueryRow],
+    export_format: MailExportFormat,
+    target: &Path,
+) -> Result<usize> {
+    match export_format {
+        MailExportFormat::Mbox => {
+            let mut writer = BufWriter::new(File::create(target)?);
+            for_each_mail(config, rows, |_, row, data| {
+                writer.write_all(from_line(row).as_bytes())?;
+                let escaped = escape_from_lines(data);
+                writer.write_all(&escaped)?;
+                if !escaped.ends_with(b"\n") {
+                    writer.write_all(b"\n")?;
+                }
+                writer.write_all(b"\n")?;
+                Ok(())
+            })?;
+            writer.flush()?;
+        }
+        MailExportFormat::Eml => {
+            fs::create_dir_all(target)?;
+            for_each_mail(config, rows, |index, _, data| {
+                fs::write(target.join(format!("{:06}.eml", index + 1)), data)?;
+                Ok(())
+            })?;
+        }
+        MailExportFormat::Maildir => {
+            for folder in ["cur", "new", "tmp"] {
+                fs::create_dir_all(target.join(folder))?;
+            }
+            let process = std::process::id();
+            for_each_mail(config, rows, |index, row, data| {
+                let name = format!("{}.M{}P{}.postsack", timestamp(row), index, process);
+                // Maildir requires writing into `tmp` first and then moving the mail
+                let tmp_path = target.join("tmp").join(&name);
+                fs::write(&tmp_path, data)?;
+                fs::rename(&tmp_path, target.join("new").join(&name))?;
+                Ok(())
+            })?;
+        }
+    }
+    tracing::info!("Exported {} mails to {}", rows.len(), target.display());
+    Ok(rows.len())
+}
+
+fn for_each_mail(
+    config: &Config,
+    rows: &[QueryRow],
+    mut action: impl FnMut(usize, &QueryRow, &[u8]) -> Result<()>,
+) -> Result<()> {
+    for (index, row) in rows.iter().enumerate() {
+        let location =
+            MailLocation::from_row(row).ok_or_else(|| eyre!("Missing path for mail {}", index))?;
+        let data = read_message(&config.format_of_row(row), &location)
+            .wrap_err_with(|| format!("Could not read {}", location.path.display()))?;
+        action(index, row, &data)?;
+    }
+    Ok(())
+}
+
+fn timestamp(row: &QueryRow) -> i64 {
+    row.get(&Field::Timestamp)
+        .and_then(|e| e.value().as_i64())
+        .unwrap_or_default()
+}
+
+/// The `From ` separator line of a mail in an mbox file
+fn from_line(row: &QueryRow) -> String {
+    let local_part = row.get(&Field::SenderLocalPart).map(|e| e.to_string());
+    let domain = row.get(&Field::SenderDomain).map(|e| e.to_string());
+    let sender = match (local_part, domain) {
+        (Some(local_part), Some(domain)) if !local_part.is_empty() => {
+            format!("{}@{}", local_part, domain)
+        }
+        _ => "MAILER-DAEMON".to_string(),
+    };
+    let date = Utc.timestamp(timestamp(row), 0);
+    format!("From {} {}\n", sender, date.format("%a %b %e %H:%M:%S %Y"))
+}
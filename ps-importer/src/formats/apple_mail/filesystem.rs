@@ -8,12 +8,16 @@ use walkdir::WalkDir;
 use super::super::shared::filesystem::emails_in;
 use ps_core::eyre::{eyre, Result};
 use ps_core::tracing;
-use ps_core::{Config, Message, MessageSender};
+use ps_core::{CancellationToken, Config, ImportPhase, Message, MessageSender};
 
 use super::mail::Mail;
 use std::path::PathBuf;
 
-pub fn read_emails(config: &Config, sender: MessageSender) -> Result<Vec<Mail>> {
+pub fn read_emails(
+    config: &Config,
+    sender: MessageSender,
+    token: &CancellationToken,
+) -> Result<Vec<Mail>> {
     // on macOS, we might need permission for the `Library` folder...
     match std::fs::read_dir(&config.emails_folder_path) {
         Ok(_) => (),
@@ -47,7 +51,8 @@ pub fn read_emails(config: &Config, sender: MessageSender) -> Result<Vec<Mail>>
                     && n.path()
                         .to_str()
                         .map(|e| e.contains(".mbox"))
-                        .unwrap_or(false) =>
+                        .unwrap_or(false)
+                    && config.includes_folder(n.path()) =>
             {
                 tracing::trace!("Found folder {}", n.path().display());
                 Some(n.path().to_path_buf())
@@ -64,11 +69,13 @@ pub fn read_emails(config: &Config, sender: MessageSender) -> Result<Vec<Mail>>
         })
         .collect();
     sender.send(Message::ReadTotal(folders.len()))?;
+    sender.send(Message::Phase(ImportPhase::Reading))?;
     let mails: Vec<Mail> = folders
         .into_par_iter()
         .filter_map(
-            |path| match emails_in(path.clone(), sender.clone(), Mail::new) {
+            |path| match emails_in(path.clone(), sender.clone(), token, Mail::new) {
                 Ok(n) => Some(n),
+                Err(_) if token.is_cancelled() => None,
                 Err(e) => {
                     tracing::error!("{} {:?}", path.display(), &e);
                     if let Err(e) = sender.send(Message::Error(eyre!(
@@ -84,5 +91,6 @@ pub fn read_emails(config: &Config, sender: MessageSender) -> Result<Vec<Mail>>
         )
         .flatten()
         .collect();
+    token.checkpoint()?;
     Ok(mails)
 }
@@ -1,4 +1,4 @@
-use emlx::parse_emlx;
+use emlx::{parse_emlx, Flags};
 
 use std::borrow::Cow;
 use std::path::{Path, PathBuf};
@@ -7,13 +7,14 @@ use ps_core::eyre::Result;
 use ps_core::EmailMeta;
 
 use super::super::shared::parse::{MessageKind, ParseableEmail};
+use super::partial;
 
 pub struct Mail {
     path: PathBuf,
     // This is parsed out of the `emlx` as it is parsed
-    is_seen: bool,
+    flags: Flags,
     // This is parsed out of the `path`
-    label: Option<String>,
+    tags: Vec<String>,
     // Maildata
     data: Vec<u8>,
 }
@@ -25,18 +26,10 @@ impl Mail {
         if !name.ends_with(".emlx") {
             return None;
         }
-        // find the folder ending with `.mbox` in the path
-        let ext = ".mbox";
-        let label = path
-            .iter()
-            .map(|e| e.to_str())
-            .flatten()
-            .find(|s| s.ends_with(ext))
-            .map(|s| s.replace(ext, ""));
         Some(Self {
             path: path.to_path_buf(),
-            is_seen: false,
-            label,
+            flags: Flags::default(),
+            tags: mailbox_tags(path),
             data: Vec::new(),
         })
     }
@@ -44,10 +37,9 @@ impl Mail {
 
 impl ParseableEmail for Mail {
     fn prepare(&mut self) -> Result<()> {
-        let data = std::fs::read(self.path.as_path())?;
-        let parsed = parse_emlx(&data)?;
-        self.is_seen = !parsed.flags.is_read;
-        self.data = parsed.message.to_vec();
+        let (data, flags) = read_emlx(&self.path)?;
+        self.flags = flags;
+        self.data = data;
         Ok(())
     }
     fn kind(&self) -> MessageKind<'_> {
@@ -57,14 +49,91 @@ impl ParseableEmail for Mail {
         self.path.as_path()
     }
     fn meta(&self) -> Result<Option<EmailMeta>> {
-        let tags = match self.label {
-            Some(ref n) => vec![n.clone()],
-            None => vec![],
-        };
+        let mut tags = self.tags.clone();
+        // These have no fields of their own
+        if self.flags.is_forwarded {
+            tags.push("$Forwarded".to_owned());
+        }
+        if self.flags.is_junk {
+            tags.push("$Junk".to_owned());
+        }
         let meta = EmailMeta {
             tags,
-            is_seen: self.is_seen,
+            is_seen: self.flags.is_read,
+            is_flagged: self.flags.is_flagged,
+            is_answered: self.flags.is_answered,
+            is_draft: self.flags.is_draft,
+            is_trashed: self.flags.is_deleted,
         };
         Ok(Some(meta))
     }
 }
+
+/// Read the message and the flags of the `emlx` at `path`.
+/// The attachments of partial `emlx` are added back into the message.
+pub fn read_emlx(path: &Path) -> Result<(Vec<u8>, Flags)> {
+    let data = std::fs::read(path)?;
+    let parsed = parse_emlx(&data)?;
+    let message = match partial::attachments_folder(path) {
+        Some(folder) => partial::reassemble(parsed.message, &folder),
+        None => parsed.message.to_vec(),
+    };
+    Ok((message, parsed.flags))
+}
+
+/// The tags of a mail, based on the folders in its `path`:
+/// The mailbox (the `.mbox` folders), with nested mailboxes separated by `/`,
+/// e.g. `Archive/2020` for `Archive.mbox/2020.mbox`, and the account, e.g.
+/// `E4B5C1D2-...` for mails in an account folder.
+fn mailbox_tags(path: &Path) -> Vec<String> {
+    let ext = ".mbox";
+    let components: Vec<&str> = path.iter().filter_map(|e| e.to_str()).collect();
+    let mailboxes: Vec<&str> = components
+        .iter()
+        .filter_map(|e| e.strip_suffix(ext))
+        .collect();
+    let mut tags = Vec::new();
+    if !mailboxes.is_empty() {
+        tags.push(mailboxes.join("/"));
+    }
+    // Mailboxes contain UUID folders as well, so only look before the first mailbox
+    let account = components
+        .iter()
+        .take_while(|e| !e.ends_with(ext))
+        .filter(|e| is_uuid(e))
+        .last();
+    if let Some(account) = account {
+        tags.push(account.to_string());
+    }
+    tags
+}
+
+/// Is `name` a UUID such as `E4B5C1D2-6A2B-4C7D-9E8F-0A1B2C3D4E5F`
+fn is_uuid(name: &str) -> bool {
+    name.len() == 36
+        && name.char_indices().all(|(index, c)| match index {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mailbox_tags() {
+        let account = "E4B5C1D2-6A2B-4C7D-9E8F-0A1B2C3D4E5F";
+        let mailbox = "0A1B2C3D-6A2B-4C7D-9E8F-E4B5C1D2AAAA";
+        let path = PathBuf::from(format!(
+            "/Mail/V8/{}/Archive.mbox/2020.mbox/{}/Data/Messages/1.emlx",
+            account, mailbox
+        ));
+        assert_eq!(
+            mailbox_tags(&path),
+            vec!["Archive/2020".to_owned(), account.to_owned()]
+        );
+        let path = PathBuf::from("/Mail/V8/Mailboxes/INBOX.mbox/Messages/1.emlx");
+        assert_eq!(mailbox_tags(&path), vec!["INBOX".to_owned()]);
+    }
+}
@@ -1,10 +1,13 @@
 mod filesystem;
 mod mail;
+mod partial;
+
+pub(crate) use mail::read_emlx;
 
 use std::{path::PathBuf, str::FromStr};
 
 use super::{ImporterFormat, Result};
-use ps_core::{Config, MessageSender};
+use ps_core::{CancellationToken, Config, MessageSender};
 
 #[derive(Default)]
 pub struct AppleMail {}
@@ -17,7 +20,12 @@ impl ImporterFormat for AppleMail {
         Some(PathBuf::from_str(&path.to_string()).unwrap())
     }
 
-    fn emails(&self, config: &Config, sender: MessageSender) -> Result<Vec<Self::Item>> {
-        filesystem::read_emails(config, sender)
+    fn emails(
+        &self,
+        config: &Config,
+        sender: MessageSender,
+        token: &CancellationToken,
+    ) -> Result<Vec<Self::Item>> {
+        filesystem::read_emails(config, sender, token)
     }
 }
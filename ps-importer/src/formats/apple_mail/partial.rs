@@ -0,0 +1,161 @@
+//! Apple Mail does not always store the attachments of a message in its `emlx`.
+//! These messages are named `<id>.partial.emlx` and the MIME parts of their
+//! attachments are empty. The attachments themselves are stored in
+//! `../Attachments/<id>/<part>/<filename>`, where `part` is the IMAP part
+//! number of the empty MIME part (e.g. `2` or `1.2`).
+
+use std::path::{Path, PathBuf};
+
+/// The folder with the attachments of the message at `path`,
+/// if it is a partial `emlx`
+pub fn attachments_folder(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    let id = name.strip_suffix(".partial.emlx")?;
+    Some(path.parent()?.parent()?.join("Attachments").join(id))
+}
+
+/// Insert the attachments in `folder` into the empty MIME parts of `message`
+pub fn reassemble(message: &[u8], folder: &Path) -> Vec<u8> {
+    let mut output = Vec::with_capacity(message.len());
+    reassemble_part(message, "", folder, &mut output);
+    output
+}
+
+fn reassemble_part(part: &[u8], number: &str, folder: &Path, output: &mut Vec<u8>) {
+    let (header, body) = split_header(part);
+    output.extend_from_slice(header);
+    let header_text = unfolded_header(header);
+    if let Some(boundary) = boundary(&header_text) {
+        reassemble_multipart(body, &boundary, number, folder, output);
+        return;
+    }
+    // A message that is not multipart only has the part `1`
+    let number = if number.is_empty() { "1" } else { number };
+    let is_empty = body.iter().all(|b| b.is_ascii_whitespace());
+    let data = match is_empty {
+        true => attachment(folder, number),
+        false => None,
+    };
+    if let Some(data) = data {
+        let newline: &[u8] = match header.ends_with(b"\r\n") {
+            true => b"\r\n",
+            false => b"\n",
+        };
+        let is_base64 = header_text.lines().any(|line| {
+            let line = line.to_ascii_lowercase();
+            line.starts_with("content-transfer-encoding:") && line.contains("base64")
+        });
+        match is_base64 {
+            true => {
+                for line in base64::encode(&data).as_bytes().chunks(76) {
+                    output.extend_from_slice(line);
+                    output.extend_from_slice(newline);
+                }
+            }
+            false => output.extend_from_slice(&data),
+        }
+    }
+    output.extend_from_slice(body);
+}
+
+/// Reassemble every part between the `boundary` delimiters.
+/// The preamble and epilogue are kept as they are.
+fn reassemble_multipart(
+    body: &[u8],
+    boundary: &str,
+    number: &str,
+    folder: &Path,
+    output: &mut Vec<u8>,
+) {
+    let delimiter = format!("--{}", boundary);
+    let close = format!("--{}--", boundary);
+    let mut current: Option<Vec<u8>> = None;
+    let mut index = 0;
+    let mut closed = false;
+    let flush = |content: Vec<u8>, index: usize, output: &mut Vec<u8>| {
+        let child = match number.is_empty() {
+            true => index.to_string(),
+            false => format!("{}.{}", number, index),
+        };
+        reassemble_part(&content, &child, folder, output);
+    };
+    for line in body.split_inclusive(|b| *b == b'\n') {
+        if closed {
+            output.extend_from_slice(line);
+            continue;
+        }
+        let trimmed = line.trim_ascii_end();
+        if trimmed == delimiter.as_bytes() || trimmed == close.as_bytes() {
+            if let Some(content) = current.take() {
+                flush(content, index, output);
+            }
+            output.extend_from_slice(line);
+            if trimmed == close.as_bytes() {
+                closed = true;
+            } else {
+                index += 1;
+                current = Some(Vec::new());
+            }
+            continue;
+        }
+        match current.as_mut() {
+            Some(content) => content.extend_from_slice(line),
+            None => output.extend_from_slice(line),
+        }
+    }
+    if let Some(content) = current {
+        flush(content, index, output);
+    }
+}
+
+/// Split a MIME part after the empty line that ends the header
+fn split_header(part: &[u8]) -> (&[u8], &[u8]) {
+    let position = part
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map(|p| p + 4);
+    let position = match part.windows(2).position(|w| w == b"\n\n").map(|p| p + 2) {
+        Some(n) if position.map(|p| n < p).unwrap_or(true) => Some(n),
+        _ => position,
+    };
+    match position {
+        Some(position) => part.split_at(position),
+        None => (part, &[]),
+    }
+}
+
+/// The header with one line per header field
+fn unfolded_header(header: &[u8]) -> String {
+    String::from_utf8_lossy(header)
+        .replace("\r\n", "\n")
+        .replace("\n ", " ")
+        .replace("\n\t", " ")
+}
+
+/// The boundary of a `multipart` content type
+fn boundary(header: &str) -> Option<String> {
+    let content_type = header.lines().find(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("content-type:") && line.contains("multipart/")
+    })?;
+    // The lowercased line has the same byte offsets
+    let start = content_type.to_ascii_lowercase().find("boundary=")? + "boundary=".len();
+    let value = &content_type[start..];
+    let boundary = match value.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next()?,
+        None => value
+            .split(|c: char| c == ';' || c.is_whitespace())
+            .next()?,
+    };
+    (!boundary.is_empty()).then(|| boundary.to_owned())
+}
+
+/// The contents of the file in the folder of the part `number`
+fn attachment(folder: &Path, number: &str) -> Option<Vec<u8>> {
+    let file = std::fs::read_dir(folder.join(number))
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|path| path.is_file())?;
+    std::fs::read(file).ok()
+}
@@ -2,7 +2,7 @@ mod meta;
 mod raw_email;
 
 use super::shared::filesystem::{emails_in, folders_in};
-use super::{Config, ImporterFormat, MessageSender, Result};
+use super::{CancellationToken, Config, ImporterFormat, Message, MessageSender, Result};
 use raw_email::RawEmailEntry;
 
 #[derive(Default)]
@@ -15,9 +15,18 @@ impl ImporterFormat for Gmail {
         None
     }
 
-    fn emails(&self, config: &Config, sender: MessageSender) -> Result<Vec<Self::Item>> {
-        folders_in(&config.emails_folder_path, sender, |path, sender| {
-            emails_in(path, sender, RawEmailEntry::new)
+    fn emails(
+        &self,
+        config: &Config,
+        sender: MessageSender,
+        token: &CancellationToken,
+    ) -> Result<Vec<Self::Item>> {
+        folders_in(&config.emails_folder_path, sender, token, |path, sender| {
+            if !config.includes_folder(&path) {
+                sender.send(Message::ReadOne)?;
+                return Ok(Vec::new());
+            }
+            emails_in(path, sender, token, RawEmailEntry::new)
         })
     }
 }
@@ -6,7 +6,7 @@ use std::borrow::Cow;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
-use super::super::shared::parse::{MessageKind, ParseableEmail};
+use super::super::shared::parse::{read_headers_from, MessageKind, ParseableEmail};
 use ps_core::EmailMeta;
 
 /// Raw representation of an email.
@@ -19,8 +19,9 @@ pub struct RawEmailEntry {
     eml_path: PathBuf,
     gmail_meta_path: Option<PathBuf>,
     is_compressed: bool,
-    #[allow(unused)]
     size: u64,
+    /// Only the headers are read, until the whole mail is needed
+    is_whole: bool,
 }
 
 impl RawEmailEntry {
@@ -40,6 +41,16 @@ impl RawEmailEntry {
         }
     }
 
+    /// Read the headers of the mail
+    pub fn read_headers(&self) -> Result<Vec<u8>> {
+        let reader = std::fs::File::open(&self.eml_path)?;
+        let headers = match self.is_compressed {
+            true => read_headers_from(GzDecoder::new(reader))?,
+            false => read_headers_from(reader)?,
+        };
+        Ok(headers)
+    }
+
     pub fn has_gmail_meta(&self) -> bool {
         self.gmail_meta_path.is_some()
     }
@@ -93,6 +104,7 @@ impl RawEmailEntry {
             gmail_meta_path,
             is_compressed,
             size: file_metadata.len(),
+            is_whole: false,
         })
     }
 }
@@ -102,7 +114,11 @@ impl ParseableEmail for RawEmailEntry {
         Ok(())
     }
     fn kind(&self) -> MessageKind<'_> {
-        match self.read() {
+        let data = match self.is_whole {
+            true => self.read(),
+            false => self.read_headers(),
+        };
+        match data {
             Ok(n) => MessageKind::Data(Cow::Owned(n)),
             Err(e) => return MessageKind::Error(e),
         }
@@ -119,4 +135,14 @@ impl ParseableEmail for RawEmailEntry {
             Ok(None)
         }
     }
+
+    fn read_whole(&mut self) -> Result<bool> {
+        let was_whole = self.is_whole;
+        self.is_whole = true;
+        Ok(!was_whole)
+    }
+
+    fn size(&self) -> Option<usize> {
+        Some(self.size as usize)
+    }
 }
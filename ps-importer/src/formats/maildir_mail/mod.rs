@@ -3,19 +3,34 @@ use ps_core::tracing;
 use rayon::prelude::*;
 use walkdir::{DirEntry, WalkDir};
 
-use super::{Config, ImporterFormat, Message, MessageSender, Result};
+use super::{CancellationToken, Config, ImporterFormat, Message, MessageSender, Result};
 
-use super::shared::parse::{MessageKind, ParseableEmail};
+use super::shared::parse::{read_headers, MessageKind, ParseableEmail};
 use maildir;
-use ps_core::EmailMeta;
+use ps_core::{EmailMeta, ImportPhase};
 
 use std::borrow::Cow;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+mod tags;
+use tags::TagIndex;
+
+/// The file in which Dovecot stores the names of the custom keywords of a folder
+const DOVECOT_KEYWORDS: &str = "dovecot-keywords";
 
 pub struct Mail {
     path: PathBuf,
+    /// The headers of the mail, or the whole mail after `read_whole`
     data: Vec<u8>,
-    is_seen: bool,
+    /// The size of the mail file
+    size: usize,
+    /// The flags of the filename after the `:2,`, e.g. `FRSa`
+    flags: String,
+    /// The keywords of the folder, the flag `a` is the first one
+    keywords: Arc<Vec<String>>,
+    /// The notmuch or mu tags of the Maildir
+    index: Arc<TagIndex>,
 }
 
 #[derive(Default)]
@@ -74,18 +89,26 @@ fn inner_folders(config: &Config, sender: MessageSender) -> Result<Vec<PathBuf>>
             }
             _ => None,
         })
+        .filter(|folder| config.includes_folder(folder))
         .collect();
 
     Ok(folders)
 }
 
 /// The inner email parsing code
-fn inner_emails(path: &PathBuf, sender: MessageSender) -> Result<Vec<Mail>> {
-    let maildir = maildir::Maildir::from(path.clone());
+fn inner_emails(
+    path: &Path,
+    index: &Arc<TagIndex>,
+    sender: MessageSender,
+    token: &CancellationToken,
+) -> Result<Vec<Mail>> {
+    let maildir = maildir::Maildir::from(path.to_path_buf());
     let new_mails = maildir.list_new();
     let cur_mails = maildir.list_cur();
 
     tracing::info!("Finding maildirs in {}", path.display());
+    sender.send(Message::CurrentFile(path.to_path_buf()))?;
+    let keywords = Arc::new(read_keywords(path));
 
     let parsed_mails = new_mails
         .chain(cur_mails)
@@ -100,11 +123,12 @@ fn inner_emails(path: &PathBuf, sender: MessageSender) -> Result<Vec<Mail>> {
                     return None;
                 }
             };
-            Some((mail_entry.path().clone(), mail_entry.is_seen()))
+            Some((mail_entry.path().clone(), mail_entry.flags().to_owned()))
         })
         .par_bridge()
-        .filter_map(|(path, seen)| {
-            let data = match std::fs::read(&path) {
+        .filter_map(|(path, flags)| {
+            token.checkpoint().ok()?;
+            let (data, size) = match read_headers(&path) {
                 Ok(n) => n,
                 Err(e) => {
                     tracing::error!("Could not read mail {}: {}", path.display(), e);
@@ -120,11 +144,15 @@ fn inner_emails(path: &PathBuf, sender: MessageSender) -> Result<Vec<Mail>> {
             };
             Some(Mail {
                 path: path.clone(),
-                is_seen: seen,
                 data,
+                size,
+                flags,
+                keywords: keywords.clone(),
+                index: index.clone(),
             })
         })
         .collect();
+    sender.send(Message::ReadOne)?;
 
     Ok(parsed_mails)
 }
@@ -136,14 +164,33 @@ impl ImporterFormat for Maildir {
         None
     }
 
-    fn emails(&self, config: &Config, sender: MessageSender) -> Result<Vec<Self::Item>> {
+    fn emails(
+        &self,
+        config: &Config,
+        sender: MessageSender,
+        token: &CancellationToken,
+    ) -> Result<Vec<Self::Item>> {
         // First get all the folders containing maildirs
         let folders = inner_folders(config, sender.clone())?;
+        sender.send(Message::ReadTotal(folders.len()))?;
+        sender.send(Message::Phase(ImportPhase::Reading))?;
+        let index = match &config.tag_source {
+            Some(source) => TagIndex::read(source, &config.emails_folder_path)?,
+            None => TagIndex::default(),
+        };
+        if !index.is_empty() {
+            tracing::info!(
+                "Found notmuch / mu tags for {}",
+                config.emails_folder_path.display()
+            );
+        }
+        let index = Arc::new(index);
         let mails = folders
             .par_iter()
-            .filter_map(|folder| inner_emails(folder, sender.clone()).ok())
+            .filter_map(|folder| inner_emails(folder, &index, sender.clone(), token).ok())
             .flatten()
             .collect();
+        token.checkpoint()?;
         Ok(mails)
     }
 }
@@ -159,9 +206,81 @@ impl ParseableEmail for Mail {
         self.path.as_path()
     }
     fn meta(&self) -> Result<Option<EmailMeta>> {
-        Ok(Some(EmailMeta {
-            tags: Vec::new(),
-            is_seen: self.is_seen,
-        }))
+        Ok(Some(meta_from_flags(&self.flags, &self.keywords)))
+    }
+    fn indexed_tags(&self, message_id: &str) -> Vec<String> {
+        self.index.tags(message_id).to_vec()
+    }
+    fn read_whole(&mut self) -> Result<bool> {
+        if self.data.len() >= self.size {
+            return Ok(false);
+        }
+        self.data = std::fs::read(&self.path)?;
+        Ok(true)
+    }
+    fn size(&self) -> Option<usize> {
+        Some(self.size)
+    }
+}
+
+/// Read the Dovecot keywords of a maildir folder. Each line is
+/// the index and the name of a keyword, e.g. `0 $Junk`.
+fn read_keywords(folder: &Path) -> Vec<String> {
+    let contents = match std::fs::read_to_string(folder.join(DOVECOT_KEYWORDS)) {
+        Ok(n) => n,
+        Err(_) => return Vec::new(),
+    };
+    let mut keywords = Vec::new();
+    for (index, name) in contents
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter_map(|(index, name)| Some((index.trim().parse::<usize>().ok()?, name.trim())))
+    {
+        if keywords.len() <= index {
+            keywords.resize(index + 1, String::new());
+        }
+        keywords[index] = name.to_owned();
+    }
+    keywords
+}
+
+/// The `:2,` flags of a maildir filename.
+/// Uppercase letters are the standard flags, lowercase letters
+/// are the Dovecot `keywords`, which become tags.
+fn meta_from_flags(flags: &str, keywords: &[String]) -> EmailMeta {
+    let mut tags: Vec<String> = flags
+        .chars()
+        .filter(|c| c.is_ascii_lowercase())
+        .filter_map(|c| keywords.get((c as u8 - b'a') as usize))
+        .filter(|keyword| !keyword.is_empty())
+        .cloned()
+        .collect();
+    // Passed has no field of its own
+    if flags.contains('P') {
+        tags.push("$Forwarded".to_owned());
+    }
+    EmailMeta {
+        tags,
+        is_seen: flags.contains('S'),
+        is_flagged: flags.contains('F'),
+        is_answered: flags.contains('R'),
+        is_draft: flags.contains('D'),
+        is_trashed: flags.contains('T'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meta_from_flags() {
+        let keywords = vec!["$Junk".to_owned(), "".to_owned(), "Work".to_owned()];
+        let meta = meta_from_flags("FPRSbcz", &keywords);
+        assert_eq!(meta.tags, vec!["Work".to_owned(), "$Forwarded".to_owned()]);
+        assert!(meta.is_seen && meta.is_flagged && meta.is_answered);
+        assert!(!meta.is_draft && !meta.is_trashed);
+        let meta = meta_from_flags("", &keywords);
+        assert!(meta.tags.is_empty() && !meta.is_seen);
     }
 }
@@ -0,0 +1,216 @@
+//! Tags that notmuch or mu keep in their own index instead of the Maildir.
+//!
+//! The tags are read from the [`TagSource`] selected in the config:
+//! - a `notmuch dump` export file
+//! - a notmuch database, via `notmuch dump`
+//! - a mu index, via `mu find`
+//!
+//! and matched to the mails by their `Message-Id`. Only the mails of the
+//! imported Maildir are read from a database or index.
+
+use ps_core::eyre::{bail, eyre, Result, WrapErr};
+use ps_core::TagSource;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// The separator of the fields in the `mu find` output
+const MU_SEPARATOR: char = '\t';
+
+/// The tags by `Message-Id` (without angle brackets)
+#[derive(Debug, Default)]
+pub struct TagIndex {
+    tags: HashMap<String, Vec<String>>,
+}
+
+impl TagIndex {
+    /// Read the tags of the Maildir at `maildir` from `source`
+    pub fn read(source: &TagSource, maildir: &Path) -> Result<TagIndex> {
+        match source {
+            TagSource::NotmuchDump(path) => {
+                let contents = std::fs::read_to_string(path)
+                    .wrap_err_with(|| format!("Could not read {}", path.display()))?;
+                Ok(TagIndex::from_notmuch_dump(&contents))
+            }
+            TagSource::Notmuch(database) => {
+                let mut command = Command::new("notmuch");
+                command.arg("dump").env("NOTMUCH_DATABASE", database);
+                // Only the mails below the Maildir, if the database contains more
+                if let Ok(relative) = maildir.strip_prefix(database) {
+                    if !relative.as_os_str().is_empty() {
+                        command
+                            .arg("--")
+                            .arg(format!("path:\"{}/**\"", relative.display()));
+                    }
+                }
+                Ok(TagIndex::from_notmuch_dump(&run(command)?))
+            }
+            TagSource::Mu(muhome) => {
+                let fields = ["l", "i", "x"].join(&MU_SEPARATOR.to_string());
+                let mut command = Command::new("mu");
+                command.arg("find").arg("--muhome").arg(muhome).args([
+                    "--fields",
+                    &fields,
+                    "--nocolor",
+                    "",
+                ]);
+                let maildir = maildir.canonicalize().unwrap_or_else(|_| maildir.into());
+                Ok(TagIndex::from_mu(&run(command)?, &maildir))
+            }
+        }
+    }
+
+    /// Parse the output of `notmuch dump`, either in the `batch-tag` format
+    /// (`+inbox +work -- id:1234@example.com`) or the older `sup` format
+    /// (`1234@example.com (inbox work)`)
+    pub fn from_notmuch_dump(contents: &str) -> TagIndex {
+        let mut index = TagIndex::default();
+        for line in contents.lines().map(|e| e.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((tags, id)) = line.split_once(" -- id:") {
+                let tags = tags
+                    .split_whitespace()
+                    .filter_map(|tag| tag.strip_prefix('+'))
+                    .map(decode_tag)
+                    .collect();
+                index.insert(unquote_id(id), tags);
+            } else if let Some((id, tags)) = line.split_once(" (") {
+                let tags = tags
+                    .trim_end_matches(')')
+                    .split_whitespace()
+                    .map(|e| e.to_owned())
+                    .collect();
+                index.insert(id.to_owned(), tags);
+            }
+        }
+        index
+    }
+
+    /// Parse the output of `mu find --fields "l\ti\tx"`: the path, message id and
+    /// comma separated tags of each mail, e.g. `/mail/cur/1\t1234@example.com\tinbox,work`.
+    /// Only the mails below `maildir` are kept.
+    pub fn from_mu(contents: &str, maildir: &Path) -> TagIndex {
+        let mut index = TagIndex::default();
+        for line in contents.lines() {
+            let mut fields = line.splitn(3, MU_SEPARATOR);
+            let (path, id, tags) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(path), Some(id), tags) => (path, id, tags.unwrap_or_default()),
+                _ => continue,
+            };
+            if !Path::new(path).starts_with(maildir) {
+                continue;
+            }
+            let tags = tags
+                .split(',')
+                .map(|e| e.trim())
+                .filter(|e| !e.is_empty())
+                .map(|e| e.to_owned())
+                .collect();
+            index.insert(id.to_owned(), tags);
+        }
+        index
+    }
+
+    fn insert(&mut self, id: String, tags: Vec<String>) {
+        let id = id.trim().trim_start_matches('<').trim_end_matches('>');
+        if id.is_empty() || tags.is_empty() {
+            return;
+        }
+        self.tags.entry(id.to_owned()).or_default().extend(tags);
+    }
+
+    /// The tags of the mail with `message_id`
+    pub fn tags(&self, message_id: &str) -> &[String] {
+        self.tags
+            .get(message_id)
+            .map(|e| e.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+/// The output of a successful `command`
+fn run(mut command: Command) -> Result<String> {
+    let output = command
+        .output()
+        .map_err(|e| eyre!("Could not run {:?}: {}", command, e))?;
+    if !output.status.success() {
+        bail!(
+            "{:?} failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Message ids with special characters are quoted in double quotes,
+/// with double quotes inside the id doubled
+fn unquote_id(id: &str) -> String {
+    let id = id.trim();
+    match id.strip_prefix('"').and_then(|e| e.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => id.to_owned(),
+    }
+}
+
+/// Tags with special characters are hex encoded like `%20`
+fn decode_tag(tag: &str) -> String {
+    let bytes = tag.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|e| std::str::from_utf8(e).ok())
+            .and_then(|e| u8::from_str_radix(e, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notmuch_dump() {
+        let dump = "#notmuch-dump batch-tag:3 config,properties,tags\n\
+                    #@ key value\n\
+                    +inbox +to%20do -- id:1@example.com\n\
+                    +work -- id:\"a \"\"b\"\"@example.com\"\n\
+                    -- id:empty@example.com\n\
+                    2@example.com (inbox unread)\n";
+        let index = TagIndex::from_notmuch_dump(dump);
+        assert_eq!(index.tags("1@example.com"), ["inbox", "to do"]);
+        assert_eq!(index.tags("a \"b\"@example.com"), ["work"]);
+        assert_eq!(index.tags("2@example.com"), ["inbox", "unread"]);
+        assert!(index.tags("empty@example.com").is_empty());
+    }
+
+    #[test]
+    fn test_mu() {
+        let output = "/mail/a b/cur/1\t1@example.com\tinbox,work\n\
+                      /mail/a b/cur/2\t2@example.com\t\n\
+                      /other/cur/3\t3@example.com\tinbox\n";
+        let index = TagIndex::from_mu(output, Path::new("/mail/a b"));
+        assert_eq!(index.tags("1@example.com"), ["inbox", "work"]);
+        assert!(index.tags("2@example.com").is_empty());
+        assert!(index.tags("3@example.com").is_empty());
+    }
+}
@@ -3,38 +3,113 @@ use ps_core::tracing;
 use rayon::prelude::*;
 use walkdir::WalkDir;
 
-use super::{Config, ImporterFormat, Message, MessageSender, Result};
+use super::{CancellationToken, Config, ImporterFormat, Message, MessageSender, Result};
 
 use super::shared::parse::{MessageKind, ParseableEmail};
-use ps_core::EmailMeta;
+use ps_core::{EmailMeta, ImportError, ImportErrorKind, ImportPhase};
 
+use memmap2::Mmap;
+
+use std::io::Read;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-pub struct Mail {
+mod split;
+pub use split::MboxVariant;
+use split::{split, Region};
+
+/// An mbox file that is mapped into memory. The mails refer to
+/// their region in the file instead of keeping a copy.
+struct MappedMbox {
     path: PathBuf,
-    /// For now, we go with a very simple implementation:
-    /// Each mal will have a heap-allocated vec of the corresponding
-    /// bytes in the mbox.
-    /// This wastes a lot of allocations and shows the limits of our current abstraction.
-    /// It would be better to just save the headers and ignore the rest.
-    content: Vec<u8>,
+    data: Mmap,
+    variant: MboxVariant,
+}
+
+pub struct Mail {
+    mbox: Arc<MappedMbox>,
+    /// The byte range of the mail in the mbox file
+    range: Range<usize>,
+    /// This region of the mbox could not be split into a mail
+    unsplittable: bool,
 }
 
 #[derive(Default)]
 pub struct Mbox;
 
+/// Is `path` an mbox file: Either `.mbox` is part of the path or
+/// it is a file without an extension that starts with a `From ` line.
+fn is_mbox_file(path: &Path) -> bool {
+    if path.to_str().map(|e| e.contains(".mbox")).unwrap_or(false) {
+        return true;
+    }
+    if path.extension().is_some() {
+        return false;
+    }
+    let mut buffer = [0u8; 5];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut buffer))
+        .map(|_| &buffer == b"From ")
+        .unwrap_or(false)
+}
+
+/// Split the mbox at `path` into mails
+fn read_mbox(path: &Path, token: &CancellationToken) -> Result<Vec<Mail>> {
+    let file = std::fs::File::open(path)?;
+    // Empty files can't be mapped
+    if file.metadata()?.len() == 0 {
+        return Ok(Vec::new());
+    }
+    // Safety: The mapping is only read. If another process truncates
+    // the file during the import, reading the mails will fail.
+    let data = unsafe { Mmap::map(&file)? };
+    let variant = MboxVariant::detect(&data);
+    tracing::trace!("{} is {:?}", path.display(), variant);
+    let regions = split(&data, variant);
+    token.checkpoint()?;
+    let mbox = Arc::new(MappedMbox {
+        path: path.to_path_buf(),
+        data,
+        variant,
+    });
+    let mails = regions
+        .into_iter()
+        .map(|region| {
+            let (range, unsplittable) = match region {
+                Region::Mail(range) => (range, false),
+                Region::Unsplittable(range) => {
+                    tracing::warn!(
+                        "Could not split {} at offset {}",
+                        path.display(),
+                        range.start
+                    );
+                    (range, true)
+                }
+            };
+            Mail {
+                mbox: mbox.clone(),
+                range,
+                unsplittable,
+            }
+        })
+        .collect();
+    Ok(mails)
+}
+
 /// The inner parsing code
-fn inner_emails(config: &Config, sender: MessageSender) -> Result<Vec<Mail>> {
-    // find all files ending in .mbox
+fn inner_emails(
+    config: &Config,
+    sender: MessageSender,
+    token: &CancellationToken,
+) -> Result<Vec<Mail>> {
     let mboxes: Vec<PathBuf> = WalkDir::new(&config.emails_folder_path)
         .into_iter()
         .filter_map(|e| match e {
             Ok(n)
                 if n.path().is_file()
-                    && n.path()
-                        .to_str()
-                        .map(|e| e.contains(".mbox"))
-                        .unwrap_or(false) =>
+                    && is_mbox_file(n.path())
+                    && config.includes_folder(n.path()) =>
             {
                 tracing::trace!("Found mbox file {}", n.path().display());
                 Some(n.path().to_path_buf())
@@ -51,11 +126,18 @@ fn inner_emails(config: &Config, sender: MessageSender) -> Result<Vec<Mail>> {
         })
         .collect();
 
+    sender.send(Message::ReadTotal(mboxes.len()))?;
+    sender.send(Message::Phase(ImportPhase::Reading))?;
     let mails: Vec<Mail> = mboxes
         .into_par_iter()
         .filter_map(|mbox_file| {
-            let mbox = match mbox_reader::MboxFile::from_file(&mbox_file) {
+            token.checkpoint().ok()?;
+            if let Err(e) = sender.send(Message::CurrentFile(mbox_file.clone())) {
+                tracing::error!("Error sending {}", e);
+            }
+            let inner_mails = match read_mbox(&mbox_file, token) {
                 Ok(n) => n,
+                Err(_) if token.is_cancelled() => return None,
                 Err(e) => {
                     tracing::error!(
                         "Could not open mbox file at {}: {}",
@@ -65,26 +147,14 @@ fn inner_emails(config: &Config, sender: MessageSender) -> Result<Vec<Mail>> {
                     return None;
                 }
             };
-            let inner_mails: Vec<Mail> = mbox
-                .iter()
-                .filter_map(|e| {
-                    let content = match e.message() {
-                        Some(n) => n,
-                        None => {
-                            tracing::error!("Could not parse mail at offset {}", e.offset());
-                            return None;
-                        }
-                    };
-                    Some(Mail {
-                        path: mbox_file.clone(),
-                        content: content.to_owned(),
-                    })
-                })
-                .collect();
+            if let Err(e) = sender.send(Message::ReadOne) {
+                tracing::error!("Error sending {}", e);
+            }
             Some(inner_mails)
         })
         .flatten()
         .collect();
+    token.checkpoint()?;
     Ok(mails)
 }
 
@@ -95,8 +165,13 @@ impl ImporterFormat for Mbox {
         None
     }
 
-    fn emails(&self, config: &Config, sender: MessageSender) -> Result<Vec<Self::Item>> {
-        inner_emails(config, sender)
+    fn emails(
+        &self,
+        config: &Config,
+        sender: MessageSender,
+        token: &CancellationToken,
+    ) -> Result<Vec<Self::Item>> {
+        inner_emails(config, sender, token)
     }
 }
 
@@ -105,18 +180,31 @@ impl ParseableEmail for Mail {
         Ok(())
     }
     fn kind(&self) -> MessageKind<'_> {
-        MessageKind::Data(self.content.as_slice().into())
+        let data = &self.mbox.data[self.range.clone()];
+        if self.unsplittable {
+            let message = format!(
+                "Could not split the mbox into mails at offset {}",
+                self.range.start
+            );
+            let error = ImportError::new(ImportErrorKind::Parse, &self.mbox.path, message)
+                .with_snippet(data);
+            return MessageKind::Error(error.into());
+        }
+        MessageKind::Data(self.mbox.variant.unescape(data))
     }
     fn path(&self) -> &Path {
-        self.path.as_path()
+        self.mbox.path.as_path()
+    }
+    fn mbox_location(&self) -> Option<(usize, usize)> {
+        Some((self.range.start, self.range.len()))
     }
     fn meta(&self) -> Result<Option<EmailMeta>> {
         // The filename is a tag, e.g. `INBOX.mbox`, `WORK.mbox`
-        if let Some(prefix) = self.path.file_stem() {
+        if let Some(prefix) = self.mbox.path.file_stem() {
             if let Some(s) = prefix.to_str().map(|s| s.to_owned()) {
                 return Ok(Some(EmailMeta {
                     tags: vec![s],
-                    is_seen: false,
+                    ..Default::default()
                 }));
             }
         }
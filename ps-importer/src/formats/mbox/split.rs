@@ -0,0 +1,392 @@
+//! Splitting mbox files into mails.
+//!
+//! There are several variants of the mbox format (see
+//! <https://www.loc.gov/preservation/digital/formats/fdd/fdd000383.shtml>):
+//! - `mboxo`: Mails are separated by `From ` lines. `From ` lines in a mail are
+//!   quoted as `>From `. This can't be undone unambiguously.
+//! - `mboxrd`: Like `mboxo`, but `>From ` lines are quoted again as `>>From `.
+//! - `mboxcl`: Like `mboxo`, with a `Content-Length` header with the length of the body.
+//! - `mboxcl2`: Like `mboxcl`, but without any quoting.
+//!
+//! Not every writer follows its variant, so a `From ` line is only used as a
+//! separator if it is preceded by an empty line and followed by a header, or if it
+//! looks like `From <sender> <date>` and one of the two applies. An unquoted body
+//! paragraph starting with `From ` doesn't split a mail. The `Content-Length`
+//! is only used if it ends at the next `From ` line.
+//!
+//! Mbox files can be huge (e.g. a single mbox in a Google Takeout), so the
+//! `From ` lines are searched in parallel chunks of the file.
+
+use rayon::prelude::*;
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// The amount of bytes at the beginning of a file that are used to detect the variant
+const DETECT_LENGTH: usize = 1024 * 1024;
+
+/// The size of the chunks of a file that are searched for `From ` lines in parallel
+const CHUNK_LENGTH: usize = 16 * 1024 * 1024;
+
+const FROM: &[u8] = b"From ";
+
+/// The months in the dates of `From ` lines
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MboxVariant {
+    Mboxo,
+    Mboxrd,
+    Mboxcl,
+    Mboxcl2,
+}
+
+/// A part of an mbox file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Region {
+    /// The range of a mail, without its `From ` line
+    Mail(Range<usize>),
+    /// Data that is not a mail, e.g. garbage before the first `From ` line
+    Unsplittable(Range<usize>),
+}
+
+impl MboxVariant {
+    /// Detect the variant based on the beginning of `data`
+    pub fn detect(data: &[u8]) -> MboxVariant {
+        let data = &data[..data.len().min(DETECT_LENGTH)];
+        let framed = separators(data)
+            .first()
+            .and_then(|start| content_length_end(data, *start))
+            .is_some();
+        if framed {
+            // Only `mboxcl2` has unquoted `From ` lines inside of the mails
+            let unquoted = split(data, MboxVariant::Mboxcl2)
+                .iter()
+                .any(|region| match region {
+                    Region::Mail(range) => {
+                        lines(&data[range.clone()]).any(|(_, l)| l.starts_with(FROM))
+                    }
+                    _ => false,
+                });
+            return match unquoted {
+                true => MboxVariant::Mboxcl2,
+                false => MboxVariant::Mboxcl,
+            };
+        }
+        let requoted = lines(data).any(|(_, line)| {
+            let quotes = line.iter().take_while(|b| **b == b'>').count();
+            quotes > 1 && line[quotes..].starts_with(FROM)
+        });
+        match requoted {
+            true => MboxVariant::Mboxrd,
+            false => MboxVariant::Mboxo,
+        }
+    }
+
+    fn is_content_length_framed(&self) -> bool {
+        matches!(self, MboxVariant::Mboxcl | MboxVariant::Mboxcl2)
+    }
+
+    /// Remove the quoting of `From ` lines in a mail.
+    /// Only copies the mail if there is something to unescape.
+    pub fn unescape<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        let is_quoted = |line: &[u8]| {
+            let quotes = line.iter().take_while(|b| **b == b'>').count();
+            let quoted = match self {
+                MboxVariant::Mboxo | MboxVariant::Mboxcl => quotes == 1,
+                MboxVariant::Mboxrd => quotes > 0,
+                MboxVariant::Mboxcl2 => false,
+            };
+            quoted && line[quotes..].starts_with(FROM)
+        };
+        let mut lines = data.split_inclusive(|b| *b == b'\n');
+        if !lines.any(is_quoted) {
+            return Cow::Borrowed(data);
+        }
+        let mut output = Vec::with_capacity(data.len());
+        for line in data.split_inclusive(|b| *b == b'\n') {
+            match is_quoted(line) {
+                true => output.extend_from_slice(&line[1..]),
+                false => output.extend_from_slice(line),
+            }
+        }
+        Cow::Owned(output)
+    }
+}
+
+/// Split `data` into mails
+pub fn split(data: &[u8], variant: MboxVariant) -> Vec<Region> {
+    let separators = separators(data);
+    let mut regions = Vec::new();
+    let mut start = match separators.first() {
+        Some(n) => *n,
+        None => {
+            if !is_blank(data) {
+                regions.push(Region::Unsplittable(0..data.len()));
+            }
+            return regions;
+        }
+    };
+    if !is_blank(&data[..start]) {
+        regions.push(Region::Unsplittable(0..start));
+    }
+    while start < data.len() {
+        let content_start = line_end(data, start);
+        let framed = match variant.is_content_length_framed() {
+            true => content_length_end(data, start),
+            false => None,
+        };
+        let (end, next) = match framed {
+            Some(n) => n,
+            None => {
+                let index = separators.partition_point(|n| *n < content_start);
+                let next = separators.get(index).copied().unwrap_or(data.len());
+                (next, next)
+            }
+        };
+        let end = trim_separator_line(data, content_start, end);
+        match lines(&data[content_start..end]).next() {
+            Some((_, line)) if is_header_line(line) => {
+                regions.push(Region::Mail(content_start..end))
+            }
+            _ => regions.push(Region::Unsplittable(start..end)),
+        }
+        start = next;
+    }
+    regions
+}
+
+/// The positions of all `From ` lines in `data` that could separate two mails.
+/// With `mboxcl2`, some of them can be part of a mail.
+fn separators(data: &[u8]) -> Vec<usize> {
+    separators_in_chunks(data, CHUNK_LENGTH)
+}
+
+fn separators_in_chunks(data: &[u8], chunk_length: usize) -> Vec<usize> {
+    let chunks: Vec<usize> = (0..data.len()).step_by(chunk_length).collect();
+    chunks
+        .into_par_iter()
+        .flat_map_iter(|chunk_start| {
+            let chunk_end = (chunk_start + chunk_length).min(data.len());
+            let first = match chunk_start {
+                0 => Some(0),
+                _ => None,
+            };
+            let line_starts = data[chunk_start..chunk_end]
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b == b'\n')
+                .map(move |(index, _)| chunk_start + index + 1);
+            first
+                .into_iter()
+                .chain(line_starts)
+                .filter(|start| is_separator(data, *start))
+        })
+        .collect()
+}
+
+/// Is the line at `start` a `From ` line that separates two mails, see the module
+/// documentation. Leading whitespace in the file counts as empty lines.
+fn is_separator(data: &[u8], start: usize) -> bool {
+    if !data[start..].starts_with(FROM) {
+        return false;
+    }
+    let previous_start = data[..start.saturating_sub(1)]
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map(|n| n + 1)
+        .unwrap_or(0);
+    let end = line_end(data, start);
+    let after_empty_line = is_blank(&data[previous_start..start]);
+    let before_header = lines(&data[end..])
+        .next()
+        .map(|(_, line)| is_header_line(line))
+        .unwrap_or(false);
+    match is_from_line(&data[start..end]) {
+        true => after_empty_line || before_header,
+        false => after_empty_line && before_header,
+    }
+}
+
+/// Does `line` look like `From <sender> <date>`, e.g. `From a@b Thu Jan  1 00:00:00 1970`:
+/// a sender followed by a date with a month, a time and a year
+fn is_from_line(line: &[u8]) -> bool {
+    let line = String::from_utf8_lossy(&line[FROM.len()..]);
+    let mut words = line.split_whitespace();
+    if words.next().is_none() {
+        return false;
+    }
+    let date: Vec<&str> = words.collect();
+    let is_time = |word: &&str| {
+        let parts: Vec<&str> = word.split(':').collect();
+        (2..=3).contains(&parts.len())
+            && parts
+                .iter()
+                .all(|e| e.len() == 2 && e.bytes().all(|b| b.is_ascii_digit()))
+    };
+    let is_year = |word: &&str| word.len() == 4 && word.bytes().all(|b| b.is_ascii_digit());
+    let is_month = |word: &&str| MONTHS.iter().any(|e| word.eq_ignore_ascii_case(e));
+    date.iter().any(is_month) && date.iter().any(is_time) && date.iter().any(is_year)
+}
+
+/// For `mboxcl` and `mboxcl2`: The end of the mail that starts at the `From `
+/// line at `start`, and the start of the next mail, based on the `Content-Length`.
+/// `None` if there is no `Content-Length` or it does not end at a `From ` line.
+fn content_length_end(data: &[u8], start: usize) -> Option<(usize, usize)> {
+    let content_start = line_end(data, start);
+    let mut body_start = None;
+    let mut length = None;
+    for (offset, line) in lines(&data[content_start..]) {
+        if is_blank(line) {
+            body_start = Some(content_start + offset + line.len());
+            break;
+        }
+        let line = String::from_utf8_lossy(line);
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let end = body_start? + length?;
+    if end > data.len() {
+        return None;
+    }
+    // The mail is followed by an empty line
+    let next = data[end..]
+        .iter()
+        .position(|b| *b != b'\n' && *b != b'\r')
+        .map(|n| end + n)
+        .unwrap_or(data.len());
+    match next == data.len() || data[next..].starts_with(FROM) {
+        true => Some((end, next)),
+        false => None,
+    }
+}
+
+/// Remove the empty line in front of the next `From ` line
+fn trim_separator_line(data: &[u8], start: usize, end: usize) -> usize {
+    let content = &data[start..end];
+    if content.ends_with(b"\r\n\r\n") {
+        end - 2
+    } else if content.ends_with(b"\n\n") {
+        end - 1
+    } else {
+        end
+    }
+}
+
+/// The position after the line that starts at `start`
+fn line_end(data: &[u8], start: usize) -> usize {
+    data[start..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .map(|n| start + n + 1)
+        .unwrap_or(data.len())
+}
+
+/// The lines of `data` with their offsets, including the line endings
+fn lines(data: &[u8]) -> impl Iterator<Item = (usize, &[u8])> {
+    data.split_inclusive(|b| *b == b'\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some((start, line))
+        })
+}
+
+fn is_blank(data: &[u8]) -> bool {
+    data.iter().all(|b| b.is_ascii_whitespace())
+}
+
+/// Does `line` look like `Name: value`
+fn is_header_line(line: &[u8]) -> bool {
+    match line.iter().position(|b| *b == b':') {
+        Some(0) | None => false,
+        Some(n) => line[..n].iter().all(|b| b.is_ascii_graphic()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mails<'a>(data: &'a [u8], regions: &[Region]) -> Vec<&'a str> {
+        regions
+            .iter()
+            .filter_map(|region| match region {
+                Region::Mail(range) => std::str::from_utf8(&data[range.clone()]).ok(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mboxrd() {
+        let data = b"From a@b Thu Jan  1 00:00:00 1970\nSubject: 1\n\n>>From here\nFrom there\n\nFrom a@b Thu Jan  1 00:00:00 1970\nSubject: 2\n\nBody\n";
+        let variant = MboxVariant::detect(data);
+        assert_eq!(variant, MboxVariant::Mboxrd);
+        let regions = split(data, variant);
+        assert_eq!(
+            mails(data, &regions),
+            vec![
+                "Subject: 1\n\n>>From here\nFrom there\n",
+                "Subject: 2\n\nBody\n"
+            ]
+        );
+        assert_eq!(
+            variant.unescape(b">>From here\n>From x\n").as_ref(),
+            b">From here\nFrom x\n"
+        );
+        assert!(matches!(
+            MboxVariant::Mboxo.unescape(b">>From here\n"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_mboxcl2() {
+        let data = b"From a@b Thu Jan  1 00:00:00 1970\nSubject: 1\nContent-Length: 23\n\nFrom the start\n\nFrom x\n\nFrom a@b Thu Jan  1 00:00:00 1970\nSubject: 2\nContent-Length: 5\n\nBody\n";
+        let variant = MboxVariant::detect(data);
+        assert_eq!(variant, MboxVariant::Mboxcl2);
+        let regions = split(data, variant);
+        assert_eq!(
+            mails(data, &regions),
+            vec![
+                "Subject: 1\nContent-Length: 23\n\nFrom the start\n\nFrom x\n",
+                "Subject: 2\nContent-Length: 5\n\nBody\n"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsplittable() {
+        let data = b"garbage\nFrom a@b Thu Jan  1 00:00:00 1970\nSubject: 1\n\nBody\n";
+        let regions = split(data, MboxVariant::detect(data));
+        assert_eq!(regions[0], Region::Unsplittable(0..8));
+        assert_eq!(mails(data, &regions), vec!["Subject: 1\n\nBody\n"]);
+        assert_eq!(split(b"  \n", MboxVariant::Mboxo), vec![]);
+    }
+
+    #[test]
+    fn test_unquoted_body() {
+        // Unquoted body paragraphs starting with `From `,
+        // and a missing empty line before the next mail
+        let data = b"From a@b Thu Jan  1 00:00:00 1970\nSubject: 1\n\nFrom here\n\nFrom me, 10:00 in 2020\nBye\nFrom a@b Thu Jan  1 00:00:00 1970\nSubject: 2\n\nBody\n";
+        let expected = separators_in_chunks(data, data.len());
+        assert_eq!(expected.len(), 2);
+        for chunk_length in 1..data.len() {
+            assert_eq!(separators_in_chunks(data, chunk_length), expected);
+        }
+        let variant = MboxVariant::detect(data);
+        assert_eq!(
+            mails(data, &split(data, variant)),
+            vec![
+                "Subject: 1\n\nFrom here\n\nFrom me, 10:00 in 2020\nBye\n",
+                "Subject: 2\n\nBody\n"
+            ]
+        );
+    }
+}
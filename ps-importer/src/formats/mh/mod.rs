@@ -0,0 +1,152 @@
+//! MH folders, as used by nmh, Claws Mail and Sylpheed.
+//!
+//! Every folder contains one file per mail, named by its number, and
+//! a `.mh_sequences` file that lists the numbers in each sequence, e.g.
+//! `unseen: 1-3 7`. Folders can be nested.
+
+use rayon::prelude::*;
+use walkdir::WalkDir;
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use ps_core::eyre::{bail, eyre};
+use ps_core::{tracing, EmailMeta, ImportPhase};
+
+use super::shared::filesystem::emails_in;
+use super::shared::parse::{read_headers, MessageKind, ParseableEmail};
+use super::{CancellationToken, Config, ImporterFormat, Message, MessageSender, Result};
+
+mod sequences;
+use sequences::Sequences;
+
+pub struct Mail {
+    path: PathBuf,
+    number: u64,
+    /// The sequences of the folder of the mail
+    sequences: Arc<Sequences>,
+    /// The path of the folder, relative to the root folder, e.g. `inbox/work`
+    folder: Arc<String>,
+    /// The headers of the mail, or the whole mail after `read_whole`
+    data: Vec<u8>,
+    /// The size of the mail file
+    size: usize,
+}
+
+impl Mail {
+    fn new(path: PathBuf, sequences: Arc<Sequences>, folder: Arc<String>) -> Option<Self> {
+        let number = path.file_name()?.to_str()?.parse().ok()?;
+        Some(Mail {
+            path,
+            number,
+            sequences,
+            folder,
+            data: Vec::new(),
+            size: 0,
+        })
+    }
+}
+
+impl ParseableEmail for Mail {
+    fn prepare(&mut self) -> Result<()> {
+        let (data, size) = read_headers(&self.path)?;
+        self.data = data;
+        self.size = size;
+        Ok(())
+    }
+    fn kind(&self) -> MessageKind<'_> {
+        MessageKind::Data(Cow::Borrowed(self.data.as_slice()))
+    }
+    fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+    fn meta(&self) -> Result<Option<EmailMeta>> {
+        let mut meta = self.sequences.meta(self.number);
+        if !self.folder.is_empty() {
+            meta.tags.insert(0, self.folder.to_string());
+        }
+        Ok(Some(meta))
+    }
+    fn read_whole(&mut self) -> Result<bool> {
+        if self.data.len() >= self.size {
+            return Ok(false);
+        }
+        self.data = std::fs::read(&self.path)?;
+        Ok(true)
+    }
+    fn size(&self) -> Option<usize> {
+        Some(self.size)
+    }
+}
+
+#[derive(Default)]
+pub struct Mh;
+
+impl ImporterFormat for Mh {
+    type Item = Mail;
+
+    fn default_path() -> Option<PathBuf> {
+        let path = shellexpand::tilde("~/Mail");
+        Some(PathBuf::from(path.as_ref()))
+    }
+
+    fn emails(
+        &self,
+        config: &Config,
+        sender: MessageSender,
+        token: &CancellationToken,
+    ) -> Result<Vec<Self::Item>> {
+        let root = config.emails_folder_path.as_path();
+        if !root.is_dir() {
+            bail!("Folder {} does not exist", root.display());
+        }
+        let folders: Vec<PathBuf> = WalkDir::new(root)
+            .into_iter()
+            // Skip hidden folders such as the `.git` of a mail folder
+            .filter_entry(|e| e.path() == root || !e.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|e| match e {
+                Ok(n) if n.file_type().is_dir() && config.includes_folder(n.path()) => {
+                    Some(n.into_path())
+                }
+                Ok(_) => None,
+                Err(e) => {
+                    tracing::info!("Could not read folder: {}", e);
+                    None
+                }
+            })
+            .collect();
+        sender.send(Message::ReadTotal(folders.len()))?;
+        sender.send(Message::Phase(ImportPhase::Reading))?;
+        let mails = folders
+            .into_par_iter()
+            .filter_map(|path| {
+                let sequences = Arc::new(Sequences::read(&path));
+                let folder = path
+                    .strip_prefix(root)
+                    .map(|e| e.to_string_lossy().replace('\\', "/"))
+                    .unwrap_or_default();
+                let folder = Arc::new(folder);
+                let make = move |path| Mail::new(path, sequences.clone(), folder.clone());
+                match emails_in(&path, sender.clone(), token, make) {
+                    Ok(n) => Some(n),
+                    Err(_) if token.is_cancelled() => None,
+                    Err(e) => {
+                        tracing::error!("{} {:?}", path.display(), &e);
+                        if let Err(e) = sender.send(Message::Error(eyre!(
+                            "Could read mails in {}: {:?}",
+                            path.display(),
+                            e
+                        ))) {
+                            tracing::error!("Error sending error {}", e);
+                        }
+                        None
+                    }
+                }
+            })
+            .flatten()
+            .collect();
+        token.checkpoint()?;
+        Ok(mails)
+    }
+}
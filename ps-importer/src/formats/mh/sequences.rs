@@ -0,0 +1,102 @@
+use ps_core::EmailMeta;
+
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+/// The file with the sequences of a folder
+const MH_SEQUENCES: &str = ".mh_sequences";
+
+/// The sequence of the current mail, which is not a property of the mails
+const CURRENT: &str = "cur";
+
+/// The named sequences of an MH folder, e.g. `unseen: 1-3 7`
+#[derive(Debug, Default)]
+pub struct Sequences {
+    sequences: BTreeMap<String, Vec<RangeInclusive<u64>>>,
+}
+
+impl Sequences {
+    /// Read the `.mh_sequences` in `folder`
+    pub fn read(folder: &Path) -> Sequences {
+        match std::fs::read_to_string(folder.join(MH_SEQUENCES)) {
+            Ok(contents) => Sequences::parse(&contents),
+            Err(_) => Sequences::default(),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Sequences {
+        let mut sequences: BTreeMap<String, Vec<RangeInclusive<u64>>> = BTreeMap::new();
+        let mut current: Option<String> = None;
+        for line in contents.lines() {
+            // Long sequences continue on lines starting with whitespace
+            let numbers = match (line.starts_with(char::is_whitespace), &current) {
+                (true, Some(_)) => line,
+                _ => match line.split_once(':') {
+                    Some((name, numbers)) => {
+                        current = Some(name.trim().to_owned());
+                        numbers
+                    }
+                    None => continue,
+                },
+            };
+            let name = match &current {
+                Some(n) => n.clone(),
+                None => continue,
+            };
+            let ranges =
+                numbers
+                    .split_whitespace()
+                    .filter_map(|range| match range.split_once('-') {
+                        Some((start, end)) => Some(start.parse().ok()?..=end.parse().ok()?),
+                        None => range.parse().ok().map(|n| n..=n),
+                    });
+            sequences.entry(name).or_default().extend(ranges);
+        }
+        Sequences { sequences }
+    }
+
+    fn contains(&self, name: &str, number: u64) -> bool {
+        self.sequences
+            .get(name)
+            .map(|ranges| ranges.iter().any(|r| r.contains(&number)))
+            .unwrap_or_default()
+    }
+
+    /// The flags of mail `number`. All sequences other than
+    /// `unseen`, `flagged` and `replied` become tags.
+    pub fn meta(&self, number: u64) -> EmailMeta {
+        let flags = ["unseen", "flagged", "replied", CURRENT];
+        let tags = self
+            .sequences
+            .keys()
+            .filter(|name| !flags.contains(&name.as_str()))
+            .filter(|name| self.contains(name, number))
+            .cloned()
+            .collect();
+        EmailMeta {
+            tags,
+            is_seen: !self.contains("unseen", number),
+            is_flagged: self.contains("flagged", number),
+            is_answered: self.contains("replied", number),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequences() {
+        let sequences = Sequences::parse("cur: 4\nunseen: 1-3 7\n 9\nflagged: 2\nwork: 3 4\n");
+        let meta = sequences.meta(2);
+        assert!(!meta.is_seen && meta.is_flagged && !meta.is_answered);
+        assert!(meta.tags.is_empty());
+        let meta = sequences.meta(4);
+        assert!(meta.is_seen);
+        assert_eq!(meta.tags, vec!["work".to_owned()]);
+        assert!(!sequences.meta(9).is_seen);
+    }
+}
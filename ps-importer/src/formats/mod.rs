@@ -7,17 +7,21 @@ mod gmailbackup;
 #[cfg(not(target_os = "windows"))]
 mod maildir_mail;
 mod mbox;
+mod mh;
 pub mod shared;
 
+pub(crate) use apple_mail::read_emlx;
 pub use apple_mail::AppleMail;
 pub use gmailbackup::Gmail;
 #[cfg(not(target_os = "windows"))]
 pub use maildir_mail::Maildir;
 pub use mbox::Mbox;
+pub(crate) use mbox::MboxVariant;
+pub use mh::Mh;
 
 use shared::parse::ParseableEmail;
 
-pub use ps_core::{Config, Message, MessageReceiver, MessageSender};
+pub use ps_core::{CancellationToken, Config, Message, MessageSender};
 
 /// This is implemented by the various formats
 /// to define how they return email data.
@@ -30,5 +34,11 @@ pub trait ImporterFormat: Send + Sync {
 
     /// Return all the emails in this format.
     /// Use the sneder to give progress updates via the `ReadProgress` case.
-    fn emails(&self, config: &Config, sender: MessageSender) -> Result<Vec<Self::Item>>;
+    /// Stop reading and return the `token` error once it is cancelled.
+    fn emails(
+        &self,
+        config: &Config,
+        sender: MessageSender,
+        token: &CancellationToken,
+    ) -> Result<Vec<Self::Item>>;
 }
@@ -1,67 +1,104 @@
 use super::parse::{parse_email, MessageKind, ParseableEmail};
-use ps_core::{Config, DBMessage, DatabaseLike, Message, MessageSender};
+use ps_core::{
+    CancellationToken, Cancelled, Config, DBMessage, DatabaseLike, EmailEntry, ImportError,
+    ImportPhase, Message, MessageSender,
+};
 
+use ps_core::crossbeam_channel::Sender;
 use ps_core::eyre::{self, bail, Result};
+
 use ps_core::tracing;
 use rayon::prelude::*;
+use std::thread::JoinHandle;
 
 pub fn into_database<Mail: ParseableEmail + 'static, Database: DatabaseLike + 'static>(
     config: &Config,
-    mut emails: Vec<Mail>,
+    emails: Vec<Mail>,
     tx: MessageSender,
     database: Database,
+    token: &CancellationToken,
 ) -> Result<usize> {
-    let total = emails.len();
-    tracing::info!("Loaded {} emails", &total);
-
-    // First, communicate the total amount of mails received
-    if let Err(e) = tx.send(Message::WriteTotal(total)) {
-        bail!("Channel Failure {:?}", &e);
+    let (sender, handle) = begin_import(config, database)?;
+    match send_emails(config, emails, &tx, &sender, token) {
+        Ok(()) => (),
+        Err(_) if token.is_cancelled() => return discard(sender, handle),
+        Err(e) => return Err(e),
     }
+    finish_import::<Database>(config, tx, sender, handle)
+}
 
-    // Save the config into the database
+/// Save the config into the database and consume the connection to begin the import.
+/// Returns the `sender` to submit work and the `handle` to use for waiting for the
+/// database to finish importing.
+pub fn begin_import<Database: DatabaseLike + 'static>(
+    config: &Config,
+    database: Database,
+) -> Result<(Sender<DBMessage>, JoinHandle<Result<usize>>)> {
     if let Err(e) = database.save_config(config.clone()) {
         bail!("Could not save config to database {:?}", &e);
     }
+    Ok(database.import())
+}
 
-    // Consume the connection to begin the import. It will return the `handle` to use for
-    // waiting for the database to finish importing, and the `sender` to submit work.
-    let (sender, handle) = database.import();
+/// Parse `emails` and send them to the database. The mails are tagged with
+/// the `source_name` of `config`. Mails outside of the date window or with a
+/// skipped tag of the `filter` of `config` are left out.
+pub fn send_emails<Mail: ParseableEmail + 'static>(
+    config: &Config,
+    mut emails: Vec<Mail>,
+    tx: &MessageSender,
+    sender: &Sender<DBMessage>,
+    token: &CancellationToken,
+) -> Result<()> {
+    let total = emails.len();
+    tracing::info!("Loaded {} emails", &total);
+
+    // First, communicate the total amount of mails received
+    if let Err(e) = tx
+        .send(Message::Phase(ImportPhase::Parsing))
+        .and_then(|_| tx.send(Message::WriteTotal(total)))
+    {
+        bail!("Channel Failure {:?}", &e);
+    }
 
     // Iterate over the mails..
     emails
         // in paralell..
         .par_iter_mut()
-        // parsing them
-        .map(|raw_mail| {
-            raw_mail.prepare()?;
-            match raw_mail.kind() {
-                MessageKind::Data(data) => parse_email(
-                    &data,
-                    raw_mail.path(),
-                    raw_mail.meta()?,
-                    &config.sender_emails,
-                ),
-                MessageKind::Parsed(mail) => Ok(mail),
-                MessageKind::Error(e) => Err(e),
-            }
-        })
-        // and inserting them into SQLite
-        .for_each(|entry| {
-            // Try to write the message into the database
+        // until the import is cancelled..
+        .try_for_each(|raw_mail| -> Result<()> {
+            token.checkpoint()?;
+            // parsing them
+            let (entry, bytes) = parse_mail(config, raw_mail);
+            let path = raw_mail.path().to_path_buf();
+            // and inserting them into SQLite, unless the filter skips them
             if let Err(e) = match entry {
+                Ok(mail) if !config.filter.includes_mail(&mail) => {
+                    tracing::trace!("Skipping filtered mail {}", path.display());
+                    Ok(())
+                }
                 Ok(mail) => sender.send(DBMessage::Mail(Box::new(mail))),
-                Err(e) => sender.send(DBMessage::Error(e)),
+                Err(e) => sender.send(DBMessage::Error(Box::new(e))),
             } {
                 tracing::info!("Error Inserting into Database: {:?}", &e);
-                return;
+                return Ok(());
             }
             // Signal the write
-            if let Err(e) = tx.send(Message::WriteOne) {
+            if let Err(e) = tx.send(Message::WriteOne { path, bytes }) {
                 tracing::info!("Channel Failure: {:?}", &e);
             }
-        });
+            Ok(())
+        })
+}
 
+/// Wait for the database to write all mails, create the indexes and
+/// tell the caller that the import is done.
+pub fn finish_import<Database: DatabaseLike + 'static>(
+    config: &Config,
+    tx: MessageSender,
+    sender: Sender<DBMessage>,
+    handle: JoinHandle<Result<usize>>,
+) -> Result<usize> {
     // Tell SQLite there's no more work coming. This will exit the listening loop
     if let Err(e) = sender.send(DBMessage::Done) {
         bail!("Channel Failure {:?}", &e);
@@ -70,7 +107,7 @@ pub fn into_database<Mail: ParseableEmail + 'static, Database: DatabaseLike + 's
     // Wait for SQLite to finish parsing
     tracing::info!("Waiting for SQLite to finish");
 
-    if let Err(e) = tx.send(Message::FinishingUp) {
+    if let Err(e) = tx.send(Message::Phase(ImportPhase::Writing)) {
         bail!("Channel Failure {:?}", &e);
     }
 
@@ -81,6 +118,15 @@ pub fn into_database<Mail: ParseableEmail + 'static, Database: DatabaseLike + 's
         Err(e) => Err(eyre::eyre!("Join Error: {:?}", &e)),
     };
 
+    // Once everything is written, index the database
+    let output = output.and_then(|count| {
+        if let Err(e) = tx.send(Message::Phase(ImportPhase::Indexing)) {
+            bail!("Channel Failure {:?}", &e);
+        }
+        Database::new(&config.database_path)?.create_indexes()?;
+        Ok(count)
+    });
+
     // Tell the caller that we're done processing. This will allow leaving the
     // display loop
     tracing::trace!("Messaging Done");
@@ -90,3 +136,148 @@ pub fn into_database<Mail: ParseableEmail + 'static, Database: DatabaseLike + 's
 
     output
 }
+
+/// Parse a mail. Also returns the size of the mail in bytes, if it could be read
+fn parse_mail<Mail: ParseableEmail>(
+    config: &Config,
+    raw_mail: &mut Mail,
+) -> (Result<EmailEntry, ImportError>, usize) {
+    let path = raw_mail.path().to_path_buf();
+    let error = |report| ImportError::from_report(&path, report).with_format(config.format);
+    if let Err(e) = raw_mail.prepare() {
+        return (Err(error(e)), 0);
+    }
+    let (entry, bytes) = parse_kind(config, raw_mail);
+    if entry.is_ok() {
+        return (entry, bytes);
+    }
+    // Some importers only read the headers. Try again with the whole mail
+    match raw_mail.read_whole() {
+        Ok(true) => parse_kind(config, raw_mail),
+        Ok(false) => (entry, bytes),
+        Err(read_error) => {
+            tracing::warn!("Could not read {}: {}", path.display(), read_error);
+            (entry, bytes)
+        }
+    }
+}
+
+fn parse_kind<Mail: ParseableEmail>(
+    config: &Config,
+    raw_mail: &Mail,
+) -> (Result<EmailEntry, ImportError>, usize) {
+    let path = raw_mail.path().to_path_buf();
+    let error = |report| ImportError::from_report(&path, report).with_format(config.format);
+    match raw_mail.kind() {
+        MessageKind::Data(data) => {
+            let entry = raw_mail.meta().map_err(error).and_then(|meta| {
+                parse_email(&data, raw_mail.path(), meta, &config.sender_emails)
+                    .map(|mut mail| {
+                        mail.mbox_location = raw_mail.mbox_location();
+                        mail.source = config.source_name.clone();
+                        if let (Some(meta), Some(id)) = (&mut mail.meta, &mail.message_id) {
+                            meta.tags.extend(raw_mail.indexed_tags(id));
+                        }
+                        mail
+                    })
+                    .map_err(|report| error(report).with_snippet(&data))
+            });
+            (entry, raw_mail.size().unwrap_or(data.len()))
+        }
+        MessageKind::Parsed(mail) => (Ok(mail), 0),
+        MessageKind::Error(e) => (Err(error(e)), 0),
+    }
+}
+
+/// Remove everything that was written to `database` during a cancelled import.
+/// Always returns the [`Cancelled`] error.
+pub fn discard_database<Database: DatabaseLike + 'static>(database: Database) -> Result<usize> {
+    let (sender, handle) = database.import();
+    discard(sender, handle)
+}
+
+pub fn discard(sender: Sender<DBMessage>, handle: JoinHandle<Result<usize>>) -> Result<usize> {
+    if let Err(e) = sender.send(DBMessage::Cancel) {
+        bail!("Channel Failure {:?}", &e);
+    }
+    match handle.join() {
+        Ok(Ok(_)) => Err(Cancelled.into()),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(eyre::eyre!("Join Error: {:?}", &e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ps_core::{EmailMeta, FormatType};
+    use std::borrow::Cow;
+    use std::path::{Path, PathBuf};
+
+    /// A mail that only has its headers until `read_whole` is called
+    struct HeaderMail {
+        path: PathBuf,
+        headers: Vec<u8>,
+        whole: Vec<u8>,
+        is_whole: bool,
+        read_whole_calls: usize,
+    }
+
+    impl HeaderMail {
+        fn new(headers: &str, body: &str) -> Self {
+            HeaderMail {
+                path: PathBuf::from("1.mail"),
+                headers: headers.as_bytes().to_vec(),
+                whole: [headers, body].concat().into_bytes(),
+                is_whole: false,
+                read_whole_calls: 0,
+            }
+        }
+    }
+
+    impl ParseableEmail for HeaderMail {
+        fn prepare(&mut self) -> Result<()> {
+            Ok(())
+        }
+        fn kind(&self) -> MessageKind<'_> {
+            match self.is_whole {
+                true => MessageKind::Data(Cow::Borrowed(&self.whole)),
+                false => MessageKind::Data(Cow::Borrowed(&self.headers)),
+            }
+        }
+        fn path(&self) -> &Path {
+            &self.path
+        }
+        fn meta(&self) -> Result<Option<EmailMeta>> {
+            Ok(None)
+        }
+        fn read_whole(&mut self) -> Result<bool> {
+            self.read_whole_calls += 1;
+            let was_whole = self.is_whole;
+            self.is_whole = true;
+            Ok(!was_whole)
+        }
+        fn size(&self) -> Option<usize> {
+            Some(self.whole.len())
+        }
+    }
+
+    #[test]
+    fn test_parse_mail_reads_whole_only_on_error() {
+        let config = Config::new(None, "", Vec::new(), FormatType::Mbox).unwrap();
+        let mut mail = HeaderMail::new(
+            "From: a@example.com\nDate: Thu, 12 Mar 2020 10:00:00 +0100\nSubject: Hi\n\n",
+            "Body\n",
+        );
+        let (entry, bytes) = parse_mail(&config, &mut mail);
+        assert!(entry.is_ok());
+        assert_eq!(bytes, mail.whole.len());
+        assert_eq!(mail.read_whole_calls, 0);
+
+        // Without a date, the mail can't be parsed and the whole mail is read
+        let mut mail = HeaderMail::new("From: a@example.com\nSubject: Hi\n\n", "Body\n");
+        let (entry, _) = parse_mail(&config, &mut mail);
+        assert!(entry.is_err());
+        assert_eq!(mail.read_whole_calls, 1);
+    }
+}
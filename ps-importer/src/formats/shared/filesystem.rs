@@ -4,13 +4,14 @@ use rayon::prelude::*;
 
 use std::path::{Path, PathBuf};
 
-use ps_core::{Message, MessageSender};
+use ps_core::{CancellationToken, ImportPhase, Message, MessageSender};
 
 /// Call `FolderAction` on all files in all sub folders in
 /// folder `folder`.
 pub fn folders_in<FolderAction, ActionResult, P>(
     folder: P,
     sender: MessageSender,
+    token: &CancellationToken,
     action: FolderAction,
 ) -> Result<Vec<ActionResult>>
 where
@@ -27,10 +28,12 @@ where
     let items: Vec<_> = std::fs::read_dir(&folder)?.collect();
     let total = items.len();
     sender.send(Message::ReadTotal(total))?;
-    Ok(items
+    sender.send(Message::Phase(ImportPhase::Reading))?;
+    let results = items
         .into_iter()
         .par_bridge()
         .filter_map(|entry| {
+            token.checkpoint().ok()?;
             let path = entry
                 .map_err(|e| tracing::error!("{} {:?}", &folder.display(), &e))
                 .ok()?
@@ -45,20 +48,28 @@ where
                 .ok()
         })
         .flatten()
-        .collect())
+        .collect();
+    token.checkpoint()?;
+    Ok(results)
 }
 
-pub fn emails_in<O, F, P: AsRef<Path>>(path: P, sender: MessageSender, make: F) -> Result<Vec<O>>
+pub fn emails_in<O, F, P: AsRef<Path>>(
+    path: P,
+    sender: MessageSender,
+    token: &CancellationToken,
+    make: F,
+) -> Result<Vec<O>>
 where
     F: Fn(PathBuf) -> Option<O>,
     F: Send + Sync + 'static,
     O: Send + Sync,
 {
     let path = path.as_ref();
-    let result = Ok(std::fs::read_dir(path)?
-        .into_iter()
+    sender.send(Message::CurrentFile(path.to_path_buf()))?;
+    let result: Vec<O> = std::fs::read_dir(path)?
         .par_bridge()
         .filter_map(|entry| {
+            token.checkpoint().ok()?;
             let path = entry
                 .map_err(|e| tracing::error!("{} {:?}", &path.display(), &e))
                 .ok()?
@@ -69,8 +80,9 @@ where
             trace!("Reading {}", &path.display());
             make(path)
         })
-        .collect());
+        .collect();
+    token.checkpoint()?;
     // We're done reading the folder
     sender.send(Message::ReadOne).unwrap();
-    result
+    Ok(result)
 }
@@ -1,3 +1,5 @@
 pub mod database;
 pub mod filesystem;
+pub mod organization;
 pub mod parse;
+pub mod tolerant;
@@ -0,0 +1,102 @@
+//! The organization of a sender: its registrable domain according to the
+//! [Public Suffix List](https://publicsuffix.org), e.g. `mail.google.com` -> `google.com`
+//! or `news.bbc.co.uk` -> `bbc.co.uk`.
+//!
+//! A snapshot of the list is bundled, so that imports work offline.
+
+use once_cell::sync::Lazy;
+
+use std::collections::HashSet;
+
+static RULES: Lazy<Rules> =
+    Lazy::new(|| Rules::parse(include_str!("../../../resources/public_suffix_list.dat")));
+
+struct Rules {
+    /// e.g. `co.uk`
+    suffixes: HashSet<&'static str>,
+    /// The parents of wildcard rules, e.g. `ck` for `*.ck`
+    wildcards: HashSet<&'static str>,
+    /// Exceptions to wildcard rules, e.g. `www.ck` for `!www.ck`
+    exceptions: HashSet<&'static str>,
+}
+
+impl Rules {
+    fn parse(list: &'static str) -> Self {
+        let mut rules = Rules {
+            suffixes: HashSet::new(),
+            wildcards: HashSet::new(),
+            exceptions: HashSet::new(),
+        };
+        for line in list.lines() {
+            let rule = line.split_whitespace().next().unwrap_or_default();
+            if rule.is_empty() || rule.starts_with("//") {
+                continue;
+            }
+            if let Some(exception) = rule.strip_prefix('!') {
+                rules.exceptions.insert(exception);
+            } else if let Some(parent) = rule.strip_prefix("*.") {
+                rules.wildcards.insert(parent);
+            } else {
+                rules.suffixes.insert(rule);
+            }
+        }
+        rules
+    }
+
+    /// The amount of labels of the longest public suffix of `labels`.
+    /// Unknown top level domains are public suffixes, too.
+    fn suffix_length(&self, labels: &[&str]) -> usize {
+        for start in 0..labels.len() {
+            let suffix = labels[start..].join(".");
+            if self.exceptions.contains(suffix.as_str()) {
+                return labels.len() - start - 1;
+            }
+            if self.suffixes.contains(suffix.as_str()) {
+                return labels.len() - start;
+            }
+            if start + 1 < labels.len()
+                && self
+                    .wildcards
+                    .contains(labels[start + 1..].join(".").as_str())
+            {
+                return labels.len() - start;
+            }
+        }
+        1
+    }
+}
+
+/// The registrable domain of `domain`. If `domain` is a public suffix itself
+/// (e.g. `localhost` or `co.uk`) it is returned unchanged.
+pub fn organization(domain: &str) -> String {
+    let domain = domain.trim().trim_end_matches('.').to_lowercase();
+    let labels: Vec<&str> = domain.split('.').collect();
+    let suffix_length = RULES.suffix_length(&labels);
+    if labels.len() <= suffix_length {
+        return domain;
+    }
+    labels[labels.len() - suffix_length - 1..].join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_organization() {
+        assert_eq!(organization("mail.google.com"), "google.com");
+        assert_eq!(organization("google.com"), "google.com");
+        assert_eq!(organization("News.BBC.co.uk"), "bbc.co.uk");
+        assert_eq!(
+            organization("a.b.example.kawasaki.jp"),
+            "b.example.kawasaki.jp"
+        );
+        assert_eq!(organization("www.city.kawasaki.jp"), "city.kawasaki.jp");
+        assert_eq!(
+            organization("lists.example.unknowntld"),
+            "example.unknowntld"
+        );
+        assert_eq!(organization("co.uk"), "co.uk");
+        assert_eq!(organization("localhost"), "localhost");
+    }
+}
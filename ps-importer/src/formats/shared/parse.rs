@@ -2,14 +2,18 @@ use email_address_parser::EmailAddress;
 use mail_parser::{self, Addr, HeaderValue};
 use ps_core::chrono;
 use ps_core::chrono::prelude::*;
-use ps_core::eyre::{eyre, Report, Result};
+use ps_core::eyre::{Report, Result};
 use ps_core::tracing;
 
 use std::borrow::Cow;
 use std::collections::HashSet;
+use std::io::Read;
 use std::path::Path;
 
-use ps_core::{EmailEntry, EmailMeta};
+use ps_core::{EmailEntry, EmailMeta, ImportError, ImportErrorKind, ParseQuality};
+
+use super::organization::organization;
+use super::tolerant;
 
 /// Different `importer`s can implement this trait to provide the necessary
 /// data to parse their data into a `EmailEntry`.
@@ -29,6 +33,26 @@ pub trait ParseableEmail: Send + Sized + Sync {
     /// Optional meta information if they're available.
     /// (Depending on the `importer` capabilities and system)
     fn meta(&self) -> Result<Option<EmailMeta>>;
+    /// For mails that are stored inside of an mbox file, the
+    /// byte offset and length of the message in the file
+    fn mbox_location(&self) -> Option<(usize, usize)> {
+        None
+    }
+    /// Tags of the mail with the `Message-Id` (without angle brackets) that
+    /// are kept in an index outside of the mails, such as notmuch
+    fn indexed_tags(&self, _message_id: &str) -> Vec<String> {
+        Vec::new()
+    }
+    /// Importers that only read the headers of a mail from disk read the whole
+    /// mail here, so that `kind` returns it. This is called if the headers alone
+    /// could not be parsed. Returns `false` if there was nothing more to read.
+    fn read_whole(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+    /// The size of the whole mail in bytes, if `kind` only returns its headers
+    fn size(&self) -> Option<usize> {
+        None
+    }
 }
 
 #[derive(Debug)]
@@ -39,13 +63,87 @@ pub enum MessageKind<'a> {
     Error(Report),
 }
 
+/// The size of the chunks in which the headers of a mail are read
+const HEADER_CHUNK_LENGTH: usize = 8 * 1024;
+
 pub fn parse_email(
     data: &[u8],
     path: &Path,
     meta: Option<EmailMeta>,
     sender_emails: &HashSet<String>,
 ) -> Result<EmailEntry> {
-    match mail_parser::Message::parse(&data) {
+    // Only the headers are used, so the body and the attachments are not parsed,
+    // unless the headers alone can't be parsed
+    let headers = header_length(data).filter(|n| *n < data.len());
+    let parsed = match headers.map(|n| parse_message(&data[..n], path, sender_emails)) {
+        Some(Ok(entry)) => Ok(entry),
+        Some(Err(e)) => {
+            tracing::trace!("Parsing the whole mail {}: {}", path.display(), e);
+            parse_message(data, path, sender_emails)
+        }
+        None => parse_message(data, path, sender_emails),
+    };
+    let parsed = match parsed {
+        Ok(entry) if !tolerant::is_lossy(&entry) => Ok(entry),
+        parsed => {
+            // Try again with headers that were decoded on a best-effort basis
+            let repaired = tolerant::repair_headers(data);
+            let lossy = |entry| EmailEntry {
+                parse_quality: ParseQuality::Lossy,
+                ..entry
+            };
+            match parse_message(&repaired, path, sender_emails) {
+                Ok(entry) => Ok(lossy(entry)),
+                Err(_) => parsed.map(lossy),
+            }
+        }
+    };
+    parsed.map(|entry| EmailEntry { meta, ..entry })
+}
+
+/// The length of the headers of `data`, including the empty line after them
+pub fn header_length(data: &[u8]) -> Option<usize> {
+    data.iter().enumerate().find_map(|(index, byte)| {
+        if *byte != b'\n' {
+            return None;
+        }
+        match &data[index + 1..] {
+            [b'\n', ..] => Some(index + 2),
+            [b'\r', b'\n', ..] => Some(index + 3),
+            _ => None,
+        }
+    })
+}
+
+/// Read the headers of the mail file at `path`.
+/// Also returns the size of the whole file.
+pub fn read_headers(path: &Path) -> std::io::Result<(Vec<u8>, usize)> {
+    let file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len() as usize;
+    Ok((read_headers_from(file)?, size))
+}
+
+/// Read from `reader` until the end of the headers of a mail
+pub fn read_headers_from<R: Read>(mut reader: R) -> std::io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; HEADER_CHUNK_LENGTH];
+    loop {
+        let read = reader.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(buffer);
+        }
+        // The empty line can start in the previous chunk
+        let searched = buffer.len().saturating_sub(2);
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(length) = header_length(&buffer[searched..]) {
+            buffer.truncate(searched + length);
+            return Ok(buffer);
+        }
+    }
+}
+
+fn parse_message(data: &[u8], path: &Path, sender_emails: &HashSet<String>) -> Result<EmailEntry> {
+    match mail_parser::Message::parse(data) {
         Some(email) => {
             tracing::info!("Parsing {}", path.display());
 
@@ -56,16 +154,33 @@ pub fn parse_email(
                 HeaderValue::Empty => split_single_address_header(&email.get_from()),
                 _ => split_single_address_header(sender_header),
             }
-            .ok_or(eyre!("Could not parse address: {:?}", email.get_sender()))?;
+            .ok_or_else(|| {
+                ImportError::new(
+                    ImportErrorKind::Address,
+                    path,
+                    format!("Could not parse address: {:?}", email.get_sender()),
+                )
+            })?;
 
-            let datetime =
-                emaildatetime_to_chrono(email.get_date()).ok_or(eyre!("Could not parse date"))?;
+            let original_datetime = emaildatetime_to_chrono(email.get_date()).ok_or_else(|| {
+                ImportError::new(ImportErrorKind::Date, path, "Could not parse date")
+            })?;
+            let datetime = original_datetime.with_timezone(&Utc);
+            let tz_offset = original_datetime.offset().local_minus_utc();
 
             let subject = email.get_subject().unwrap_or_default().to_string();
 
             let (to_count, to_group, to_first) =
                 split_multi_address_header(email.get_to()).unwrap_or((0, None, None));
 
+            let recipients = [email.get_to(), email.get_cc(), email.get_bcc()]
+                .into_iter()
+                .flat_map(all_addresses)
+                .collect();
+
+            let message_id = email.get_message_id().and_then(normalize_message_id);
+            let in_reply_to = parse_in_reply_to(email.get_in_reply_to());
+
             let mut is_reply = false;
             match split_single_address_header(&email.get_reply_to()) {
                 Some(_) => is_reply = true,
@@ -76,33 +191,58 @@ pub fn parse_email(
             }
 
             let is_send = sender_emails.contains(&address);
+            let sender_organization = organization(&sender_domain);
+
+            let list_id = parse_list_id(email.get_list_id());
+            let list_unsubscribe = parse_unsubscribe_links(email.get_list_unsubscribe());
+            let is_bulk_precedence = email.headers_other.iter().any(|(name, value)| {
+                name.eq_ignore_ascii_case("Precedence")
+                    && matches!(value, HeaderValue::Text(text)
+                        if ["bulk", "list", "junk"].contains(&text.trim().to_lowercase().as_str()))
+            });
+            let is_bulk = list_id.is_some() || !list_unsubscribe.is_empty() || is_bulk_precedence;
 
             Ok(EmailEntry {
                 path: path.to_path_buf(),
                 sender_domain,
+                sender_organization,
                 sender_local_part,
                 sender_name,
                 datetime,
+                tz_offset,
                 subject,
-                meta,
+                meta: None,
                 is_reply,
                 to_count,
                 to_group,
                 to_first,
+                recipients,
+                message_id,
+                in_reply_to,
                 is_send,
+                mbox_location: None,
+                source: String::new(),
+                list_id,
+                list_unsubscribe,
+                is_bulk,
+                parse_quality: ParseQuality::Exact,
             })
         }
         None => {
-            let error = eyre!(
-                "Could not parse email (trace to see contents): [{}]",
-                path.display()
+            let error = ImportError::new(
+                ImportErrorKind::Parse,
+                path,
+                format!(
+                    "Could not parse email (trace to see contents): [{}]",
+                    path.display()
+                ),
             );
             if let Ok(content_string) = String::from_utf8(data.to_vec()) {
                 tracing::trace!("Contents:\n{}\n---\n", content_string);
             } else {
                 tracing::trace!("Contents:\nInvalid UTF8\n---\n");
             }
-            Err(error)
+            Err(error.into())
         }
     }
 }
@@ -127,6 +267,92 @@ fn parse_address(addr: &Addr) -> Option<(String, String, String, String)> {
     Some((name, address, local_part, domain))
 }
 
+/// The `List-Id` header, e.g. `Rust Users <rust-users.lists.example.com>`,
+/// returns `rust-users.lists.example.com`
+fn parse_list_id(value: &HeaderValue) -> Option<String> {
+    let id = match value {
+        HeaderValue::Address(addr) => addr.address.as_ref()?.to_string(),
+        HeaderValue::Text(text) => {
+            // No angle brackets: the whole text is the id
+            let text = text.rsplit('<').next().unwrap_or_default();
+            text.trim_end_matches('>').to_string()
+        }
+        _ => return None,
+    };
+    let id = id.trim().to_lowercase();
+    match id.is_empty() {
+        true => None,
+        false => Some(id),
+    }
+}
+
+/// The `mailto:` and `https:` links of the `List-Unsubscribe` header.
+/// Other links (e.g. `http:`) are ignored.
+fn parse_unsubscribe_links(value: &HeaderValue) -> Vec<String> {
+    let links: Vec<String> = match value {
+        HeaderValue::Address(addr) => addr.address.iter().map(|e| e.to_string()).collect(),
+        HeaderValue::AddressList(addrs) => addrs
+            .iter()
+            .filter_map(|addr| addr.address.as_ref().map(|e| e.to_string()))
+            .collect(),
+        HeaderValue::Text(text) => text.split(',').map(|e| e.to_string()).collect(),
+        _ => Vec::new(),
+    };
+    links
+        .iter()
+        .map(|link| link.trim().trim_start_matches('<').trim_end_matches('>'))
+        .filter(|link| {
+            let lowercase = link.to_lowercase();
+            lowercase.starts_with("mailto:") || lowercase.starts_with("https:")
+        })
+        .map(|link| link.to_string())
+        .collect()
+}
+
+/// The first message id of the `In-Reply-To` header. `mail-parser` parses it
+/// like an address header, so ids in angle brackets end up as addresses.
+fn parse_in_reply_to(value: &HeaderValue) -> Option<String> {
+    match value {
+        HeaderValue::Address(addr) => addr.address.as_deref().and_then(normalize_message_id),
+        HeaderValue::AddressList(addrs) => addrs
+            .iter()
+            .find_map(|addr| addr.address.as_deref().and_then(normalize_message_id)),
+        HeaderValue::Text(text) => normalize_message_id(text),
+        HeaderValue::TextList(texts) => texts.first().and_then(|t| normalize_message_id(t)),
+        _ => None,
+    }
+}
+
+/// Remove whitespace and angle brackets from a message id
+fn normalize_message_id(id: &str) -> Option<String> {
+    let id = id
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .trim();
+    match id.is_empty() {
+        true => None,
+        false => Some(id.to_string()),
+    }
+}
+
+/// The addresses of all entries of an address header, without whitespace
+fn all_addresses(value: &HeaderValue) -> Vec<String> {
+    let addrs: Vec<&Addr> = match value {
+        HeaderValue::Address(addr) => vec![addr],
+        HeaderValue::AddressList(addrs) => addrs.iter().collect(),
+        HeaderValue::Group(grp) => grp.addresses.iter().collect(),
+        HeaderValue::GroupList(grps) => grps.iter().flat_map(|g| g.addresses.iter()).collect(),
+        _ => Vec::new(),
+    };
+    addrs
+        .into_iter()
+        .filter_map(|addr| addr.address.as_ref())
+        .map(|address| address.split_whitespace().collect::<String>())
+        .filter(|address| !address.is_empty())
+        .collect()
+}
+
 /// Returns (display name, email address, local part, domain)
 fn split_single_address_header(value: &HeaderValue) -> Option<(String, String, String, String)> {
     let addr = match value {
@@ -174,11 +400,21 @@ fn split_multi_address_header(
     Some((count, name, Some((address, display_name))))
 }
 
-fn emaildatetime_to_chrono(
+/// Convert the `Date` header into a date that keeps the original offset
+pub(crate) fn emaildatetime_to_chrono(
     datetime: Option<&mail_parser::DateTime>,
-) -> Option<chrono::DateTime<Utc>> {
+) -> Option<chrono::DateTime<FixedOffset>> {
     let dt = datetime?;
-    match Utc
+    let offset_seconds = (dt.tz_hour as i32 * 3600 + dt.tz_minute as i32 * 60)
+        * if dt.tz_before_gmt { -1 } else { 1 };
+    let offset = match FixedOffset::east_opt(offset_seconds) {
+        Some(n) => n,
+        None => {
+            tracing::error!("Invalid timezone offset in date {:?}", dt);
+            FixedOffset::east(0)
+        }
+    };
+    match offset
         .ymd_opt(dt.year as i32, dt.month as u32, dt.day as u32)
         .and_hms_opt(dt.hour as u32, dt.minute as u32, dt.second as u32)
     {
@@ -190,3 +426,67 @@ fn emaildatetime_to_chrono(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_headers() {
+        assert_eq!(header_length(b"Subject: Hi\n\nBody\n"), Some(13));
+        assert_eq!(header_length(b"Subject: Hi\r\n\r\nBody\r\n"), Some(15));
+        assert_eq!(header_length(b"Subject: Hi\r\n"), None);
+        let mail = format!(
+            "Subject: {}\r\n\r\nBody\r\n",
+            "a".repeat(HEADER_CHUNK_LENGTH - 12)
+        );
+        let headers = read_headers_from(mail.as_bytes()).unwrap();
+        assert!(headers.ends_with(b"a\r\n\r\n"));
+        assert_eq!(headers.len(), mail.len() - 6);
+    }
+
+    #[test]
+    fn test_parse_quality() {
+        let senders = HashSet::new();
+        let parse = |data: &[u8]| {
+            parse_email(data, Path::new("mail"), None, &senders)
+                .map(|entry| (entry.subject, entry.parse_quality))
+        };
+        let date = "Date: Thu, 12 Mar 2020 10:00:00 +0100";
+        let exact = format!("From: a@example.com\n{}\nSubject: Exact\n\nBody\n", date);
+        assert_eq!(
+            parse(exact.as_bytes()).unwrap(),
+            ("Exact".to_owned(), ParseQuality::Exact)
+        );
+        // Latin-1 without a charset
+        let latin1 = [
+            format!("From: a@example.com\n{}\nSubject: caf", date).as_bytes(),
+            b"\xe9\n\nBody\n",
+        ]
+        .concat();
+        assert_eq!(
+            parse(&latin1).unwrap(),
+            ("café".to_owned(), ParseQuality::Lossy)
+        );
+        // An mbox `From ` line in front of the headers
+        let envelope = format!(
+            "\nFrom a@example.com Thu Mar 12 10:00:00 2020\nFrom: a@example.com\n{}\nSubject: Envelope\n\nBody\n",
+            date
+        );
+        assert_eq!(
+            parse(envelope.as_bytes()).unwrap(),
+            ("Envelope".to_owned(), ParseQuality::Lossy)
+        );
+        // Old Mac line endings and a wrong charset
+        let mac = format!(
+            "From: a@example.com\r{}\rSubject: =?utf-8?Q?caf=E9?=\r\rBody\r",
+            date
+        );
+        assert_eq!(
+            parse(mac.as_bytes()).unwrap(),
+            ("café".to_owned(), ParseQuality::Lossy)
+        );
+        // Still an error
+        assert!(parse(b"From: a@example.com\nSubject: No date\n\nBody\n").is_err());
+    }
+}
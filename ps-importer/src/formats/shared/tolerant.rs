@@ -0,0 +1,250 @@
+//! Best-effort decoding of mails with broken headers.
+//!
+//! Some mails can't be parsed, or only with replacement characters, because of
+//! - 8-bit header values in an undeclared charset (usually Windows-1252 or Latin-1)
+//! - RFC 2047 encoded words with a wrong or unknown charset or a broken encoding
+//! - UTF-16 data, old Mac (`\r`) line endings, leading empty lines or lines
+//!   in the headers that are not headers (e.g. an mbox `From ` line)
+//!
+//! Instead of dropping them, the headers are repaired here and parsed again.
+//! These mails are imported with [`ParseQuality::Lossy`](ps_core::ParseQuality).
+
+use ps_core::EmailEntry;
+
+use std::borrow::Cow;
+
+/// The characters of Windows-1252 for the bytes `0x80` to `0x9F`.
+/// The unassigned bytes are mapped to the C1 control characters, like Latin-1.
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// Were the headers of `entry` not fully decoded
+pub fn is_lossy(entry: &EmailEntry) -> bool {
+    entry.subject.contains(char::REPLACEMENT_CHARACTER)
+        || entry.sender_name.contains(char::REPLACEMENT_CHARACTER)
+        || has_encoded_word(&entry.subject)
+}
+
+/// The headers of `data`, with valid structure and UTF-8 values
+pub fn repair_headers(data: &[u8]) -> Vec<u8> {
+    let data = decode_utf16(data);
+    let data = normalize_line_endings(&data);
+    let mut headers: Vec<String> = Vec::new();
+    for line in data.split(|b| *b == b'\n') {
+        if line.iter().all(|b| b.is_ascii_whitespace()) {
+            match headers.is_empty() {
+                // Skip empty lines in front of the headers
+                true => continue,
+                false => break,
+            }
+        }
+        let line = decode_bytes(line);
+        if line.starts_with([' ', '\t']) {
+            if let Some(header) = headers.last_mut() {
+                header.push_str(&line);
+            }
+        } else if is_header_line(&line) {
+            headers.push(line.into_owned());
+        }
+    }
+    let mut output = String::new();
+    for header in headers {
+        match header.split_once(':') {
+            Some((name, value)) if name.eq_ignore_ascii_case("subject") => {
+                output.push_str(name);
+                output.push(':');
+                output.push_str(&decode_encoded_words(value));
+            }
+            _ => output.push_str(&header),
+        }
+        output.push('\n');
+    }
+    output.push('\n');
+    output.into_bytes()
+}
+
+/// Decode `bytes` as UTF-8, or as Windows-1252 if they're not valid UTF-8
+pub fn decode_bytes(bytes: &[u8]) -> Cow<'_, str> {
+    match std::str::from_utf8(bytes) {
+        Ok(n) => Cow::Borrowed(n),
+        Err(_) => Cow::Owned(decode_windows_1252(bytes)),
+    }
+}
+
+fn decode_windows_1252(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| match byte {
+            0x80..=0x9F => WINDOWS_1252[(byte - 0x80) as usize],
+            _ => *byte as char,
+        })
+        .collect()
+}
+
+/// Convert UTF-16 data with a byte order mark to UTF-8
+/// and remove a UTF-8 byte order mark
+fn decode_utf16(data: &[u8]) -> Cow<'_, [u8]> {
+    let decode = |to_u16: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = data[2..]
+            .chunks_exact(2)
+            .map(|e| to_u16([e[0], e[1]]))
+            .collect();
+        Cow::Owned(String::from_utf16_lossy(&units).into_bytes())
+    };
+    match data {
+        [0xFF, 0xFE, ..] => decode(u16::from_le_bytes),
+        [0xFE, 0xFF, ..] => decode(u16::from_be_bytes),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => Cow::Borrowed(rest),
+        _ => Cow::Borrowed(data),
+    }
+}
+
+/// Replace `\r\n` and single `\r` with `\n`
+fn normalize_line_endings(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    let mut bytes = data.iter().peekable();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'\r' => {
+                if bytes.peek() == Some(&&b'\n') {
+                    bytes.next();
+                }
+                output.push(b'\n');
+            }
+            _ => output.push(*byte),
+        }
+    }
+    output
+}
+
+/// Does `line` look like `Name: value`
+fn is_header_line(line: &str) -> bool {
+    match line.split_once(':') {
+        Some((name, _)) => !name.is_empty() && name.bytes().all(|b| b.is_ascii_graphic()),
+        None => false,
+    }
+}
+
+fn has_encoded_word(value: &str) -> bool {
+    match value.find("=?") {
+        Some(start) => value[start + 2..].contains("?="),
+        None => false,
+    }
+}
+
+/// Decode the RFC 2047 encoded words (`=?charset?Q?text?=`) in `value`.
+/// If a word can't be decoded with its charset, its bytes are decoded as
+/// UTF-8 or Windows-1252. Words with a broken encoding are kept as they are.
+fn decode_encoded_words(value: &str) -> String {
+    let mut output = String::new();
+    let mut rest = value;
+    let mut previous_was_word = false;
+    while let Some(start) = rest.find("=?") {
+        let word = encoded_word(&rest[start..]);
+        let decoded = word.and_then(decode_encoded_word);
+        let (word, decoded) = match (word, decoded) {
+            (Some(word), Some(decoded)) => (word, decoded),
+            _ => {
+                output.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                previous_was_word = false;
+                continue;
+            }
+        };
+        // Whitespace between two encoded words is not part of the text
+        let between = &rest[..start];
+        if !(previous_was_word && between.trim().is_empty()) {
+            output.push_str(between);
+        }
+        output.push_str(&decoded);
+        rest = &rest[start + word.len()..];
+        previous_was_word = true;
+    }
+    output.push_str(rest);
+    // Decoded words can contain line breaks, which would end the header
+    output.replace(|c: char| c.is_control(), " ")
+}
+
+/// The encoded word at the start of `value`, e.g. `=?utf-8?B?SGk=?=`
+fn encoded_word(value: &str) -> Option<&str> {
+    let mut parts = value.get(2..)?.splitn(3, '?');
+    let charset = parts.next()?;
+    let encoding = parts.next()?;
+    let text = parts.next()?;
+    let end = text.find("?=")?;
+    let length = 2 + charset.len() + 1 + encoding.len() + 1 + end + 2;
+    match encoding.len() == 1 && !charset.contains(char::is_whitespace) {
+        true => Some(&value[..length]),
+        false => None,
+    }
+}
+
+fn decode_encoded_word(word: &str) -> Option<String> {
+    // Charsets that the parser knows are decoded by it
+    let header = format!("Subject: {}\n\n", word);
+    if let Some(message) = mail_parser::Message::parse(header.as_bytes()) {
+        if let Some(subject) = message.get_subject() {
+            if subject != word && !subject.contains(char::REPLACEMENT_CHARACTER) {
+                return Some(subject.to_string());
+            }
+        }
+    }
+    let mut parts = word[2..word.len() - 2].splitn(3, '?');
+    let _charset = parts.next()?;
+    let encoding = parts.next()?;
+    let text = parts.next()?;
+    let bytes = match encoding {
+        "B" | "b" => base64::decode(text).ok()?,
+        "Q" | "q" => decode_quoted_printable(text)?,
+        _ => return None,
+    };
+    Some(decode_bytes(&bytes).into_owned())
+}
+
+/// The `Q` encoding of encoded words: `_` is a space, `=XX` a hex encoded byte
+fn decode_quoted_printable(text: &str) -> Option<Vec<u8>> {
+    let bytes = text.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'_' => output.push(b' '),
+            b'=' => {
+                let hex = std::str::from_utf8(bytes.get(index + 1..index + 3)?).ok()?;
+                output.push(u8::from_str_radix(hex, 16).ok()?);
+                index += 2;
+            }
+            byte => output.push(byte),
+        }
+        index += 1;
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repair_headers() {
+        let data = b"\nFrom foo Thu Mar 12 10:00:00 2020\rFrom: J\xf6rg <a@b.com>\r\nSubject: =?utf-8?Q?caf=E9?=\r =?x-unknown?B?4pyT?= =?utf-8?Q?_ok?=\r\rBody \xe9\r";
+        let repaired = String::from_utf8(repair_headers(data)).unwrap();
+        assert_eq!(repaired, "From: Jörg <a@b.com>\nSubject: café✓ ok\n\n");
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode_bytes(b"\x93quoted\x94 \xe9"), "“quoted” é");
+        assert_eq!(
+            decode_encoded_words("=?utf-8?B?!!!?= a =?ISO-8859-1?Q?=E9?="),
+            "=?utf-8?B?!!!?= a é"
+        );
+        let utf16: Vec<u8> = [0xFF, 0xFE]
+            .into_iter()
+            .chain("Subject: ✓".encode_utf16().flat_map(|e| e.to_le_bytes()))
+            .collect();
+        assert_eq!(repair_headers(&utf16), "Subject: ✓\n\n".as_bytes());
+    }
+}
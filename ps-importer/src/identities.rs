@@ -0,0 +1,265 @@
+//! Propose the addresses that belong to the user before an import.
+//!
+//! Every mail of the sources is scanned for hints:
+//! - The sender of mails in a `Sent` folder (or with a `Sent` label)
+//! - `Delivered-To` and `X-Original-To`, which name the receiving mailbox
+//! - `Return-Path` of mails that were sent by the user
+//! - Recipients that appear in a large share of all mails
+//!
+//! The user should confirm the candidates, as these are only heuristics.
+
+use mail_parser::{Addr, HeaderValue};
+use rayon::prelude::*;
+
+use ps_core::crossbeam_channel::unbounded;
+use ps_core::eyre::Result;
+use ps_core::{CancellationToken, Config, EmailMeta, FormatType};
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::formats::shared::parse::{MessageKind, ParseableEmail};
+use super::formats::{self, ImporterFormat};
+
+/// Recipients that are part of at least this share of all mails are proposed
+const RECIPIENT_SHARE: f64 = 0.2;
+
+/// At most this many candidates are proposed
+const MAX_CANDIDATES: usize = 20;
+
+/// An address that probably belongs to the user, and why
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdentityCandidate {
+    pub address: String,
+    /// Mails in a sent folder that were sent from this address
+    pub sent_folder: usize,
+    /// Mails with this address in `Delivered-To` or `X-Original-To`
+    pub delivered_to: usize,
+    /// Mails sent by the user with this address as `Return-Path`
+    pub return_path: usize,
+    /// Mails with this address as a recipient
+    pub recipient: usize,
+}
+
+impl IdentityCandidate {
+    /// The amount of mails that point to this address
+    pub fn score(&self) -> usize {
+        self.sent_folder + self.delivered_to + self.return_path
+    }
+
+    /// E.g. `12 sent, 30 Delivered-To, 41 received`
+    pub fn reasons(&self) -> String {
+        let reasons = [
+            (self.sent_folder, "sent"),
+            (self.delivered_to, "Delivered-To"),
+            (self.return_path, "Return-Path"),
+            (self.recipient, "received"),
+        ];
+        reasons
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, label)| format!("{} {}", count, label))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn merge(&mut self, other: &IdentityCandidate) {
+        self.sent_folder += other.sent_folder;
+        self.delivered_to += other.delivered_to;
+        self.return_path += other.return_path;
+        self.recipient += other.recipient;
+    }
+}
+
+#[derive(Debug, Default)]
+struct IdentityScan {
+    mails: usize,
+    candidates: HashMap<String, IdentityCandidate>,
+}
+
+impl IdentityScan {
+    fn candidate(&mut self, address: &str) -> &mut IdentityCandidate {
+        self.candidates
+            .entry(address.to_string())
+            .or_insert_with(|| IdentityCandidate {
+                address: address.to_string(),
+                ..Default::default()
+            })
+    }
+
+    fn scan(mut self, data: &[u8], path: &Path, meta: Option<EmailMeta>) -> Self {
+        let email = match mail_parser::Message::parse(data) {
+            Some(n) => n,
+            None => return self,
+        };
+        self.mails += 1;
+
+        let is_sent = path
+            .iter()
+            .filter_map(|e| e.to_str())
+            .chain(meta.iter().flat_map(|m| m.tags.iter().map(|t| t.as_str())))
+            .any(is_sent_name);
+
+        let delivered: Vec<String> = email
+            .headers_other
+            .iter()
+            .filter(|(name, _)| {
+                name.eq_ignore_ascii_case("Delivered-To")
+                    || name.eq_ignore_ascii_case("X-Original-To")
+            })
+            .flat_map(|(_, value)| addresses(value))
+            .collect();
+        for address in &delivered {
+            self.candidate(address).delivered_to += 1;
+        }
+
+        if is_sent {
+            let sender = match email.get_sender() {
+                HeaderValue::Empty => email.get_from(),
+                sender => sender,
+            };
+            if let Some(address) = addresses(sender).into_iter().next() {
+                self.candidate(&address).sent_folder += 1;
+            }
+        }
+
+        // A `Return-Path` only names the user for mails the user sent,
+        // either into a sent folder or to themselves
+        for address in addresses(email.get_return_path()) {
+            if is_sent || delivered.contains(&address) {
+                self.candidate(&address).return_path += 1;
+            }
+        }
+
+        if !is_sent {
+            for header in [email.get_to(), email.get_cc()] {
+                for address in addresses(header) {
+                    self.candidate(&address).recipient += 1;
+                }
+            }
+        }
+        self
+    }
+
+    fn merge(mut self, other: IdentityScan) -> Self {
+        self.mails += other.mails;
+        for (address, candidate) in other.candidates {
+            self.candidate(&address).merge(&candidate);
+        }
+        self
+    }
+
+    /// The most likely candidates first
+    fn into_candidates(self) -> Vec<IdentityCandidate> {
+        let minimum_recipient = ((self.mails as f64 * RECIPIENT_SHARE).ceil() as usize).max(2);
+        let mut candidates: Vec<IdentityCandidate> = self
+            .candidates
+            .into_values()
+            .filter(|c| c.score() > 0 || c.recipient >= minimum_recipient)
+            .collect();
+        candidates.sort_by(|a, b| {
+            (b.score(), b.recipient, &a.address).cmp(&(a.score(), a.recipient, &b.address))
+        });
+        candidates.truncate(MAX_CANDIDATES);
+        candidates
+    }
+}
+
+/// Is this the name of a folder or label for sent mails, e.g. `Sent Messages.mbox`
+fn is_sent_name(name: &str) -> bool {
+    let name = name.rsplit('/').next().unwrap_or(name);
+    name.trim_start_matches('.')
+        .to_lowercase()
+        .starts_with("sent")
+}
+
+/// All addresses in an address header, or in an unstructured header like `Delivered-To`
+fn addresses(value: &HeaderValue) -> Vec<String> {
+    let address = |addr: &Addr| addr.address.as_ref().map(|e| e.trim().to_string());
+    match value {
+        HeaderValue::Address(addr) => address(addr).into_iter().collect(),
+        HeaderValue::AddressList(addrs) => addrs.iter().filter_map(address).collect(),
+        HeaderValue::Group(group) => group.addresses.iter().filter_map(address).collect(),
+        HeaderValue::GroupList(groups) => groups
+            .iter()
+            .flat_map(|g| g.addresses.iter())
+            .filter_map(address)
+            .collect(),
+        HeaderValue::Text(text) => {
+            let text = text.trim().trim_start_matches('<').trim_end_matches('>');
+            match text.contains('@') {
+                true => vec![text.trim().to_string()],
+                false => Vec::new(),
+            }
+        }
+        HeaderValue::TextList(texts) => texts
+            .iter()
+            .flat_map(|t| addresses(&HeaderValue::Text(t.clone())))
+            .collect(),
+        HeaderValue::Collection(values) => values.iter().flat_map(addresses).collect(),
+        _ => Vec::new(),
+    }
+    .into_iter()
+    .filter(|e| !e.is_empty())
+    .collect()
+}
+
+/// Scan all sources of `config` and propose the addresses of the user.
+/// This reads every mail, so it takes about as long as reading during an import.
+pub fn detect_identities(config: &Config) -> Result<Vec<IdentityCandidate>> {
+    let mut scan = IdentityScan::default();
+    for source in config.all_sources() {
+        let config = config.for_source(&source);
+        let next = match config.format {
+            FormatType::AppleMail => scan_source(formats::AppleMail::default(), &config)?,
+            FormatType::GmailVault => scan_source(formats::Gmail::default(), &config)?,
+            FormatType::Mbox => scan_source(formats::Mbox, &config)?,
+            #[cfg(not(target_os = "windows"))]
+            FormatType::Maildir => scan_source(formats::Maildir, &config)?,
+            FormatType::Mh => scan_source(formats::Mh, &config)?,
+        };
+        scan = scan.merge(next);
+    }
+    Ok(scan.into_candidates())
+}
+
+fn scan_source<Format: ImporterFormat>(format: Format, config: &Config) -> Result<IdentityScan> {
+    // Nobody listens to the progress of the scan
+    let (sender, _receiver) = unbounded();
+    let mut emails = format.emails(config, sender, &CancellationToken::new())?;
+    let scan = emails
+        .par_iter_mut()
+        .fold(IdentityScan::default, |scan, mail| {
+            if mail.prepare().is_err() {
+                return scan;
+            }
+            let meta = mail.meta().ok().flatten();
+            match mail.kind() {
+                MessageKind::Data(data) => scan.scan(&data, mail.path(), meta),
+                _ => scan,
+            }
+        })
+        .reduce(IdentityScan::default, IdentityScan::merge);
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        let received = b"From: Friend <friend@example.com>\r\nTo: me@example.org\r\nDelivered-To: me@example.org\r\nDate: Mon, 1 Jan 2018 10:00:00 +0000\r\nSubject: Hi\r\n\r\nHello\r\n";
+        let sent = b"From: Me <alias@example.org>\r\nReturn-Path: <alias@example.org>\r\nTo: friend@example.com\r\nDate: Mon, 1 Jan 2018 11:00:00 +0000\r\nSubject: Re: Hi\r\n\r\nHello\r\n";
+        let scan = IdentityScan::default()
+            .scan(received, Path::new("archive/INBOX.mbox"), None)
+            .scan(sent, Path::new("archive/Sent Messages.mbox"), None);
+        let candidates = scan.into_candidates();
+        let addresses: Vec<&str> = candidates.iter().map(|c| c.address.as_str()).collect();
+        assert_eq!(addresses, vec!["alias@example.org", "me@example.org"]);
+        assert_eq!(candidates[0].sent_folder, 1);
+        assert_eq!(candidates[0].return_path, 1);
+        assert_eq!(candidates[1].delivered_to, 1);
+        assert_eq!(candidates[1].reasons(), "1 Delivered-To, 1 received");
+    }
+}
@@ -21,15 +21,27 @@
 
 use ps_core::eyre::Result;
 
+mod export;
 pub(crate) mod formats;
+mod identities;
+mod preview;
+mod probe;
+mod source;
+
+pub use export::{export_mails, MailExportFormat};
+pub use identities::{detect_identities, IdentityCandidate};
+pub use preview::load_preview;
+pub use probe::{probe_format, FormatCandidate, ProbedFormat};
+pub use source::read_message;
 
 use formats::{shared, ImporterFormat};
 
 use std::{path::PathBuf, thread::JoinHandle};
 
 use ps_core::{
-    crossbeam_channel::unbounded, Config, DatabaseLike, FormatType, Importerlike, Message,
-    MessageReceiver,
+    crossbeam_channel::{unbounded, Sender},
+    CancellationToken, Cancelled, Config, DBMessage, DatabaseLike, FormatType, ImportPhase,
+    Importerlike, Message, MessageReceiver, MessageSender,
 };
 
 pub struct Importer<Format: ImporterFormat> {
@@ -44,9 +56,10 @@ impl<Format: ImporterFormat + 'static> Importer<Format> {
 }
 
 impl<Format: ImporterFormat + 'static> Importerlike for Importer<Format> {
-    fn import<Database: DatabaseLike + 'static>(
+    fn cancellable_import<Database: DatabaseLike + 'static>(
         self,
         database: Database,
+        token: CancellationToken,
     ) -> Result<(MessageReceiver, JoinHandle<Result<()>>)> {
         let Importer { format, .. } = self;
         let (sender, receiver) = unbounded();
@@ -55,27 +68,119 @@ impl<Format: ImporterFormat + 'static> Importerlike for Importer<Format> {
         let handle: JoinHandle<Result<()>> = std::thread::spawn(move || {
             let outer_sender = sender.clone();
             let processed = move || {
-                let emails = format.emails(&config, sender.clone())?;
-                let processed =
-                    shared::database::into_database(&config, emails, sender.clone(), database)?;
+                sender.send(Message::Phase(ImportPhase::Discovering))?;
+                let emails = match format.emails(&config, sender.clone(), &token) {
+                    Ok(n) => n,
+                    Err(_) if token.is_cancelled() => {
+                        return shared::database::discard_database(database)
+                    }
+                    Err(e) => return Err(e),
+                };
+                let processed = shared::database::into_database(
+                    &config,
+                    emails,
+                    sender.clone(),
+                    database,
+                    &token,
+                )?;
 
                 Ok(processed)
             };
-            let result = processed();
-
-            // Send the error away and map it to a crossbeam channel error
-            match result {
-                Ok(_) => Ok(()),
-                Err(e) => match outer_sender.send(Message::Error(e)) {
-                    Ok(_) => Ok(()),
-                    Err(e) => Err(ps_core::eyre::Report::new(e)),
-                },
-            }
+            report(processed(), outer_sender)
         });
         Ok((receiver, handle))
     }
 }
 
+/// Imports all sources of a config (see [`Config::all_sources`]) one after
+/// the other into the same database.
+pub struct SourcesImporter {
+    config: Config,
+}
+
+impl Importerlike for SourcesImporter {
+    fn cancellable_import<Database: DatabaseLike + 'static>(
+        self,
+        database: Database,
+        token: CancellationToken,
+    ) -> Result<(MessageReceiver, JoinHandle<Result<()>>)> {
+        let (sender, receiver) = unbounded();
+
+        let config = self.config;
+        let handle: JoinHandle<Result<()>> = std::thread::spawn(move || {
+            let outer_sender = sender.clone();
+            let processed = move || {
+                let (db_sender, db_handle) = shared::database::begin_import(&config, database)?;
+                for source in config.all_sources() {
+                    let source_config = config.for_source(&source);
+                    match import_source(&source_config, &sender, &db_sender, &token) {
+                        Ok(()) => (),
+                        Err(_) if token.is_cancelled() => {
+                            return shared::database::discard(db_sender, db_handle)
+                        }
+                        Err(e) => return Err(e),
+                    }
+                }
+                shared::database::finish_import::<Database>(&config, sender, db_sender, db_handle)
+            };
+            report(processed(), outer_sender)
+        });
+        Ok((receiver, handle))
+    }
+}
+
+/// Read the mails of the single source in `config` and send them to the database
+fn import_source(
+    config: &Config,
+    tx: &MessageSender,
+    sender: &Sender<DBMessage>,
+    token: &CancellationToken,
+) -> Result<()> {
+    match config.format {
+        FormatType::AppleMail => {
+            read_source(formats::AppleMail::default(), config, tx, sender, token)
+        }
+        FormatType::GmailVault => read_source(formats::Gmail::default(), config, tx, sender, token),
+        FormatType::Mbox => read_source(formats::Mbox, config, tx, sender, token),
+        #[cfg(not(target_os = "windows"))]
+        FormatType::Maildir => read_source(formats::Maildir, config, tx, sender, token),
+        FormatType::Mh => read_source(formats::Mh, config, tx, sender, token),
+    }
+}
+
+fn read_source<Format: ImporterFormat>(
+    format: Format,
+    config: &Config,
+    tx: &MessageSender,
+    sender: &Sender<DBMessage>,
+    token: &CancellationToken,
+) -> Result<()>
+where
+    Format::Item: 'static,
+{
+    tx.send(Message::Phase(ImportPhase::Discovering))?;
+    let emails = format.emails(config, tx.clone(), token)?;
+    shared::database::send_emails(config, emails, tx, sender, token)
+}
+
+/// Send the error of a finished import away and map it to a crossbeam channel error
+fn report(result: Result<usize>, sender: MessageSender) -> Result<()> {
+    let message = match result {
+        Ok(_) => return Ok(()),
+        Err(e) if e.downcast_ref::<Cancelled>().is_some() => Message::Cancelled,
+        Err(e) => Message::Error(e),
+    };
+    match sender.send(message) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(ps_core::eyre::Report::new(e)),
+    }
+}
+
+/// Import every source of `config`, not just the one at `emails_folder_path`
+pub fn sources_importer(config: Config) -> SourcesImporter {
+    SourcesImporter { config }
+}
+
 pub fn gmail_importer(config: Config) -> Importer<formats::Gmail> {
     Importer::new(config, formats::Gmail::default())
 }
@@ -93,6 +198,10 @@ pub fn maildir_importer(config: Config) -> Importer<formats::Maildir> {
     Importer::new(config, formats::Maildir::default())
 }
 
+pub fn mh_importer(config: Config) -> Importer<formats::Mh> {
+    Importer::new(config, formats::Mh)
+}
+
 pub fn default_path(format: &FormatType) -> Option<PathBuf> {
     match format {
         FormatType::AppleMail => formats::AppleMail::default_path(),
@@ -100,5 +209,6 @@ pub fn default_path(format: &FormatType) -> Option<PathBuf> {
         FormatType::Mbox => formats::Mbox::default_path(),
         #[cfg(not(target_os = "windows"))]
         FormatType::Maildir => formats::Maildir::default_path(),
+        FormatType::Mh => formats::Mh::default_path(),
     }
 }
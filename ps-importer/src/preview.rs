@@ -0,0 +1,113 @@
+//! Load an `EmailPreview` for a single email from its original location.
+
+use mail_parser::{HeaderValue, Message, MessagePart, MimeHeaders};
+use ps_core::eyre::{eyre, Result};
+use ps_core::{Attachment, EmailPreview, FormatType, MailLocation};
+
+use super::formats::shared::parse::emaildatetime_to_chrono;
+use super::source::read_message;
+
+/// Read the email that was imported with the given `format` from `location`
+/// and return the parts that are relevant for displaying it.
+pub fn load_preview(format: &FormatType, location: &MailLocation) -> Result<EmailPreview> {
+    let data = read_message(format, location)?;
+    let message = Message::parse(&data)
+        .ok_or_else(|| eyre!("Could not parse email at {}", location.path.display()))?;
+
+    let mut headers = Vec::new();
+    let address_headers = [
+        ("From", message.get_from()),
+        ("To", message.get_to()),
+        ("Cc", message.get_cc()),
+        ("Reply-To", message.get_reply_to()),
+    ];
+    for (name, value) in address_headers {
+        if let Some(value) = header_to_string(value) {
+            headers.push((name.to_string(), value));
+        }
+    }
+    if let Some(date) = emaildatetime_to_chrono(message.get_date()) {
+        headers.push((
+            "Date".to_string(),
+            date.format("%a, %d %b %Y %H:%M:%S %z").to_string(),
+        ));
+    }
+    if let Some(subject) = message.get_subject() {
+        headers.push(("Subject".to_string(), subject.to_string()));
+    }
+
+    let body = (0..message.get_text_body_count())
+        .filter_map(|index| message.get_text_body(index))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let attachments = message.get_attachments().filter_map(attachment).collect();
+
+    Ok(EmailPreview {
+        headers,
+        body,
+        attachments,
+    })
+}
+
+fn attachment(part: &MessagePart) -> Option<Attachment> {
+    let (name, content_type, size) = match part {
+        MessagePart::Text(p) | MessagePart::Html(p) => {
+            (p.get_attachment_name(), p.get_content_type(), p.body.len())
+        }
+        MessagePart::Binary(p) | MessagePart::InlineBinary(p) => {
+            (p.get_attachment_name(), p.get_content_type(), p.body.len())
+        }
+        MessagePart::Message(p) => (p.get_attachment_name(), p.get_content_type(), 0),
+        MessagePart::Multipart(_) => return None,
+    };
+    let content_type = content_type
+        .map(|c| match c.get_subtype() {
+            Some(subtype) => format!("{}/{}", c.get_type(), subtype),
+            None => c.get_type().to_string(),
+        })
+        .unwrap_or_default();
+    Some(Attachment {
+        name: name.unwrap_or("Unnamed").to_string(),
+        content_type,
+        size,
+    })
+}
+
+/// Returns a readable representation of a header, or `None` for empty headers
+fn header_to_string(value: &HeaderValue) -> Option<String> {
+    let addr_to_string = |addr: &mail_parser::Addr| match (&addr.name, &addr.address) {
+        (Some(name), Some(address)) => format!("{} <{}>", name, address),
+        (None, Some(address)) => address.to_string(),
+        (Some(name), None) => name.to_string(),
+        (None, None) => String::new(),
+    };
+    let group_to_string = |group: &mail_parser::Group| {
+        let addresses: Vec<String> = group.addresses.iter().map(addr_to_string).collect();
+        match &group.name {
+            Some(name) => format!("{}: {}", name, addresses.join(", ")),
+            None => addresses.join(", "),
+        }
+    };
+    let result = match value {
+        HeaderValue::Address(addr) => addr_to_string(addr),
+        HeaderValue::AddressList(addrs) => {
+            let addrs: Vec<String> = addrs.iter().map(addr_to_string).collect();
+            addrs.join(", ")
+        }
+        HeaderValue::Group(group) => group_to_string(group),
+        HeaderValue::GroupList(groups) => {
+            let groups: Vec<String> = groups.iter().map(group_to_string).collect();
+            groups.join("; ")
+        }
+        HeaderValue::Text(text) => text.to_string(),
+        HeaderValue::TextList(texts) => texts.join(", "),
+        HeaderValue::DateTime(date) => date.to_string(),
+        HeaderValue::Collection(values) => {
+            let values: Vec<String> = values.iter().filter_map(header_to_string).collect();
+            values.join(", ")
+        }
+        HeaderValue::ContentType(_) | HeaderValue::Empty => return None,
+    };
+    Some(result)
+}
@@ -0,0 +1,218 @@
+//! Guess the format of a folder (or file) of mails before importing it.
+//!
+//! Only the names and the first bytes of the files are inspected:
+//! - mbox: files starting with `From `
+//! - Maildir: folders with `cur` and `new` sub folders
+//! - Apple Mail: `.emlx` files
+//! - Gmail Vault: `.eml` files, ideally with a `.meta` JSON file next to them
+//! - Outlook: `.pst` files with the `!BDN` magic bytes. These can't be imported.
+
+use ps_core::eyre::{bail, Result};
+use ps_core::tracing;
+use walkdir::WalkDir;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use ps_core::FormatType;
+
+/// How deep into the folder hierarchy we look
+const MAX_DEPTH: usize = 6;
+
+/// At most this many files and folders are inspected
+const MAX_ENTRIES: usize = 5000;
+
+/// The amount of bytes read from the start of each file
+const PROBE_LENGTH: usize = 1024;
+
+/// The magic bytes at the start of a PST file
+const PST_MAGIC: &[u8] = b"!BDN";
+
+/// A format found by [`probe_format`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProbedFormat {
+    Supported(FormatType),
+    /// An Outlook data file, which can't be imported
+    Pst,
+}
+
+impl ProbedFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProbedFormat::Supported(format) => format.name(),
+            ProbedFormat::Pst => "Outlook PST",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatCandidate {
+    pub format: ProbedFormat,
+    /// Between 0 and 1
+    pub confidence: f32,
+    /// What was found, e.g. `12 files starting with "From "`
+    pub reason: String,
+}
+
+impl FormatCandidate {
+    /// The format, if it can be imported
+    pub fn supported(&self) -> Option<FormatType> {
+        match self.format {
+            ProbedFormat::Supported(format) => Some(format),
+            ProbedFormat::Pst => None,
+        }
+    }
+}
+
+/// The evidence for one format
+#[derive(Debug, Default)]
+struct Evidence {
+    /// The amount of matching files or folders
+    count: usize,
+    /// The strength of the best hint
+    strength: f32,
+    reason: String,
+}
+
+impl Evidence {
+    fn add(&mut self, strength: f32, reason: &str) {
+        self.count += 1;
+        if strength > self.strength {
+            self.strength = strength;
+            self.reason = reason.to_string();
+        }
+    }
+}
+
+/// Inspect `path` and return the formats it might contain, the most likely first.
+/// Returns an empty list if nothing looks like mail.
+pub fn probe_format(path: &Path) -> Result<Vec<FormatCandidate>> {
+    if !path.exists() {
+        bail!("{} does not exist", path.display());
+    }
+    let mut evidence: HashMap<ProbedFormat, Evidence> = HashMap::new();
+    let mut add = |format: ProbedFormat, strength: f32, reason: &str| {
+        evidence.entry(format).or_default().add(strength, reason)
+    };
+    for entry in WalkDir::new(path)
+        .max_depth(MAX_DEPTH)
+        .into_iter()
+        .take(MAX_ENTRIES)
+    {
+        let entry = match entry {
+            Ok(n) => n,
+            Err(e) => {
+                tracing::info!("Could not probe: {}", e);
+                continue;
+            }
+        };
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        if entry.file_type().is_dir() {
+            #[cfg(not(target_os = "windows"))]
+            if path.join("cur").is_dir() && path.join("new").is_dir() {
+                add(
+                    ProbedFormat::Supported(FormatType::Maildir),
+                    0.9,
+                    "folders with cur and new sub folders",
+                );
+            }
+            continue;
+        }
+        // The mails inside of a Maildir were already counted with their folder
+        let parent = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str());
+        if matches!(parent, Some("cur" | "new" | "tmp")) {
+            continue;
+        }
+        if name == ".mh_sequences" {
+            add(
+                ProbedFormat::Supported(FormatType::Mh),
+                0.9,
+                "MH folders with .mh_sequences",
+            );
+        } else if !name.is_empty() && name.chars().all(|c| c.is_ascii_digit()) {
+            add(
+                ProbedFormat::Supported(FormatType::Mh),
+                0.5,
+                "numbered mail files",
+            );
+        } else if name.ends_with(".emlx") {
+            add(
+                ProbedFormat::Supported(FormatType::AppleMail),
+                0.9,
+                ".emlx files",
+            );
+        } else if name.ends_with(".eml") || name.ends_with(".eml.gz") {
+            let stem = name.trim_end_matches(".gz").trim_end_matches(".eml");
+            let meta = path.with_file_name(format!("{}.meta", stem));
+            match starts_with(&meta, b"{") {
+                true => add(
+                    ProbedFormat::Supported(FormatType::GmailVault),
+                    0.9,
+                    ".eml files with Gmail meta data",
+                ),
+                false => add(
+                    ProbedFormat::Supported(FormatType::GmailVault),
+                    0.5,
+                    ".eml files",
+                ),
+            }
+        } else if starts_with(path, PST_MAGIC) {
+            add(ProbedFormat::Pst, 0.9, ".pst files");
+        } else if starts_with(path, b"From ") {
+            // The mbox importer reads files with `.mbox` in their path and files without extension
+            if path.to_string_lossy().contains(".mbox") {
+                add(
+                    ProbedFormat::Supported(FormatType::Mbox),
+                    0.9,
+                    ".mbox files",
+                );
+            } else if path.extension().is_none() {
+                add(
+                    ProbedFormat::Supported(FormatType::Mbox),
+                    0.8,
+                    "files starting with \"From \"",
+                );
+            } else {
+                add(
+                    ProbedFormat::Supported(FormatType::Mbox),
+                    0.4,
+                    "files starting with \"From \" (remove their extension to import them)",
+                );
+            }
+        }
+    }
+
+    // Weigh each format by its share of all hints
+    let total: usize = evidence.values().map(|e| e.count).sum();
+    let mut candidates: Vec<FormatCandidate> = evidence
+        .into_iter()
+        .map(|(format, evidence)| FormatCandidate {
+            format,
+            confidence: evidence.strength * evidence.count as f32 / total as f32,
+            reason: format!("{} {}", evidence.count, evidence.reason),
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    Ok(candidates)
+}
+
+/// Does the file at `path` start with `prefix`? Leading whitespace is ignored.
+fn starts_with(path: &Path, prefix: &[u8]) -> bool {
+    let mut buffer = Vec::with_capacity(PROBE_LENGTH);
+    let read =
+        File::open(path).and_then(|file| file.take(PROBE_LENGTH as u64).read_to_end(&mut buffer));
+    if read.is_err() {
+        return false;
+    }
+    let start = buffer
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(buffer.len());
+    buffer[start..].starts_with(prefix)
+}
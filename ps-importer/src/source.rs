@@ -0,0 +1,66 @@
+//! Access to the original data of a single imported email.
+
+use flate2::read::GzDecoder;
+use ps_core::eyre::{bail, Result};
+use ps_core::{FormatType, MailLocation};
+
+use std::io::{Read, Seek, SeekFrom};
+
+/// The amount of bytes at the beginning of an mbox that are used to detect its variant
+const MBOX_DETECT_LENGTH: u64 = 1024 * 1024;
+
+use super::formats::{read_emlx, MboxVariant};
+
+/// Read the raw message data of an email that was imported
+/// with the given `format` from `location`.
+pub fn read_message(format: &FormatType, location: &MailLocation) -> Result<Vec<u8>> {
+    let path = location.path.as_path();
+    match format {
+        FormatType::AppleMail => {
+            let (message, _) = read_emlx(path)?;
+            Ok(message)
+        }
+        FormatType::GmailVault if path.extension().map(|e| e == "gz").unwrap_or(false) => {
+            let reader = std::fs::File::open(path)?;
+            let mut decoder = GzDecoder::new(reader);
+            let mut buffer = Vec::new();
+            decoder.read_to_end(&mut buffer)?;
+            Ok(buffer)
+        }
+        FormatType::Mbox => {
+            let (offset, length) = match location.mbox_location {
+                Some(n) => n,
+                None => bail!(
+                    "Missing location of the mail in {}. Please import the mbox again",
+                    path.display()
+                ),
+            };
+            let mut file = std::fs::File::open(path)?;
+            // The quoting of `From ` lines depends on the variant of the whole mbox
+            let mut start = Vec::new();
+            (&mut file)
+                .take(MBOX_DETECT_LENGTH)
+                .read_to_end(&mut start)?;
+            let variant = MboxVariant::detect(&start);
+            file.seek(SeekFrom::Start(offset as u64))?;
+            let mut buffer = vec![0; length];
+            file.read_exact(&mut buffer)?;
+            Ok(variant.unescape(&buffer).into_owned())
+        }
+        _ => Ok(std::fs::read(path)?),
+    }
+}
+
+/// Quote lines that start with `From ` the way `mboxrd` does.
+/// Used when writing mbox files.
+pub(crate) fn escape_from_lines(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len());
+    for line in data.split_inclusive(|b| *b == b'\n') {
+        let quotes = line.iter().take_while(|b| **b == b'>').count();
+        if line[quotes..].starts_with(b"From ") {
+            output.push(b'>');
+        }
+        output.extend_from_slice(line);
+    }
+    output
+}